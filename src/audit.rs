@@ -0,0 +1,79 @@
+use sha2::{Digest, Sha256};
+
+use crate::json::{self, JSONError, JSONValue};
+
+/// Hashes the elements of `array` into a chain starting from `prev`: each element's hash is
+/// SHA-256 over the hash before it followed by the element's canonical form, as
+/// `to_canonical_string` writes it. The hash of the last element is the head of the chain, so
+/// appending to a log only takes its current head and the new elements.
+///
+/// Returns:
+/// - `Err(ValueError)` if `array` is not a json `Array`, or if an element holds a NaN or infinite
+///   number, which has no canonical form,
+/// - `Ok((head, hashes))` with the head of the chain and the hash of each element otherwise. An
+///   empty array gives `prev` back as the head.
+pub fn chain_digest(array: &JSONValue, prev: [u8; 32]) -> json::Result<([u8; 32], Vec<[u8; 32]>)> {
+    let items = elements(array)?;
+    let mut hashes = Vec::with_capacity(items.len());
+    let mut head = prev;
+    for item in items {
+        head = link(&head, item)?;
+        hashes.push(head);
+    }
+    Ok((head, hashes))
+}
+
+/// # VerifyReport
+///
+/// The outcome of [`verify_chain`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VerifyReport {
+    /// The head the elements of the array chain to
+    pub head: [u8; 32],
+    /// The index of the first element whose hash differs from the one recorded for it, or of the
+    /// first element that is missing or has no recorded hash
+    pub first_invalid: Option<usize>,
+}
+
+impl VerifyReport {
+    /// Whether every element matched its recorded hash.
+    pub fn is_valid(&self) -> bool {
+        self.first_invalid.is_none()
+    }
+}
+
+/// Checks the elements of `array` against the `expected` hashes that [`chain_digest`] returned
+/// for them, chaining from `seed`. A head alone can only say that something changed, so the
+/// hashes of every element are needed to find where; the last of them is the expected head.
+///
+/// Returns:
+/// - the errors `chain_digest` returns,
+/// - `Ok(VerifyReport)` naming the first element that doesn't match otherwise.
+pub fn verify_chain(array: &JSONValue, seed: [u8; 32], expected: &[[u8; 32]]) -> json::Result<VerifyReport> {
+    let items = elements(array)?;
+    let mut head = seed;
+    let mut first_invalid = None;
+    for (i, item) in items.iter().enumerate() {
+        head = link(&head, item)?;
+        if first_invalid.is_none() && expected.get(i) != Some(&head) {
+            first_invalid = Some(i);
+        }
+    }
+    // elements that were removed from the end
+    if first_invalid.is_none() && expected.len() > items.len() {
+        first_invalid = Some(items.len());
+    }
+    Ok(VerifyReport { head, first_invalid })
+}
+
+fn elements(array: &JSONValue) -> json::Result<&Vec<JSONValue>> {
+    array.as_array().ok_or_else(|| JSONError::ValueError(format!("expected array, found {:?}", array.json_type().as_str())))
+}
+
+/// The hash of `item` following `prev` in a chain
+fn link(prev: &[u8; 32], item: &JSONValue) -> json::Result<[u8; 32]> {
+    let mut hasher = Sha256::new();
+    hasher.update(prev);
+    hasher.update(item.to_canonical_string()?.as_bytes());
+    Ok(hasher.finalize().into())
+}
@@ -0,0 +1,124 @@
+use crate::format::{non_finite_error, write_string, FormatOptions};
+use crate::json::{self, JSONValue};
+
+impl JSONValue {
+    /// Serializes this value in the canonical form of RFC 8785 (the JSON Canonicalization
+    /// Scheme), so that equal values always give the same bytes, e.g. for signing:
+    /// - no whitespace between tokens,
+    /// - object keys sorted by their UTF-16 code units,
+    /// - numbers written as ECMAScript's `Number.prototype.toString` writes them,
+    /// - strings with only quotes, backslashes and control characters escaped.
+    ///
+    /// Returns `Err(ValueError)` if a number is NaN or infinite, which have no canonical form.
+    pub fn to_canonical_string(&self) -> json::Result<String> {
+        let mut out = String::new();
+        write_canonical(&mut out, self)?;
+        Ok(out)
+    }
+}
+
+fn write_canonical(out: &mut String, value: &JSONValue) -> json::Result<()> {
+    match value {
+        JSONValue::Null => out.push_str("null"),
+        JSONValue::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        JSONValue::Number(n) if !n.is_finite() => return Err(non_finite_error(*n)),
+        JSONValue::Number(n) => write_number(out, *n),
+        // writing to a String never fails, and the default options escape only what JSON requires,
+        // as RFC 8785 asks
+        JSONValue::String(s) => {
+            let _ = write_string(out, s, &FormatOptions::compact());
+        }
+        JSONValue::Array(arr) => {
+            out.push('[');
+            for (i, item) in arr.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(out, item)?;
+            }
+            out.push(']');
+        }
+        JSONValue::Object(obj) => {
+            let mut entries: Vec<_> = obj.iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+            out.push('{');
+            for (i, (key, item)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                let _ = write_string(out, key, &FormatOptions::compact());
+                out.push(':');
+                write_canonical(out, item)?;
+            }
+            out.push('}');
+        }
+    }
+    Ok(())
+}
+
+/// Writes the finite number `n` following ECMAScript's Number::toString: the shortest digits that
+/// round-trip, in plain notation from 1e-6 up to 1e21 and with an exponent beyond.
+fn write_number(out: &mut String, n: f64) {
+    // covers -0 too, which is written as `0`
+    if n == 0.0 {
+        out.push('0');
+        return;
+    }
+    if n < 0.0 {
+        out.push('-');
+    }
+    // `{:e}` gives the shortest round-trip digits as `d.ddde<exponent>`
+    let sci = format!("{:e}", n.abs());
+    let (mantissa, exponent) = sci.split_once('e').unwrap();
+    let exponent = exponent.parse::<i32>().unwrap();
+    let mut digits = mantissa.replace('.', "");
+    if let Some(even) = even_tie(&digits, exponent, n.abs()) {
+        digits = even;
+    }
+    let k = digits.len() as i32;
+    // the value is 0.<digits> * 10^n
+    let n = exponent + 1;
+
+    if k <= n && n <= 21 {
+        out.push_str(&digits);
+        out.extend(std::iter::repeat_n('0', (n - k) as usize));
+    } else if 0 < n && n <= 21 {
+        out.push_str(&digits[..n as usize]);
+        out.push('.');
+        out.push_str(&digits[n as usize..]);
+    } else if -6 < n && n <= 0 {
+        out.push_str("0.");
+        out.extend(std::iter::repeat_n('0', -n as usize));
+        out.push_str(&digits);
+    } else {
+        out.push_str(&digits[..1]);
+        if k > 1 {
+            out.push('.');
+            out.push_str(&digits[1..]);
+        }
+        out.push('e');
+        out.push(if n > 0 { '+' } else { '-' });
+        out.push_str(&(n - 1).abs().to_string());
+    }
+}
+
+/// When `x` lies exactly halfway between the shortest `digits` and the ones below them, which
+/// round-trip just as well, returns the lower ones if they end in an even digit. ECMAScript breaks
+/// such ties to even where `{:e}` rounds up.
+fn even_tie(digits: &str, exponent: i32, x: f64) -> Option<String> {
+    let (head, last) = digits.split_at(digits.len() - 1);
+    let last = last.as_bytes()[0];
+    if last % 2 == 0 {
+        return None;
+    }
+    let lower = format!("{}{}", head, (last - 1) as char);
+    if format!("{}e{}", lower, exponent + 1 - lower.len() as i32).parse::<f64>() != Ok(x) {
+        return None;
+    }
+    // every f64 has fewer than 800 significant digits, so this is exact
+    let exact = format!("{:.800e}", x);
+    let (exact, _) = exact.split_once('e')?;
+    let exact = exact.replace('.', "");
+    let rest = exact.strip_prefix(&lower)?.strip_prefix('5')?;
+    rest.bytes().all(|b| b == b'0').then_some(lower)
+}
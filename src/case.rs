@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+
+use crate::json::{self, JSONError, JSONValue, Map};
+
+/// A naming convention for object keys, for [`JSONValue::rename_keys_recursive`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyCase {
+    /// `userId`
+    CamelCase,
+    /// `user_id`
+    SnakeCase,
+    /// `user-id`
+    KebabCase,
+}
+
+impl KeyCase {
+    /// Converts `key` to this case. Words are split at underscores, hyphens and spaces, where a
+    /// lowercase letter or digit is followed by an uppercase letter, and before the last letter of
+    /// a run of capitals that goes on in lowercase, so `userID`, `HTTPServer` and `user-id` come
+    /// out in snake case as `user_id`, `http_server` and `user_id`. Underscores and hyphens at the
+    /// start of `key`, as in `_id`, are kept as they are.
+    pub fn convert(&self, key: &str) -> String {
+        let body = key.trim_start_matches(['_', '-']);
+        let mut out = key[..key.len() - body.len()].to_string();
+        for (i, word) in words(body).iter().enumerate() {
+            match self {
+                KeyCase::CamelCase if i > 0 => {
+                    let mut chars = word.chars();
+                    out.extend(chars.next().into_iter().flat_map(char::to_uppercase));
+                    out.extend(chars.flat_map(char::to_lowercase));
+                }
+                KeyCase::CamelCase => out.extend(word.chars().flat_map(char::to_lowercase)),
+                KeyCase::SnakeCase | KeyCase::KebabCase => {
+                    if i > 0 {
+                        out.push(if *self == KeyCase::SnakeCase { '_' } else { '-' });
+                    }
+                    out.extend(word.chars().flat_map(char::to_lowercase));
+                }
+            }
+        }
+        out
+    }
+}
+
+/// Splits `key` into the words `KeyCase::convert` puts back together
+fn words(key: &str) -> Vec<&str> {
+    let mut words = vec![];
+    let chars: Vec<(usize, char)> = key.char_indices().collect();
+    let mut start = None;
+    for (n, &(i, c)) in chars.iter().enumerate() {
+        if matches!(c, '_' | '-' | ' ') {
+            if let Some(start) = start.take() {
+                words.push(&key[start..i]);
+            }
+            continue;
+        }
+        let boundary = match (n.checked_sub(1).map(|n| chars[n].1), chars.get(n + 1).map(|&(_, c)| c)) {
+            (Some(prev), _) if c.is_uppercase() && (prev.is_lowercase() || prev.is_ascii_digit()) => true,
+            (Some(prev), Some(next)) => c.is_uppercase() && prev.is_uppercase() && next.is_lowercase(),
+            _ => false,
+        };
+        match start {
+            Some(word) if boundary => {
+                words.push(&key[word..i]);
+                start = Some(i);
+            }
+            Some(_) => {}
+            None => start = Some(i),
+        }
+    }
+    if let Some(start) = start {
+        words.push(&key[start..]);
+    }
+    words
+}
+
+impl JSONValue {
+    /// Converts every object key in this value, at any depth and inside arrays too, to `style`,
+    /// keeping the entries in their order and leaving everything else as it is.
+    ///
+    /// Returns `Err(KeyError)` naming both keys if two keys of the same object convert to the same
+    /// one, e.g. `userId` and `user_id` in snake case, in which case nothing is renamed.
+    pub fn rename_keys_recursive(&mut self, style: KeyCase) -> json::Result<()> {
+        check_collisions(self, style)?;
+        rename(self, style);
+        Ok(())
+    }
+}
+
+fn check_collisions(value: &JSONValue, style: KeyCase) -> json::Result<()> {
+    match value {
+        JSONValue::Array(items) => items.iter().try_for_each(|item| check_collisions(item, style)),
+        JSONValue::Object(map) => {
+            let mut renamed = HashMap::with_capacity(map.len());
+            for (key, value) in map.iter() {
+                if let Some(other) = renamed.insert(style.convert(key), key) {
+                    return Err(JSONError::KeyError(format!(
+                        "keys {} and {} both become {}",
+                        other,
+                        key,
+                        style.convert(key)
+                    )));
+                }
+                check_collisions(value, style)?;
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+fn rename(value: &mut JSONValue, style: KeyCase) {
+    match value {
+        JSONValue::Array(items) => items.iter_mut().for_each(|item| rename(item, style)),
+        JSONValue::Object(map) => {
+            *map = std::mem::replace(map, Map::new())
+                .into_iter()
+                .map(|(key, mut value)| {
+                    rename(&mut value, style);
+                    (style.convert(&key), value)
+                })
+                .collect();
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,119 @@
+//! # compat
+//!
+//! Thin wrappers that keep the behaviors of the crate's first releases, for code that upgrades a
+//! piece at a time. Each one stands in for an API whose behavior has since changed:
+//!
+//! - [`Cast`] converts numbers with `as` again, where `json::Cast` now rejects numbers that are
+//!   out of range or not integral,
+//! - [`to_string_legacy`] writes a value the way `Display` once did, where it now escapes
+//!   strings and keys and writes empty containers as `[]` and `{}`,
+//! - [`ObjectMap`] names the `HashMap` that `JSONValue::Object` once held, where it now holds a
+//!   [`Map`] that keeps its keys in order,
+//! - [`from_slice_legacy`] parses the first value of a buffer and ignores what follows it, where
+//!   `JSONValue::from_slice` now reports anything after the document as an error.
+//!
+//! Errors carry a position and come in more kinds than before; `JSONError::message` gives the
+//! text the old tuple variants held, and matching them needs `{ message, .. }` patterns.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::json::{self, CastLossy, JSONValue, Lexer, Map, Parser};
+
+/// The type `JSONValue::Object` held before objects kept their keys in order. Converts to and
+/// from a [`Map`] with `Map::from` and [`into_hash_map`].
+pub type ObjectMap = HashMap<String, JSONValue>;
+
+/// Moves the entries of `map` into an [`ObjectMap`], losing their order.
+pub fn into_hash_map(map: Map) -> ObjectMap {
+    map.into_iter().collect()
+}
+
+/// The old, lossy `Cast`: integers saturate at the bounds of their type and drop any fraction,
+/// and an `f32` may round to infinity. Import it in place of `json::Cast`; the two can't both be
+/// in scope for the same call.
+pub trait Cast<T> {
+    fn cast(&self) -> json::Result<T>;
+}
+
+impl Cast<bool> for JSONValue {
+    fn cast(&self) -> json::Result<bool> {
+        json::Cast::cast(self)
+    }
+}
+
+impl Cast<String> for JSONValue {
+    fn cast(&self) -> json::Result<String> {
+        json::Cast::cast(self)
+    }
+}
+
+macro_rules! impl_cast_legacy {
+    {$($type_name:ty) +} => {
+        $(impl Cast<$type_name> for JSONValue {
+            fn cast(&self) -> json::Result<$type_name> {
+                CastLossy::cast_lossy(self)
+            }
+        })+
+    }
+}
+
+impl_cast_legacy!(i8 i16 i32 i64 i128 isize u8 u16 u32 u64 u128 usize f32 f64);
+
+/// Writes `value` exactly as `Display` did before it escaped its output: strings and keys go
+/// between quotes as they are, numbers are written with `f64`'s `Display` (so NaN is `NaN`),
+/// and arrays and objects put each element on its own line indented by 4 spaces per level,
+/// including empty ones, which come out as `[\n]` and `{\n}`. Object keys come in the [`Map`]'s
+/// order, where they once came in the `HashMap`'s.
+pub fn to_string_legacy(value: &JSONValue) -> String {
+    let mut out = String::new();
+    write_legacy(&mut out, value, 0);
+    out
+}
+
+fn write_legacy(out: &mut String, value: &JSONValue, level: usize) {
+    let indent = " ".repeat(level * 4);
+    // writing to a String never fails
+    match value {
+        JSONValue::Bool(b) => write!(out, "{}", b).unwrap(),
+        JSONValue::Number(n) => write!(out, "{}", n).unwrap(),
+        JSONValue::String(s) => write!(out, "\"{}\"", s).unwrap(),
+        JSONValue::Array(arr) => {
+            out.push_str("[\n");
+            for (i, item) in arr.iter().enumerate() {
+                write!(out, "    {}", indent).unwrap();
+                write_legacy(out, item, level + 1);
+                if i != arr.len() - 1 {
+                    out.push(',');
+                }
+                out.push('\n');
+            }
+            write!(out, "{}]", indent).unwrap();
+        }
+        JSONValue::Object(obj) => {
+            out.push_str("{\n");
+            for (i, (key, item)) in obj.iter().enumerate() {
+                write!(out, "    {}\"{}\": ", indent, key).unwrap();
+                write_legacy(out, item, level + 1);
+                if i != obj.len() - 1 {
+                    out.push(',');
+                }
+                out.push('\n');
+            }
+            write!(out, "{}}}", indent).unwrap();
+        }
+        JSONValue::Null => out.push_str("null"),
+    }
+}
+
+/// Parses the first value in `bytes` and ignores any tokens after it, as `JSONValue::try_from`
+/// did before it required the document to end there. The whole buffer is still lexed first, so
+/// text that can't be lexed is an error wherever it is.
+///
+/// Returns:
+/// - any error the lexer returns for `bytes`,
+/// - any error `JSONValue::from_slice` would return for the first value,
+/// - `Ok(JSONValue)` with the first value otherwise.
+pub fn from_slice_legacy(bytes: &[u8]) -> json::Result<JSONValue> {
+    Parser::from(Lexer::new(bytes).tokenify()?).parse()
+}
@@ -0,0 +1,134 @@
+use crate::json::JSONValue;
+
+/// # DisplayOptions
+///
+/// Settings for [`JSONValue::to_display_string`], which renders a value as a human-readable
+/// outline. This is **not** a JSON serializer; use `to_string()` for that.
+#[derive(Clone, Debug)]
+pub struct DisplayOptions {
+    /// Character inserted between groups of three integer digits, e.g. `Some(',')` for `1,234`.
+    pub thousands_separator: Option<char>,
+    /// Character placed between the integer and fractional digits of a number.
+    pub decimal_separator: char,
+    /// Fixed number of digits printed after the decimal separator. `None` prints the shortest
+    /// representation of the number.
+    pub decimal_places: Option<usize>,
+    /// Text printed in place of a JSON `null`.
+    pub null_placeholder: String,
+    /// Number of spaces each nesting level is indented by.
+    pub indent: usize,
+}
+
+impl Default for DisplayOptions {
+    fn default() -> Self {
+        Self {
+            thousands_separator: Some(','),
+            decimal_separator: '.',
+            decimal_places: None,
+            null_placeholder: "—".to_string(),
+            indent: 2,
+        }
+    }
+}
+
+impl JSONValue {
+    /// Renders this value for humans, e.g. in logs or CLI reports.
+    ///
+    /// The output is **not valid JSON** and cannot be parsed back: strings are unquoted, numbers
+    /// are formatted according to `opts`, nulls become `opts.null_placeholder`, and objects and
+    /// arrays are laid out as an indented outline (`key: value` lines and `- item` lines).
+    /// Object keys are listed in sorted order.
+    pub fn to_display_string(&self, opts: DisplayOptions) -> String {
+        let mut out = String::new();
+        match self {
+            Self::Array(_) | Self::Object(_) if !is_empty_container(self) => {
+                display_recursive(self, &opts, 0, &mut out);
+            }
+            scalar => out.push_str(&display_scalar(scalar, &opts)),
+        }
+        out
+    }
+}
+
+fn is_empty_container(value: &JSONValue) -> bool {
+    match value {
+        JSONValue::Array(arr) => arr.is_empty(),
+        JSONValue::Object(obj) => obj.is_empty(),
+        _ => false,
+    }
+}
+
+// appends one line per entry of a non-empty container, indented by `level`
+fn display_recursive(value: &JSONValue, opts: &DisplayOptions, level: usize, out: &mut String) {
+    let pad = " ".repeat(level * opts.indent);
+    let write_entry = |label: String, child: &JSONValue, out: &mut String| {
+        match child {
+            JSONValue::Array(_) | JSONValue::Object(_) if !is_empty_container(child) => {
+                out.push_str(&format!("{pad}{label}\n"));
+                display_recursive(child, opts, level + 1, out);
+            }
+            scalar => out.push_str(&format!("{pad}{label} {}\n", display_scalar(scalar, opts))),
+        }
+    };
+
+    match value {
+        JSONValue::Array(arr) => {
+            for child in arr {
+                write_entry("-".to_string(), child, out);
+            }
+        }
+        JSONValue::Object(obj) => {
+            let mut entries: Vec<_> = obj.iter().collect();
+            entries.sort_by_key(|&(key, _)| key);
+            for (key, child) in entries {
+                write_entry(format!("{key}:"), child, out);
+            }
+        }
+        _ => unreachable!("display_recursive is only called on containers"),
+    }
+}
+
+fn display_scalar(value: &JSONValue, opts: &DisplayOptions) -> String {
+    match value {
+        JSONValue::Bool(b) => b.to_string(),
+        JSONValue::Number(n) => display_number(*n, opts),
+        JSONValue::String(s) => s.clone(),
+        JSONValue::Null => opts.null_placeholder.clone(),
+        // only empty containers reach this point
+        JSONValue::Array(_) | JSONValue::Object(_) => "(empty)".to_string(),
+    }
+}
+
+fn display_number(n: f64, opts: &DisplayOptions) -> String {
+    if !n.is_finite() {
+        return n.to_string();
+    }
+
+    let raw = match opts.decimal_places {
+        Some(places) => format!("{:.*}", places, n.abs()),
+        None => n.abs().to_string(),
+    };
+    let (int_part, frac_part) = match raw.split_once('.') {
+        Some((i, f)) => (i, Some(f)),
+        None => (raw.as_str(), None),
+    };
+
+    let mut out = String::new();
+    if n.is_sign_negative() && raw.bytes().any(|b| b.is_ascii_digit() && b != b'0') {
+        out.push('-');
+    }
+    for (i, digit) in int_part.chars().enumerate() {
+        if i > 0 && (int_part.len() - i) % 3 == 0 {
+            if let Some(sep) = opts.thousands_separator {
+                out.push(sep);
+            }
+        }
+        out.push(digit);
+    }
+    if let Some(frac) = frac_part {
+        out.push(opts.decimal_separator);
+        out.push_str(frac);
+    }
+
+    out
+}
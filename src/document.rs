@@ -0,0 +1,216 @@
+use std::fmt::Display;
+use std::ops::Range;
+
+use crate::events::{Event, EventMachine};
+use crate::format::FormatOptions;
+use crate::json::{self, JSONType, JSONValue, Options, Position};
+use crate::lexer::Lexer;
+use crate::pointer;
+
+/// # Document
+///
+/// A JSON document that is edited in place, keeping its text as it was written: comments,
+/// whitespace, key order and number spellings all stay untouched, except for the values that are
+/// replaced. This is what rewriting a hand-maintained configuration file needs, where a round
+/// trip through `JSONValue` would lose everything but the data.
+///
+/// Documents may contain `//` and `/* */` comments and trailing commas. Printing a document with
+/// `Display` gives back its text.
+#[derive(Clone, Debug)]
+pub struct Document {
+    source: String,
+    root: Node,
+}
+
+/// A value in the source text, by the bytes it spans. The text between nodes, i.e. whitespace,
+/// comments and punctuation, belongs to the nodes around it and is never touched.
+#[derive(Clone, Debug)]
+struct Node {
+    span: Range<usize>,
+    kind: Kind,
+}
+
+#[derive(Clone, Debug)]
+enum Kind {
+    Scalar(JSONType),
+    Array(Vec<Node>),
+    /// The members, with their decoded keys, in the order they are written
+    Object(Vec<(String, Node)>),
+}
+
+impl Document {
+    /// Parses `text`, which may contain comments and trailing commas.
+    ///
+    /// Returns any error `JSONValue::parse_with` returns for `text` with those extensions allowed.
+    pub fn parse(text: &str) -> json::Result<Self> {
+        Ok(Self { source: text.to_string(), root: node(text)? })
+    }
+
+    /// The text of the document, with every edit made so far.
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// The value `ptr`, an RFC 6901 JSON pointer, leads to.
+    ///
+    /// Errors are the same as those of [`json::get_path_raw`] on the document's text.
+    pub fn get_path(&self, ptr: &str) -> json::Result<JSONValue> {
+        let node = self.locate(ptr)?.into_iter().fold(&self.root, Node::child);
+        JSONValue::parse_with(self.source[node.span.clone()].as_bytes(), &options())
+    }
+
+    /// Replaces the value `ptr` leads to with `value`, written compactly in place of the old
+    /// value's text. Everything around it is left as it was.
+    ///
+    /// Returns:
+    /// - the errors [`json::get_path_raw`] would return if `ptr` doesn't lead to a value,
+    /// - `Err(ValueError)` if `value` holds a NaN or infinite number,
+    /// - `Ok(())` otherwise.
+    pub fn set_path(&mut self, ptr: &str, value: JSONValue) -> json::Result<()> {
+        let path = self.locate(ptr)?;
+        let span = path.iter().fold(&self.root, |node, &i| node.child(i)).span.clone();
+        let text = value.try_to_string_with(&FormatOptions::compact())?;
+        // only the new text is parsed; the nodes after it just move
+        let mut replacement = node(&text)?;
+        replacement.shift(0, 0, span.start);
+        self.source.replace_range(span.clone(), &text);
+        self.root.shift(span.end, span.len(), text.len());
+        *path.into_iter().fold(&mut self.root, Node::child_mut) = replacement;
+        Ok(())
+    }
+
+    /// The positions among their siblings of the nodes `ptr` leads through, from the root down.
+    /// Errors are those of looking `ptr` up in the parsed value.
+    fn locate(&self, ptr: &str) -> json::Result<Vec<usize>> {
+        let mut path = vec![];
+        let mut curr = &self.root;
+        for (token, at) in pointer::segments(ptr)? {
+            let i = match &curr.kind {
+                Kind::Array(items) => {
+                    let index = pointer::index(&token, at)?;
+                    if index >= items.len() {
+                        return Err(pointer::out_of_bounds(index, items.len(), at));
+                    }
+                    index
+                }
+                // the last of repeated keys is the one the parsed value keeps
+                Kind::Object(members) => members.iter().rposition(|(key, _)| *key == token)
+                    .ok_or_else(|| pointer::missing_key(&token, at))?,
+                Kind::Scalar(json_type) => return Err(pointer::not_a_container(json_type.as_str(), at)),
+            };
+            curr = curr.child(i);
+            path.push(i);
+        }
+        Ok(path)
+    }
+}
+
+impl Node {
+    fn child(&self, i: usize) -> &Node {
+        match &self.kind {
+            Kind::Array(items) => &items[i],
+            Kind::Object(members) => &members[i].1,
+            Kind::Scalar(_) => unreachable!("scalars have no children"),
+        }
+    }
+
+    fn child_mut(&mut self, i: usize) -> &mut Node {
+        match &mut self.kind {
+            Kind::Array(items) => &mut items[i],
+            Kind::Object(members) => &mut members[i].1,
+            Kind::Scalar(_) => unreachable!("scalars have no children"),
+        }
+    }
+
+    /// Moves every offset from `at` on by the change in length when `removed` bytes before `at`
+    /// are replaced with `added` bytes.
+    fn shift(&mut self, at: usize, removed: usize, added: usize) {
+        for offset in [&mut self.span.start, &mut self.span.end] {
+            if *offset >= at {
+                *offset = *offset + added - removed;
+            }
+        }
+        match &mut self.kind {
+            Kind::Array(items) => items.iter_mut().for_each(|item| item.shift(at, removed, added)),
+            Kind::Object(members) => members.iter_mut().for_each(|(_, node)| node.shift(at, removed, added)),
+            Kind::Scalar(_) => {}
+        }
+    }
+}
+
+impl Display for Document {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+/// The extensions to JSON that documents may use
+fn options() -> Options {
+    Options::default().comments(true).trailing_commas(true)
+}
+
+/// An array or object whose elements are still being read, with the offset it starts at
+struct Frame {
+    start: usize,
+    kind: Kind,
+    /// The key of the member being read, in an object
+    key: String,
+}
+
+/// Builds the tree of the document in `text`, checking it as it goes in a single pass over its
+/// tokens.
+fn node(text: &str) -> json::Result<Node> {
+    let mut tokens = Lexer::with_options(text.as_bytes(), &options());
+    let mut machine = EventMachine::new(Position { line: 1, column: 1, offset: 0 }).trailing_commas(true);
+    let mut stack: Vec<Frame> = vec![];
+    let mut root = None;
+    while !machine.is_done() {
+        let Some(token) = tokens.next().transpose()? else {
+            return Err(machine.eof_error());
+        };
+        let span = token.2.range();
+        let Some((event, _)) = machine.push(token, true)? else {
+            continue;
+        };
+        let done = match event {
+            Event::StartObject | Event::StartArray => {
+                let kind = match event {
+                    Event::StartObject => Kind::Object(vec![]),
+                    _ => Kind::Array(vec![]),
+                };
+                stack.push(Frame { start: span.start, kind, key: String::new() });
+                continue;
+            }
+            Event::Key(key) => {
+                // only reachable inside an object
+                if let Some(frame) = stack.last_mut() {
+                    frame.key = key;
+                }
+                continue;
+            }
+            Event::EndObject | Event::EndArray => {
+                let Some(frame) = stack.pop() else { unreachable!("the machine only closes open containers") };
+                Node { span: frame.start..span.end, kind: frame.kind }
+            }
+            scalar => {
+                let json_type = match scalar {
+                    Event::String(_) => JSONType::String,
+                    Event::Number(_) => JSONType::Number,
+                    Event::Bool(_) => JSONType::Bool,
+                    _ => JSONType::Null,
+                };
+                Node { span, kind: Kind::Scalar(json_type) }
+            }
+        };
+        match stack.last_mut() {
+            Some(Frame { kind: Kind::Array(items), .. }) => items.push(done),
+            Some(Frame { kind: Kind::Object(members), key, .. }) => members.push((std::mem::take(key), done)),
+            _ => root = Some(done),
+        }
+    }
+    // once the root value is complete, the machine rejects every token
+    if let Some(token) = tokens.next().transpose()? {
+        machine.push(token, false)?;
+    }
+    Ok(root.expect("the machine is only done after the root value"))
+}
@@ -0,0 +1,504 @@
+use crate::json::{self, JSONError, JSONValue, Map, Position};
+use crate::lexer::{Lexer, Token, TokenPos};
+use crate::options::DEFAULT_MAX_DEPTH;
+use crate::parser::number;
+
+/// # Handler
+///
+/// Receives the contents of a document from [`parse_events`] as a sequence of callbacks, in
+/// document order, without a `JSONValue` ever being built. Every callback gets the position of
+/// the token that triggered it, so handlers can point their own errors at the source text.
+///
+/// Returning an error from any callback stops parsing, and [`parse_events`] returns that error.
+/// All methods do nothing by default, so handlers only implement what they care about.
+pub trait Handler {
+    fn start_object(&mut self, _position: Position) -> json::Result<()> {
+        Ok(())
+    }
+    /// Called with the unescaped key before each value of an object.
+    fn key(&mut self, _key: &str, _position: Position) -> json::Result<()> {
+        Ok(())
+    }
+    fn end_object(&mut self, _position: Position) -> json::Result<()> {
+        Ok(())
+    }
+    fn start_array(&mut self, _position: Position) -> json::Result<()> {
+        Ok(())
+    }
+    fn end_array(&mut self, _position: Position) -> json::Result<()> {
+        Ok(())
+    }
+    /// Called with the unescaped contents of a string value.
+    fn string(&mut self, _value: &str, _position: Position) -> json::Result<()> {
+        Ok(())
+    }
+    fn number(&mut self, _value: f64, _position: Position) -> json::Result<()> {
+        Ok(())
+    }
+    fn bool(&mut self, _value: bool, _position: Position) -> json::Result<()> {
+        Ok(())
+    }
+    fn null(&mut self, _position: Position) -> json::Result<()> {
+        Ok(())
+    }
+}
+
+/// Parses the JSON document in `bytes`, reporting its contents to `handler` as it goes.
+///
+/// Syntax errors are reported after the callbacks for everything before them, so a handler may
+/// see part of a document that turns out to be invalid.
+pub fn parse_events(bytes: &[u8], handler: &mut impl Handler) -> json::Result<()> {
+    let mut reader = EventReader::new(Lexer::new(bytes));
+    while let Some((event, position)) = reader.next_event()? {
+        dispatch(handler, event, position)?;
+    }
+    reader.finish()
+}
+
+pub(crate) fn dispatch(handler: &mut impl Handler, event: Event, position: Position) -> json::Result<()> {
+    match event {
+        Event::StartObject => handler.start_object(position),
+        Event::Key(key) => handler.key(&key, position),
+        Event::EndObject => handler.end_object(position),
+        Event::StartArray => handler.start_array(position),
+        Event::EndArray => handler.end_array(position),
+        Event::String(value) => handler.string(&value, position),
+        Event::Number(value) => handler.number(value, position),
+        Event::Bool(value) => handler.bool(value, position),
+        Event::Null => handler.null(position),
+    }
+}
+
+/// Checks that `bytes` holds a single valid JSON document, without building any values. Errors
+/// are the same, at the same positions, as when parsing `bytes` with `JSONValue::try_from`.
+pub fn validate(bytes: &[u8]) -> json::Result<()> {
+    let mut reader = EventReader::new(Lexer::new(bytes));
+    reader.walk_value(false, |_, _| Ok(()))?;
+    reader.finish()
+}
+
+/// # Event
+///
+/// A single step through a document, as read by [`JsonReader::next_event`]. Keys and strings are
+/// already unescaped.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+    StartObject,
+    Key(String),
+    EndObject,
+    StartArray,
+    EndArray,
+    String(String),
+    Number(f64),
+    Bool(bool),
+    Null,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Container {
+    Object,
+    Array,
+}
+
+/// What the reader expects the next token to be
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+    /// A value, e.g. at the root or after a colon
+    Value,
+    /// A value or the end of an array that was just opened, or that may end after a comma
+    FirstValue,
+    /// A comma or the end of the enclosing container
+    AfterValue,
+    /// A key or the end of an object that was just opened, or that may end after a comma
+    FirstKey,
+    /// A key, after a comma in an object
+    Key,
+    /// The colon after a key
+    Colon,
+    /// Nothing; the root value is complete
+    Done,
+}
+
+/// Tracks where the next token of a document belongs and turns tokens into [`Event`]s, one token
+/// at a time. Nesting is kept on an explicit stack instead of the call stack, so the machine can
+/// be paused between any two tokens, e.g. while waiting for more input.
+pub(crate) struct EventMachine {
+    /// Containers that are currently open, innermost last
+    stack: Vec<Container>,
+    state: State,
+    /// Position just past the last token read, where end of input errors point
+    end: Position,
+    trailing_commas: bool,
+}
+
+impl EventMachine {
+    /// Creates a machine for a document whose text starts at `start`.
+    pub fn new(start: Position) -> Self {
+        Self {
+            stack: vec![],
+            state: State::Value,
+            end: start,
+            trailing_commas: false,
+        }
+    }
+
+    /// Whether the last element of an array or object may be followed by a comma, as with
+    /// [`Options::trailing_commas`](crate::json::Options::trailing_commas).
+    pub fn trailing_commas(mut self, allow: bool) -> Self {
+        self.trailing_commas = allow;
+        self
+    }
+
+    /// Whether the root value is complete.
+    pub fn is_done(&self) -> bool {
+        self.state == State::Done
+    }
+
+    /// The number of containers currently open.
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// The error for input that ends before the root value is complete.
+    pub fn eof_error(&self) -> JSONError {
+        let expected = match (self.state, self.stack.last()) {
+            (State::Value, _) => "a value",
+            (State::FirstValue, _) => "a value or CloseBracket",
+            (State::FirstKey, _) => "a key or CloseBrace",
+            (State::Key, _) => "a key",
+            (State::Colon, _) => "Colon",
+            (State::AfterValue, Some(Container::Object)) => "Comma or CloseBrace",
+            (State::AfterValue, _) => "Comma or CloseBracket",
+            (State::Done, _) => "nothing",
+        };
+        JSONError::UnexpectedEof {
+            message: format!("unexpected end of input, expected {}", expected),
+            position: self.end,
+        }
+    }
+
+    /// Called after a complete value: the root or a container that is still open comes next.
+    fn end_value(&mut self) {
+        self.state = if self.stack.is_empty() { State::Done } else { State::AfterValue };
+    }
+
+    /// Opens the container that starts at `position`, within the same depth limit the parser
+    /// uses by default
+    fn open(&mut self, container: Container, position: Position) -> json::Result<()> {
+        if self.stack.len() == DEFAULT_MAX_DEPTH {
+            return Err(JSONError::SyntaxError {
+                message: format!("arrays and objects nested deeper than the maximum depth of {}", DEFAULT_MAX_DEPTH),
+                position,
+            });
+        }
+        self.stack.push(container);
+        Ok(())
+    }
+
+    /// Feeds the next token of the document, returning the event it produces along with its
+    /// position, if any; colons and commas produce none. When `materialize` is false, strings
+    /// and numbers are checked but never copied, and their events carry placeholder contents.
+    pub fn push(&mut self, token: TokenPos<'_>, materialize: bool) -> json::Result<Option<(Event, Position)>> {
+        self.end = token.end();
+        let TokenPos(token, position, _) = token;
+        let top = self.stack.last().copied();
+
+        let event = match (self.state, token) {
+            (State::Value | State::FirstValue, Token::OpenBrace) => {
+                self.open(Container::Object, position)?;
+                self.state = State::FirstKey;
+                Event::StartObject
+            }
+            (State::Value | State::FirstValue, Token::OpenBracket) => {
+                self.open(Container::Array, position)?;
+                self.state = State::FirstValue;
+                Event::StartArray
+            }
+            (State::FirstKey | State::AfterValue, Token::CloseBrace) if top == Some(Container::Object) => {
+                self.stack.pop();
+                self.end_value();
+                Event::EndObject
+            }
+            (State::FirstValue | State::AfterValue, Token::CloseBracket) if top == Some(Container::Array) => {
+                self.stack.pop();
+                self.end_value();
+                Event::EndArray
+            }
+            (State::Value | State::FirstValue, Token::StringLiteral(val)) => {
+                self.end_value();
+                Event::String(if materialize { val.into_owned() } else { String::new() })
+            }
+            (State::Value | State::FirstValue, Token::NumericLiteral(val)) => {
+                self.end_value();
+                Event::Number(if materialize { number(&val, position)? } else { 0.0 })
+            }
+            (State::Value | State::FirstValue, Token::True) => {
+                self.end_value();
+                Event::Bool(true)
+            }
+            (State::Value | State::FirstValue, Token::False) => {
+                self.end_value();
+                Event::Bool(false)
+            }
+            (State::Value | State::FirstValue, Token::Null) => {
+                self.end_value();
+                Event::Null
+            }
+            (State::Value | State::FirstValue, Token::Unknown(text)) => {
+                return Err(JSONError::SyntaxError { message: format!("unexpected token `{text}`"), position });
+            }
+            (State::Value | State::FirstValue, other) => {
+                return Err(JSONError::SyntaxError { message: format!("unexpected token `{:?}`", other), position });
+            }
+            (State::FirstKey | State::Key, Token::StringLiteral(val)) => {
+                self.state = State::Colon;
+                Event::Key(if materialize { val.into_owned() } else { String::new() })
+            }
+            (State::FirstKey | State::Key, other) => {
+                return Err(JSONError::SyntaxError {
+                    message: format!("expected string literal, found {:?}", other),
+                    position,
+                });
+            }
+            (State::Colon, Token::Colon) => {
+                self.state = State::Value;
+                return Ok(None);
+            }
+            (State::AfterValue, Token::Comma) => {
+                self.state = match (top, self.trailing_commas) {
+                    (Some(Container::Object), false) => State::Key,
+                    (Some(Container::Object), true) => State::FirstKey,
+                    (_, false) => State::Value,
+                    (_, true) => State::FirstValue,
+                };
+                return Ok(None);
+            }
+            (State::Colon, other) => {
+                return Err(JSONError::SyntaxError { message: format!("expected Colon, found {:?}", other), position });
+            }
+            (State::AfterValue, other) => {
+                return Err(JSONError::SyntaxError { message: format!("expected Comma, found {:?}", other), position });
+            }
+            (State::Done, other) => {
+                return Err(JSONError::SyntaxError { message: format!("unexpected token `{:?}` after the end of the document", other), position });
+            }
+        };
+        Ok(Some((event, position)))
+    }
+}
+
+/// Reads [`Event`]s for a single document from a stream of tokens.
+pub(crate) struct EventReader<I> {
+    tokens: I,
+    machine: EventMachine,
+}
+
+impl<'a, I: Iterator<Item = json::Result<TokenPos<'a>>>> EventReader<I> {
+    pub fn new(tokens: I) -> Self {
+        Self {
+            tokens,
+            machine: EventMachine::new(Position { line: 1, column: 1, offset: 0 }),
+        }
+    }
+
+    /// Reads the next event along with the position of its token, or `None` once the root value
+    /// is complete. Tokens after the root value are never read.
+    pub fn next_event(&mut self) -> json::Result<Option<(Event, Position)>> {
+        self.step(true)
+    }
+
+    /// Reads the events of the next value, passing each to `visit`. When `materialize` is false,
+    /// strings and numbers are checked but never copied, and their events carry placeholder
+    /// contents.
+    ///
+    /// Errors if the next event does not start a value, e.g. at the end of an object.
+    pub fn walk_value(
+        &mut self,
+        materialize: bool,
+        mut visit: impl FnMut(Event, Position) -> json::Result<()>,
+    ) -> json::Result<()> {
+        let depth = self.machine.depth();
+        let mut first = true;
+        loop {
+            let (event, position) = match self.step(materialize)? {
+                Some(next) => next,
+                // only reachable before the first event, since the root value ends at depth 0
+                None => return Err(JSONError::ValueError("no value left in the document".to_string())),
+            };
+            if first && matches!(event, Event::Key(_) | Event::EndObject | Event::EndArray) {
+                return Err(JSONError::SyntaxError { message: format!("expected a value, found {:?}", event), position });
+            }
+            first = false;
+            visit(event, position)?;
+            if self.machine.depth() == depth {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Checks that nothing but whitespace follows the root value, once it is complete.
+    pub fn finish(&mut self) -> json::Result<()> {
+        match self.tokens.next().transpose()? {
+            // once the root value is complete, the machine rejects every token
+            Some(token) => self.machine.push(token, false).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    /// The number of containers currently open.
+    pub fn depth(&self) -> usize {
+        self.machine.depth()
+    }
+
+    /// Like [`EventReader::next_event`], but when `materialize` is false, strings and numbers
+    /// are checked but never copied, and their events carry placeholder contents.
+    pub fn step(&mut self, materialize: bool) -> json::Result<Option<(Event, Position)>> {
+        while !self.machine.is_done() {
+            match self.tokens.next().transpose()? {
+                Some(token) => {
+                    if let Some(event) = self.machine.push(token, materialize)? {
+                        return Ok(Some(event));
+                    }
+                }
+                None => return Err(self.machine.eof_error()),
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// # JsonReader
+///
+/// A pull parser: the caller asks for one [`Event`] at a time, so it can walk straight to the
+/// parts of a document it needs and skip everything else without building values for it.
+pub struct JsonReader<'a> {
+    events: EventReader<Lexer<'a>>,
+    /// Position of the token behind the last event read
+    position: Position,
+}
+
+impl<'a> JsonReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            events: EventReader::new(Lexer::new(bytes)),
+            position: Position { line: 1, column: 1, offset: 0 },
+        }
+    }
+
+    /// Reads the next event, or `None` once the document is complete.
+    pub fn next_event(&mut self) -> json::Result<Option<Event>> {
+        Ok(self.events.next_event()?.map(|(event, position)| {
+            self.position = position;
+            event
+        }))
+    }
+
+    /// The position of the token behind the last event read.
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    /// Reads the next event and errors unless it is the object key `key`.
+    pub fn expect_key(&mut self, key: &str) -> json::Result<()> {
+        let found = match self.next_event()? {
+            Some(Event::Key(found)) if found == key => return Ok(()),
+            Some(event) => format!("{:?}", event),
+            None => "the end of the document".to_string(),
+        };
+        Err(JSONError::SyntaxError { message: format!("expected key {:?}, found {}", key, found), position: self.position })
+    }
+
+    /// Skips the next value, including everything nested inside it. Skipped strings and numbers
+    /// are never copied, but the value is still checked just as thoroughly as when reading it.
+    pub fn skip_value(&mut self) -> json::Result<()> {
+        let last = &mut self.position;
+        self.events.walk_value(false, |_, position| {
+            *last = position;
+            Ok(())
+        })
+    }
+
+    /// Reads the next value, including everything nested inside it, into a `JSONValue`.
+    pub fn read_value(&mut self) -> json::Result<JSONValue> {
+        let mut builder = TreeBuilder::default();
+        let last = &mut self.position;
+        self.events.walk_value(true, |event, position| {
+            *last = position;
+            dispatch(&mut builder, event, position)
+        })?;
+        // walk_value only returns once a whole value has been visited
+        Ok(builder.into_value().unwrap())
+    }
+}
+
+/// A [`Handler`] that assembles the events it receives back into a `JSONValue`.
+#[derive(Default)]
+pub(crate) struct TreeBuilder {
+    /// Containers that are still being filled, innermost last
+    stack: Vec<JSONValue>,
+    /// Keys waiting for their values, one per open object
+    keys: Vec<String>,
+    root: Option<JSONValue>,
+}
+
+impl TreeBuilder {
+    /// The finished document, or `None` if no complete value was received.
+    pub fn into_value(self) -> Option<JSONValue> {
+        self.root
+    }
+
+    fn value(&mut self, value: JSONValue) {
+        match self.stack.last_mut() {
+            Some(JSONValue::Array(arr)) => arr.push(value),
+            Some(JSONValue::Object(obj)) => {
+                obj.insert(self.keys.pop().unwrap(), value);
+            }
+            Some(_) => unreachable!("only containers are pushed onto the stack"),
+            None => self.root = Some(value),
+        }
+    }
+
+    fn end_container(&mut self) {
+        let container = self.stack.pop().unwrap();
+        self.value(container);
+    }
+}
+
+impl Handler for TreeBuilder {
+    fn start_object(&mut self, _position: Position) -> json::Result<()> {
+        self.stack.push(JSONValue::Object(Map::new()));
+        Ok(())
+    }
+    fn key(&mut self, key: &str, _position: Position) -> json::Result<()> {
+        self.keys.push(key.to_owned());
+        Ok(())
+    }
+    fn end_object(&mut self, _position: Position) -> json::Result<()> {
+        self.end_container();
+        Ok(())
+    }
+    fn start_array(&mut self, _position: Position) -> json::Result<()> {
+        self.stack.push(JSONValue::Array(vec![]));
+        Ok(())
+    }
+    fn end_array(&mut self, _position: Position) -> json::Result<()> {
+        self.end_container();
+        Ok(())
+    }
+    fn string(&mut self, value: &str, _position: Position) -> json::Result<()> {
+        self.value(JSONValue::String(value.to_owned()));
+        Ok(())
+    }
+    fn number(&mut self, value: f64, _position: Position) -> json::Result<()> {
+        self.value(JSONValue::Number(value));
+        Ok(())
+    }
+    fn bool(&mut self, value: bool, _position: Position) -> json::Result<()> {
+        self.value(JSONValue::Bool(value));
+        Ok(())
+    }
+    fn null(&mut self, _position: Position) -> json::Result<()> {
+        self.value(JSONValue::Null);
+        Ok(())
+    }
+}
@@ -0,0 +1,493 @@
+use std::fmt::Write;
+
+use crate::json::{self, JSONError, JSONValue};
+
+/// # FormatOptions
+///
+/// Layout of the JSON text written by [`JSONValue::to_string_with`]. The defaults are what
+/// `to_string()` writes: four spaces per level, a space after each colon and `\n` line breaks.
+/// [`FormatOptions::compact`] leaves out all whitespace.
+///
+/// NaN and the infinities have no JSON representation; `non_finite` decides what happens to them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// Number of `indent_char`s each nesting level is indented by.
+    pub indent_width: usize,
+    /// Character indentation is made of, e.g. `' '` or `'\t'`.
+    pub indent_char: char,
+    /// Whether a space follows the colon between a key and its value.
+    pub space_after_colon: bool,
+    /// Line break written after each opening bracket and element.
+    pub newline: Newline,
+    /// What NaN and infinite numbers are written as.
+    pub non_finite: NonFinite,
+    /// Whether every character above U+007F in strings and keys is escaped as `\uXXXX`, making
+    /// the output pure ASCII. Characters outside the BMP become a surrogate pair.
+    pub ascii_only: bool,
+    /// Whether `<`, `>`, `&`, U+2028 and U+2029 in strings and keys are escaped as `\uXXXX`, so
+    /// the output is safe to embed in an HTML `<script>` element or JavaScript source.
+    pub escape_html: bool,
+    /// Whether `/` in strings and keys is escaped as `\/`, so `</` never appears in the output.
+    pub escape_solidus: bool,
+    /// Number of significant digits numbers are rounded to, if any. Integers in the range of an
+    /// `i64` are always written in full, and `Some(0)` is taken as `Some(1)`.
+    pub max_significant_digits: Option<u8>,
+    /// When numbers are written with an exponent.
+    pub exponent_notation: ExponentNotation,
+    /// Number of characters up to which an array or object is written on a single line, as in
+    /// `[1, 2, 3]`, instead of one line per element. Each container nested inside is measured on
+    /// its own, so a short array still fits on one line inside a long object. `0` never puts
+    /// one on a single line, except when it is empty. A [`Writer`](crate::json::Writer) only
+    /// applies it to the values passed to `Writer::value` whole.
+    pub inline_threshold: usize,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            indent_width: 4,
+            indent_char: ' ',
+            space_after_colon: true,
+            newline: Newline::Lf,
+            non_finite: NonFinite::Error,
+            ascii_only: false,
+            escape_html: false,
+            escape_solidus: false,
+            max_significant_digits: None,
+            exponent_notation: ExponentNotation::Auto,
+            inline_threshold: 0,
+        }
+    }
+}
+
+impl FormatOptions {
+    /// Everything on one line, with no whitespace between tokens.
+    pub fn compact() -> Self {
+        Self {
+            indent_width: 0,
+            space_after_colon: false,
+            newline: Newline::None,
+            ..Self::default()
+        }
+    }
+}
+
+/// Line break style of formatted output.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Newline {
+    /// `\n`
+    #[default]
+    Lf,
+    /// `\r\n`
+    CrLf,
+    /// No line breaks at all
+    None,
+}
+
+impl Newline {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::CrLf => "\r\n",
+            Self::None => "",
+        }
+    }
+}
+
+/// What a number JSON can't represent, i.e. NaN or an infinity, is written as.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NonFinite {
+    /// Nothing: the fallible serializers, like [`JSONValue::write_to`] and
+    /// [`JSONValue::try_to_string`], return `Err(ValueError)`. The rest, `Display` included,
+    /// write `null` instead, since they have no way to report it.
+    #[default]
+    Error,
+    /// `null`
+    Null,
+    /// A string, one of `"NaN"`, `"Infinity"` and `"-Infinity"`
+    String,
+}
+
+/// When a number is written with an exponent, as in `1.5e-7`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ExponentNotation {
+    /// Only for magnitudes below 1e-4, and from 1e16 up for numbers that aren't integers in the
+    /// range of an `i64`
+    #[default]
+    Auto,
+    /// Never, however many zeros it takes
+    Never,
+    /// Always, even for integers, e.g. `1.5e3` and `0e0`
+    Always,
+}
+
+impl JSONValue {
+    /// Serializes this value as JSON text laid out according to `opts`. The output parses back
+    /// to an equal value whatever the options, as long as every number is finite; NaN and the
+    /// infinities are written as `null` unless `opts.non_finite` says otherwise.
+    pub fn to_string_with(&self, opts: &FormatOptions) -> String {
+        let mut out = String::new();
+        self.to_string_into(&mut out, opts);
+        out
+    }
+
+    /// Serializes this value like [`JSONValue::to_string_with`], appending the text to `buf`
+    /// rather than allocating a new `String`, so one buffer can be reused across many values.
+    pub fn to_string_into(&self, buf: &mut String, opts: &FormatOptions) {
+        // writing to a String never fails
+        let _ = self.write_fmt_into(buf, opts);
+    }
+
+    /// Serializes this value like [`JSONValue::to_string_with`], writing the text to `w` as it
+    /// goes. Returns `Err` only if `w` does.
+    pub fn write_fmt_into<W: Write>(&self, w: &mut W, opts: &FormatOptions) -> std::fmt::Result {
+        write_lenient(w, self, opts)
+    }
+
+    /// Serializes this value like [`JSONValue::to_string_with`], with ANSI escape sequences
+    /// around each token to color it for a terminal: keys, strings, numbers, booleans and `null`
+    /// each get their own color, and punctuation is dimmed. With `color` unset, e.g. when the
+    /// `NO_COLOR` environment variable is set, the output is that of `to_string_with`.
+    pub fn to_string_colored(&self, opts: &FormatOptions, color: bool) -> String {
+        let mut out = String::new();
+        // as in to_string_with, non-finite numbers are written as `null` rather than refused
+        let non_finite = match opts.non_finite {
+            NonFinite::Error => NonFinite::Null,
+            other => other,
+        };
+        // writing to a String never fails
+        let _ = write_nested(&mut out, self, &FormatOptions { non_finite, ..opts.clone() }, 0, false, color);
+        out
+    }
+
+    /// Like `to_string()`, but returns `Err(ValueError)` for a NaN or infinite number instead of
+    /// writing `null` in its place.
+    pub fn try_to_string(&self) -> json::Result<String> {
+        self.try_to_string_with(&FormatOptions::default())
+    }
+
+    /// Like [`JSONValue::to_string_with`], but returns `Err(ValueError)` for a NaN or infinite
+    /// number if `opts.non_finite` is [`NonFinite::Error`].
+    pub fn try_to_string_with(&self, opts: &FormatOptions) -> json::Result<String> {
+        let mut out = String::new();
+        match write_value(&mut out, self, opts, 0) {
+            Ok(()) => Ok(out),
+            Err(Failure::NonFinite(n)) => Err(non_finite_error(n)),
+            Err(Failure::Write) => unreachable!("writing to a String never fails"),
+        }
+    }
+
+    /// Serializes this value as JSON text with no whitespace between tokens.
+    pub fn to_string_compact(&self) -> String {
+        self.to_string_with(&FormatOptions::compact())
+    }
+
+    /// Serializes this value straight into a byte buffer, with no whitespace between tokens.
+    /// The bytes are those of [`JSONValue::to_string_compact`].
+    pub fn to_vec(&self) -> Vec<u8> {
+        self.to_vec_with(&FormatOptions::compact())
+    }
+
+    /// Like [`JSONValue::to_vec`], but laid out like `to_string()`.
+    pub fn to_vec_pretty(&self) -> Vec<u8> {
+        self.to_vec_with(&FormatOptions::default())
+    }
+
+    fn to_vec_with(&self, opts: &FormatOptions) -> Vec<u8> {
+        let mut out = vec![];
+        // writing to a Vec never fails
+        let _ = self.write_fmt_into(&mut IoSink::new(&mut out), opts);
+        out
+    }
+
+    /// Writes this value to `writer` as it is serialized, without building the whole text in
+    /// memory first. The output is the same as `to_string()`. Wrapping `writer` in a `BufWriter`
+    /// is recommended.
+    ///
+    /// Returns `Err(IoError)` with the writer's error if writing fails, or `Err(ValueError)` if a
+    /// number is NaN or infinite, after which `writer` may hold part of the text.
+    pub fn write_to<W: std::io::Write>(&self, writer: &mut W) -> json::Result<()> {
+        self.write_pretty_to(writer, &FormatOptions::default())
+    }
+
+    /// Like [`JSONValue::write_to`], but laid out according to `opts` as with
+    /// [`JSONValue::to_string_with`]. NaN and infinite numbers are an error only if
+    /// `opts.non_finite` is [`NonFinite::Error`].
+    pub fn write_pretty_to<W: std::io::Write>(&self, writer: &mut W, opts: &FormatOptions) -> json::Result<()> {
+        let mut sink = IoSink::new(writer);
+        let result = write_value(&mut sink, self, opts, 0);
+        sink.check(result)
+    }
+
+    /// Writes this value to the file at `path`, laid out according to `opts`, creating the file
+    /// or truncating it if it exists. The text is written as it is serialized, through a
+    /// `BufWriter`, like [`JSONValue::write_pretty_to`] does.
+    ///
+    /// Returns `Err(IoError)` naming `path` if the file cannot be created or written, or
+    /// `Err(ValueError)` as `write_pretty_to` would.
+    pub fn to_file(&self, path: impl AsRef<std::path::Path>, opts: &FormatOptions) -> json::Result<()> {
+        let path = path.as_ref();
+        let with_path = |err| match err {
+            JSONError::IoError { source, .. } => JSONError::IoError {
+                message: format!("failed to write {}: {}", path.display(), source),
+                source,
+            },
+            other => other,
+        };
+        let file = std::fs::File::create(path).map_err(|err| with_path(err.into()))?;
+        let mut writer = std::io::BufWriter::new(file);
+        self.write_pretty_to(&mut writer, opts).map_err(with_path)?;
+        std::io::Write::flush(&mut writer).map_err(|err| with_path(err.into()))
+    }
+}
+
+/// Passes formatted text on to an `io::Write`, keeping hold of the error that stopped it, which
+/// `std::fmt::Error` has no room for
+pub(crate) struct IoSink<'a, W> {
+    writer: &'a mut W,
+    error: Option<std::io::Error>,
+}
+
+impl<'a, W> IoSink<'a, W> {
+    pub(crate) fn new(writer: &'a mut W) -> Self {
+        Self { writer, error: None }
+    }
+
+    /// The error to return for `result`, the outcome of writing through this sink
+    pub(crate) fn check(mut self, result: Result<(), Failure>) -> json::Result<()> {
+        match result {
+            Ok(()) => Ok(()),
+            Err(Failure::NonFinite(n)) => Err(non_finite_error(n)),
+            Err(Failure::Write) => Err(self.error.take().unwrap_or_else(|| std::io::Error::other("formatting failed")).into()),
+        }
+    }
+}
+
+impl<W: std::io::Write> Write for IoSink<'_, W> {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        self.writer.write_all(s.as_bytes()).map_err(|err| {
+            self.error = Some(err);
+            std::fmt::Error
+        })
+    }
+}
+
+/// Why writing a value stopped
+pub(crate) enum Failure {
+    /// The output failed
+    Write,
+    /// A NaN or infinite number, with `NonFinite::Error` in the options
+    NonFinite(f64),
+}
+
+impl From<std::fmt::Error> for Failure {
+    fn from(_: std::fmt::Error) -> Self {
+        Self::Write
+    }
+}
+
+pub(crate) fn non_finite_error(n: f64) -> JSONError {
+    JSONError::ValueError(format!("cannot serialize {} as a JSON number", n))
+}
+
+/// Writes `value` for a serializer that can't report a non-finite number, writing `null` in place
+/// of any that `opts` would make an error.
+pub(crate) fn write_lenient(out: &mut impl Write, value: &JSONValue, opts: &FormatOptions) -> std::fmt::Result {
+    let result = match opts.non_finite {
+        NonFinite::Error => write_value(out, value, &FormatOptions { non_finite: NonFinite::Null, ..opts.clone() }, 0),
+        _ => write_value(out, value, opts, 0),
+    };
+    result.map_err(|_| std::fmt::Error)
+}
+
+/// Writes `value`, whose first line is already indented for nesting `level`, along with
+/// everything nested inside it.
+pub(crate) fn write_value(out: &mut impl Write, value: &JSONValue, opts: &FormatOptions, level: usize) -> Result<(), Failure> {
+    write_nested(out, value, opts, level, false, false)
+}
+
+/// Like `write_value`, but with `inline` set, writes every container on a single line, and with
+/// `color` set, wraps each token in the ANSI escape sequences for its kind
+fn write_nested(out: &mut impl Write, value: &JSONValue, opts: &FormatOptions, level: usize, inline: bool, color: bool) -> Result<(), Failure> {
+    let paint = |style| color.then_some(style);
+    match value {
+        JSONValue::Bool(b) => Ok(styled(out, paint(ansi::BOOL), |out| write!(out, "{}", b))?),
+        JSONValue::Number(n) if !n.is_finite() => match opts.non_finite {
+            NonFinite::Error => Err(Failure::NonFinite(*n)),
+            NonFinite::Null => Ok(styled(out, paint(ansi::NULL), |out| out.write_str("null"))?),
+            NonFinite::String if n.is_nan() => Ok(styled(out, paint(ansi::STRING), |out| out.write_str("\"NaN\""))?),
+            NonFinite::String if *n > 0.0 => Ok(styled(out, paint(ansi::STRING), |out| out.write_str("\"Infinity\""))?),
+            NonFinite::String => Ok(styled(out, paint(ansi::STRING), |out| out.write_str("\"-Infinity\""))?),
+        },
+        JSONValue::Number(n) => Ok(styled(out, paint(ansi::NUMBER), |out| write_number(out, *n, opts))?),
+        JSONValue::String(s) => Ok(styled(out, paint(ansi::STRING), |out| write_string(out, s, opts))?),
+        // empty containers stay on one line
+        JSONValue::Array(arr) if arr.is_empty() => Ok(styled(out, paint(ansi::PUNCTUATION), |out| out.write_str("[]"))?),
+        JSONValue::Object(obj) if obj.is_empty() => Ok(styled(out, paint(ansi::PUNCTUATION), |out| out.write_str("{}"))?),
+        JSONValue::Array(_) | JSONValue::Object(_) if !inline && fits_inline(value, opts) => write_nested(out, value, opts, level, true, color),
+        JSONValue::Array(arr) => {
+            styled(out, paint(ansi::PUNCTUATION), |out| out.write_char('['))?;
+            for (i, item) in arr.iter().enumerate() {
+                separate(out, opts, level + 1, i, inline, color)?;
+                write_nested(out, item, opts, level + 1, inline, color)?;
+            }
+            line_break(out, opts, level, inline)?;
+            Ok(styled(out, paint(ansi::PUNCTUATION), |out| out.write_char(']'))?)
+        }
+        JSONValue::Object(obj) => {
+            styled(out, paint(ansi::PUNCTUATION), |out| out.write_char('{'))?;
+            for (i, (key, item)) in obj.iter().enumerate() {
+                separate(out, opts, level + 1, i, inline, color)?;
+                styled(out, paint(ansi::KEY), |out| write_string(out, key, opts))?;
+                styled(out, paint(ansi::PUNCTUATION), |out| out.write_char(':'))?;
+                if opts.space_after_colon {
+                    out.write_char(' ')?;
+                }
+                write_nested(out, item, opts, level + 1, inline, color)?;
+            }
+            line_break(out, opts, level, inline)?;
+            Ok(styled(out, paint(ansi::PUNCTUATION), |out| out.write_char('}'))?)
+        }
+        JSONValue::Null => Ok(styled(out, paint(ansi::NULL), |out| out.write_str("null"))?),
+    }
+}
+
+/// ANSI escape sequences for the kinds of tokens in colored output
+mod ansi {
+    pub(super) const KEY: &str = "\x1b[1;34m";
+    pub(super) const STRING: &str = "\x1b[32m";
+    pub(super) const NUMBER: &str = "\x1b[33m";
+    pub(super) const BOOL: &str = "\x1b[35m";
+    pub(super) const NULL: &str = "\x1b[90m";
+    pub(super) const PUNCTUATION: &str = "\x1b[2m";
+    pub(super) const RESET: &str = "\x1b[0m";
+}
+
+/// Writes a token with `token`, between `style` and a reset if there is one
+fn styled<W: Write>(out: &mut W, style: Option<&str>, token: impl FnOnce(&mut W) -> std::fmt::Result) -> std::fmt::Result {
+    match style {
+        Some(style) => {
+            out.write_str(style)?;
+            token(out)?;
+            out.write_str(ansi::RESET)
+        }
+        None => token(out),
+    }
+}
+
+/// Writes what goes before element `i` of a container: a comma after the previous one, then a
+/// line break, or a space if the container is inline
+fn separate(out: &mut impl Write, opts: &FormatOptions, level: usize, i: usize, inline: bool, color: bool) -> std::fmt::Result {
+    if i > 0 {
+        styled(out, color.then_some(ansi::PUNCTUATION), |out| out.write_char(','))?;
+        if inline {
+            out.write_char(' ')?;
+        }
+    }
+    line_break(out, opts, level, inline)
+}
+
+fn line_break(out: &mut impl Write, opts: &FormatOptions, level: usize, inline: bool) -> std::fmt::Result {
+    if inline {
+        return Ok(());
+    }
+    out.write_str(opts.newline.as_str())?;
+    indent(out, opts, level)
+}
+
+/// Whether the container `value` written on a single line takes no more than
+/// `opts.inline_threshold` characters
+fn fits_inline(value: &JSONValue, opts: &FormatOptions) -> bool {
+    // counts down the characters left, and stops the writing once they run out
+    struct Budget(usize);
+    impl Write for Budget {
+        fn write_str(&mut self, s: &str) -> std::fmt::Result {
+            self.0 = self.0.checked_sub(s.chars().count()).ok_or(std::fmt::Error)?;
+            Ok(())
+        }
+    }
+    opts.inline_threshold > 0 && write_nested(&mut Budget(opts.inline_threshold), value, opts, 0, true, false).is_ok()
+}
+
+/// Writes `n` with the fewest digits that parse back to exactly `n`, after rounding it to
+/// `opts.max_significant_digits`. Unless `opts.exponent_notation` says otherwise, integers in the
+/// range of an `i64` are written out in full, and anything else with a very large or very small
+/// magnitude gets an exponent, e.g. `1e21` and `1e-7`, rather than a long run of zeros.
+fn write_number(out: &mut impl Write, n: f64, opts: &FormatOptions) -> std::fmt::Result {
+    let n = match opts.max_significant_digits {
+        Some(digits) if !is_integer(n) => round_significant(n, digits),
+        _ => n,
+    };
+    if opts.exponent_notation == ExponentNotation::Always {
+        return write!(out, "{:e}", n);
+    }
+    if is_integer(n) {
+        // `-0` keeps the sign that `0` would lose
+        if n == 0.0 && n.is_sign_negative() {
+            return out.write_str("-0");
+        }
+        write!(out, "{}", n as i64)
+    } else if opts.exponent_notation == ExponentNotation::Never {
+        write!(out, "{}", n)
+    } else {
+        // `{:?}` is the shortest representation that round-trips, with an exponent below 1e-4
+        // and from 1e16 up
+        write!(out, "{:?}", n)
+    }
+}
+
+fn is_integer(n: f64) -> bool {
+    n.fract() == 0.0 && (i64::MIN as f64..i64::MAX as f64).contains(&n)
+}
+
+/// The nearest `f64` to `n` rounded to `digits` significant digits, or `n` itself if that
+/// overflows
+fn round_significant(n: f64, digits: u8) -> f64 {
+    // exact decimal formatting rounds correctly, where scaling by a power of ten wouldn't
+    let rounded: f64 = format!("{:.*e}", digits.max(1) as usize - 1, n).parse().unwrap_or(n);
+    if rounded.is_finite() { rounded } else { n }
+}
+
+pub(crate) fn indent(out: &mut impl Write, opts: &FormatOptions, level: usize) -> std::fmt::Result {
+    for _ in 0..level * opts.indent_width {
+        out.write_char(opts.indent_char)?;
+    }
+    Ok(())
+}
+
+/// Writes `s` as a JSON string literal, escaping quotes, backslashes and every control character
+/// so the output parses back to exactly `s`, along with the characters the escaping options of
+/// `opts` ask for.
+pub(crate) fn write_string(f: &mut impl Write, s: &str, opts: &FormatOptions) -> std::fmt::Result {
+    f.write_char('"')?;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        let escaped = match c {
+            '"' => "\\\"",
+            '\\' => "\\\\",
+            '\n' => "\\n",
+            '\r' => "\\r",
+            '\t' => "\\t",
+            '\u{0008}' => "\\b",
+            '\u{000c}' => "\\f",
+            '\u{0000}'..='\u{001f}' => "",
+            '<' | '>' | '&' | '\u{2028}' | '\u{2029}' if opts.escape_html => "",
+            '/' if opts.escape_solidus => "\\/",
+            '\u{0080}'.. if opts.ascii_only => "",
+            _ => continue,
+        };
+        f.write_str(&s[start..i])?;
+        match escaped {
+            "" => {
+                // one escape per UTF-16 code unit, so a surrogate pair outside the BMP
+                for unit in c.encode_utf16(&mut [0; 2]) {
+                    write!(f, "\\u{:04x}", unit)?;
+                }
+            }
+            _ => f.write_str(escaped)?,
+        }
+        start = i + c.len_utf8();
+    }
+    f.write_str(&s[start..])?;
+    f.write_char('"')
+}
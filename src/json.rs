@@ -1,11 +1,54 @@
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
 use std::error::Error;
 use std::fmt::Display;
 use std::str::FromStr;
 use std::ops::{Index, IndexMut};
 
-use crate::lexer::Lexer;
-use crate::parser::Parser;
+use crate::stream::{IterReader, ReaderTokens};
+
+pub use crate::case::KeyCase;
+pub use crate::display::DisplayOptions;
+pub use crate::document::Document;
+pub use crate::events::{parse_events, validate, Event, Handler, JsonReader};
+pub use crate::format::{ExponentNotation, FormatOptions, Newline, NonFinite};
+pub use crate::lexer::{Lexer, Span, Token, TokenPos};
+pub use crate::map::{Entry, Map};
+pub use crate::merge::{ArrayMerge, ConflictMerge, MergeStrategy};
+pub use crate::minify::{minify, minify_to, reformat};
+pub use crate::options::{DuplicateKeys, Options};
+pub use crate::parser::{Parser, VecTokens};
+pub use crate::patch::diff;
+pub use crate::pointer::{get_path_raw, MAX_POINTER_PADDING};
+pub use crate::session::Session;
+pub use crate::stream::{lines, lines_from_slice, Deserializer, StreamParser};
+pub use crate::sync::SyncJson;
+pub use crate::tolerant::parse_tolerant;
+pub use crate::writer::Writer;
+
+/// Hash chains over the elements of an array, for logs that must show whether they were changed.
+#[cfg(feature = "audit")]
+pub mod audit {
+    pub use crate::audit::{chain_digest, verify_chain, VerifyReport};
+}
+
+/// # Position
+///
+/// A location in a JSON source buffer. `line` and `column` are 1-based, matching what a text editor
+/// would display, so `column` counts characters rather than bytes. `offset` is the 0-based byte
+/// index into the buffer.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+    pub offset: usize,
+}
+
+impl Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
 
 /// # JSONError
 ///
@@ -13,12 +56,22 @@ use crate::parser::Parser;
 /// are at the parsing level caught before a JSONValue is parsed, and others are caught while
 /// using or processing it.
 ///
+/// Errors raised while reading JSON text carry the [`Position`] where they occurred, which can
+/// be queried with [`JSONError::position`].
+///
 /// These errors are typically used in expressions using [`json::Result<T>`](Result).
 #[derive(Debug)]
 pub enum JSONError {
-    /// An error involving the validity of an inputted JSON string. For example, this error would be
+    /// An error raised while splitting JSON text into tokens. For example, this error would be
     /// returned if someone tries to parse JSON data with an unterminated string.
-    SyntaxError(String),
+    LexerError { message: String, position: Position },
+    /// An error involving the structure of an inputted JSON string. For example, this error would
+    /// be returned if an object is missing a colon between a key and its value.
+    SyntaxError { message: String, position: Position },
+    /// An error raised when the input ends before the document is complete, e.g. in the middle
+    /// of an object or a string. The position is where the input ran out, so a document cut
+    /// short can be told apart from one that is malformed, and retried once more data arrives.
+    UnexpectedEof { message: String, position: Position },
     /// An error involving the types of operations being done on a `JSONValue`. For example, this
     /// error would be returned if someone tries to index a `Null` object.
     ValueError(String),
@@ -26,17 +79,117 @@ pub enum JSONError {
     KeyError(String),
     /// An error used when trying to index a `JSONArray`.
     IndexError(String),
+    /// An error returned by a [`SyncJson`] whose lock was poisoned by a thread that panicked
+    /// while holding it.
+    PoisonError(String),
+    /// An error raised by the reader that JSON text was being read from, e.g. by
+    /// [`JSONValue::from_reader`], or by the writer it was being written to, e.g. by
+    /// [`JSONValue::write_to`]. Errors with a file, e.g. from [`JSONValue::from_file`], name its
+    /// path. The original error is available through [`Error::source`].
+    IoError { message: String, source: std::io::Error },
+}
+
+impl JSONError {
+    /// Returns the location in the source text where this error occurred, or `None` if the error
+    /// did not come from reading JSON text.
+    pub fn position(&self) -> Option<Position> {
+        match self {
+            Self::LexerError { position, .. }
+            | Self::SyntaxError { position, .. }
+            | Self::UnexpectedEof { position, .. } => Some(*position),
+            _ => None,
+        }
+    }
+
+    /// Returns the description of this error, without the error kind or position.
+    pub fn message(&self) -> &str {
+        match self {
+            Self::LexerError { message, .. }
+            | Self::SyntaxError { message, .. }
+            | Self::UnexpectedEof { message, .. } => message,
+            Self::ValueError(what) | Self::KeyError(what) | Self::IndexError(what) | Self::PoisonError(what) => what,
+            Self::IoError { message, .. } => message,
+        }
+    }
+
+    /// Renders this error the way a compiler would: the message, followed by the offending line of
+    /// `source` (the buffer that was parsed) with a caret under the error position. Lines longer
+    /// than 80 characters are cut down to a window around the caret. Errors without a position
+    /// render as just their message.
+    pub fn render(&self, source: &[u8]) -> String {
+        const MAX_WIDTH: usize = 80;
+
+        let position = match self.position() {
+            Some(position) if position.offset <= source.len() => position,
+            _ => return self.to_string(),
+        };
+
+        let line_start = source[..position.offset].iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+        let line_end = source[position.offset..].iter().position(|&b| b == b'\n')
+            .map_or(source.len(), |i| position.offset + i);
+        let before: Vec<char> = String::from_utf8_lossy(&source[line_start..position.offset]).chars().collect();
+        let mut line: Vec<char> = String::from_utf8_lossy(&source[line_start..line_end]).chars().collect();
+        if line.last() == Some(&'\r') {
+            line.pop();
+        }
+        let mut caret = before.len();
+
+        // cut long lines down to a window around the caret
+        let (mut prefix, mut suffix) = ("", "");
+        if line.len() > MAX_WIDTH {
+            let start = caret.saturating_sub(MAX_WIDTH / 2).min(line.len() - MAX_WIDTH);
+            if start > 0 {
+                prefix = "...";
+            }
+            if start + MAX_WIDTH < line.len() {
+                suffix = "...";
+            }
+            line = line[start..start + MAX_WIDTH].to_vec();
+            caret -= start;
+        }
+
+        // keep tabs in the caret line so it lines up however wide the terminal draws them
+        let padding: String = line.iter().take(caret)
+            .map(|&c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let gutter = " ".repeat(position.line.to_string().len());
+        let line: String = line.into_iter().collect();
+
+        format!(
+            "{}\n{gutter} |\n{} | {prefix}{line}{suffix}\n{gutter} | {}{padding}^",
+            self,
+            position.line,
+            " ".repeat(prefix.len()),
+        )
+    }
+}
+
+impl Error for JSONError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::IoError { source, .. } => Some(source),
+            _ => None,
+        }
+    }
 }
 
-impl Error for JSONError {}
+impl From<std::io::Error> for JSONError {
+    fn from(source: std::io::Error) -> Self {
+        Self::IoError { message: source.to_string(), source }
+    }
+}
 
 impl Display for JSONError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::SyntaxError(what) => write!(f, "JSON Syntax Error: {}", what),
+            Self::LexerError { message, position } => write!(f, "JSON Lexer Error: {} at {}", message, position),
+            Self::SyntaxError { message, position } => write!(f, "JSON Syntax Error: {} at {}", message, position),
+            Self::UnexpectedEof { message, position } => write!(f, "JSON Unexpected EOF: {} at {}", message, position),
             Self::ValueError(what) => write!(f, "JSON Value Error: {}", what),
             Self::KeyError(what) => write!(f, "JSON Key Error: {}", what),
             Self::IndexError(what) => write!(f, "JSON Index Error: {}", what),
+            Self::PoisonError(what) => write!(f, "JSON Poison Error: {}", what),
+            Self::IoError { message, .. } => write!(f, "JSON IO Error: {}", message),
         }
     }
 }
@@ -45,7 +198,11 @@ impl Display for JSONError {
 ///
 /// The primitive enum type for all values that can be stored in a JSON file, with each enum variant
 /// having a unary tuple type that mimics the behavior of that JSON object.
-#[derive(Clone, Debug, PartialEq)]
+///
+/// Any value without a NaN or infinite number survives a round trip: serializing it, compact or
+/// pretty and with any [`FormatOptions`] that don't round numbers, and parsing the text gives back
+/// an equal value.
+#[derive(Clone, Debug, Default, PartialEq)]
 pub enum JSONValue {
     /// The primitive boolean type.
     Bool(bool),
@@ -56,10 +213,12 @@ pub enum JSONValue {
     String(String),
     /// The primitive Array type. Under the hood, this is just a vector of other `JSONValue`s.
     Array(Vec<JSONValue>),
-    /// The primitive Object type. Under the hood, this is a HashMap between strings and
-    /// `JSONValue`s, mirroring the object key-value pairs in JSON files.
-    Object(HashMap<String, JSONValue>),
-    /// The primitive Null type, similar to the Rust zero-sized tuple `()`.
+    /// The primitive Object type. Under the hood, this is a [`Map`] between strings and
+    /// `JSONValue`s, mirroring the object key-value pairs in JSON files in their original order.
+    Object(Map),
+    /// The primitive Null type, similar to the Rust zero-sized tuple `()`. This is the default
+    /// value.
+    #[default]
     Null,
 }
 
@@ -117,6 +276,48 @@ impl JSONValue {
         }
     }
 
+    /// Queries for a reference to a value in a `JSONValue::Object`, for keys that may be absent.
+    ///
+    /// Returns `None` both if `key` is not found and if `self` is not a json `Object`; use
+    /// [`JSONValue::try_get`] to tell the two apart.
+    pub fn get_opt(&self, key: &str) -> Option<&JSONValue> {
+        self.as_object()?.get(key)
+    }
+
+    /// Queries for a mutable reference to a value in a `JSONValue::Object`, for keys that may be
+    /// absent. Returns `None` in the same cases as [`JSONValue::get_opt`].
+    pub fn get_opt_mut(&mut self, key: &str) -> Option<&mut JSONValue> {
+        self.as_object_mut()?.get_mut(key)
+    }
+
+    /// Queries for a reference to a value in a `JSONValue::Object`, for keys that may be absent
+    /// in objects that must not be.
+    ///
+    /// Returns:
+    /// - `Err(ValueError)` if `self` is not a json `Object`,
+    /// - `Ok(None)` if `key` is not found in this `Object`,
+    /// - `Ok(Some(&JSONValue))` with a reference to the queried value otherwise.
+    pub fn try_get(&self, key: &str) -> Result<Option<&JSONValue>> {
+        match self {
+            Self::Object(vals) => Ok(vals.get(key)),
+            other => {
+                Err(JSONError::ValueError(format!("expected object, found {:?}", other.name())))
+            }
+        }
+    }
+
+    /// Queries for a value in a `JSONValue::Object` and casts it to `T`, so
+    /// `value.get_as::<u16>("port")` does what `value.get("port")?.cast()` does.
+    ///
+    /// Returns:
+    /// - `Err(ValueError)` if `self` is not a json `Object`, or if the value can't be cast to `T`,
+    ///   with a message naming `key`, like `expected number for key "port", found "string"`,
+    /// - `Err(KeyError)` if `key` is not found in this `Object`,
+    /// - `Ok(T)` with the cast value otherwise.
+    pub fn get_as<T>(&self, key: &str) -> Result<T> where JSONValue: Cast<T> {
+        self.get(key)?.cast_at(&[&format!("key {:?}", key)])
+    }
+
     /// Inserts a `value` into a `JSONValue::Object`.
     ///
     /// Returns:
@@ -126,8 +327,8 @@ impl JSONValue {
     pub fn obj_insert(&mut self, key: &str, value: JSONValue) -> Result<()> {
         match self {
             Self::Object(map) => {
-                if let Some(_) = map.get_mut(key) {
-                    Err(JSONError::KeyError(format!("key {} already in object", key)))
+                if map.contains_key(key) {
+                    Err(JSONError::KeyError(format!("key {} already in object, use obj_set to overwrite it", key)))
                 } else {
                     map.insert(key.to_string(), value);
                     Ok(())
@@ -139,6 +340,48 @@ impl JSONValue {
         }
     }
 
+    /// Takes the value of `key` out of a `JSONValue::Object` without removing the key, leaving
+    /// `JSONValue::Null` in its place.
+    ///
+    /// Returns:
+    /// - `Err(ValueError)` if `self` is not a json `Object`,
+    /// - `Err(KeyError)` if `key` is not found in this `Object`,
+    /// - `Ok(JSONValue)`, the value taken, otherwise.
+    pub fn obj_take(&mut self, key: &str) -> Result<JSONValue> {
+        self.get_mut(key).map(JSONValue::take)
+    }
+
+    /// Sets `key` to `value` in a `JSONValue::Object`, whether or not it is already there. A key
+    /// that was there keeps its place.
+    ///
+    /// Returns:
+    /// - `Err(ValueError)` if `self` is not a json `Object`,
+    /// - `Ok(Some)` with the value `key` had, if it was already in this `Object`,
+    /// - `Ok(None)` otherwise.
+    pub fn obj_set(&mut self, key: impl Into<String>, value: JSONValue) -> Result<Option<JSONValue>> {
+        match self {
+            Self::Object(map) => Ok(map.insert(key.into(), value)),
+            other => {
+                Err(JSONError::ValueError(format!("expected object, found {:?}", other.name())))
+            }
+        }
+    }
+
+    /// The entry for `key` in a `JSONValue::Object`, to insert a value if it isn't there and
+    /// change it if it is, with a single call.
+    ///
+    /// Returns:
+    /// - `Err(ValueError)` if `self` is not a json `Object`,
+    /// - `Ok(Entry)` otherwise.
+    pub fn obj_entry(&mut self, key: impl Into<String>) -> Result<Entry<'_>> {
+        match self {
+            Self::Object(map) => Ok(map.entry(key.into())),
+            other => {
+                Err(JSONError::ValueError(format!("expected object, found {:?}", other.name())))
+            }
+        }
+    }
+
     /// Removes a `value` from a `JSONValue::Object`.
     ///
     /// Returns:
@@ -160,6 +403,76 @@ impl JSONValue {
         }
     }
 
+    /// Inserts every key-value pair of `entries` into a `JSONValue::Object`, in order. A key that
+    /// is already in the object, or comes up again in `entries`, keeps its place and takes the
+    /// new value.
+    ///
+    /// Returns:
+    /// - `Err(ValueError)` if `self` is not a json `Object`, in which case it is left unchanged,
+    /// - `Ok(())` otherwise.
+    pub fn obj_extend<I: IntoIterator<Item = (String, JSONValue)>>(&mut self, entries: I) -> Result<()> {
+        match self {
+            Self::Object(map) => {
+                map.extend(entries);
+                Ok(())
+            }
+            other => {
+                Err(JSONError::ValueError(format!("expected object, found {:?}", other.name())))
+            }
+        }
+    }
+
+    /// Iterates over the keys of a `JSONValue::Object`, in order.
+    ///
+    /// Returns `Err(ValueError)` if `self` is not a json `Object`.
+    pub fn keys(&self) -> Result<impl Iterator<Item = &str>> {
+        match self {
+            Self::Object(map) => Ok(map.keys().map(String::as_str)),
+            other => Err(JSONError::ValueError(format!("expected object, found {:?}", other.name()))),
+        }
+    }
+
+    /// Iterates over the values of a `JSONValue::Object`, in order.
+    ///
+    /// Returns `Err(ValueError)` if `self` is not a json `Object`.
+    pub fn values(&self) -> Result<impl Iterator<Item = &JSONValue>> {
+        match self {
+            Self::Object(map) => Ok(map.values()),
+            other => Err(JSONError::ValueError(format!("expected object, found {:?}", other.name()))),
+        }
+    }
+
+    /// Iterates over mutable references to the values of a `JSONValue::Object`, in order.
+    ///
+    /// Returns `Err(ValueError)` if `self` is not a json `Object`.
+    pub fn values_mut(&mut self) -> Result<impl Iterator<Item = &mut JSONValue>> {
+        match self {
+            Self::Object(map) => Ok(map.values_mut()),
+            other => Err(JSONError::ValueError(format!("expected object, found {:?}", other.name()))),
+        }
+    }
+
+    /// Iterates over the key-value pairs of a `JSONValue::Object`, in order.
+    ///
+    /// Returns `Err(ValueError)` if `self` is not a json `Object`.
+    pub fn entries(&self) -> Result<impl Iterator<Item = (&str, &JSONValue)>> {
+        match self {
+            Self::Object(map) => Ok(map.iter().map(|(key, value)| (key.as_str(), value))),
+            other => Err(JSONError::ValueError(format!("expected object, found {:?}", other.name()))),
+        }
+    }
+
+    /// Iterates over the key-value pairs of a `JSONValue::Object`, in order, with the values
+    /// mutable.
+    ///
+    /// Returns `Err(ValueError)` if `self` is not a json `Object`.
+    pub fn entries_mut(&mut self) -> Result<impl Iterator<Item = (&str, &mut JSONValue)>> {
+        match self {
+            Self::Object(map) => Ok(map.iter_mut().map(|(key, value)| (key.as_str(), value))),
+            other => Err(JSONError::ValueError(format!("expected object, found {:?}", other.name()))),
+        }
+    }
+
     //////////////////////////////////////////////
     // Functions that assume `self` is an Array //
     //////////////////////////////////////////////
@@ -194,7 +507,7 @@ impl JSONValue {
     pub fn try_index_mut(&mut self, index: usize) -> Result<&mut JSONValue> {
         match self {
             Self::Array(arr) => {
-                let len = arr.len().clone();
+                let len = arr.len();
 
                 if let Some(val) = arr.get_mut(index) {
                     Ok(val)
@@ -208,6 +521,17 @@ impl JSONValue {
         }
     }
 
+    /// Queries for a value in a `JSONValue::Array` and casts it to `T`, like
+    /// [`JSONValue::get_as`] does for objects.
+    ///
+    /// Returns:
+    /// - `Err(ValueError)` if `self` is not a json `Array`, if `index` is out of bounds, or if the
+    ///   value can't be cast to `T`, with a message naming `index`,
+    /// - `Ok(T)` with the cast value otherwise.
+    pub fn index_as<T>(&self, index: usize) -> Result<T> where JSONValue: Cast<T> {
+        self.try_index(index)?.cast_at(&[&format!("index {}", index)])
+    }
+
     /// Adds a value at the end of a `JSONValue::Array`.
     ///
     /// Returns:
@@ -254,7 +578,7 @@ impl JSONValue {
     pub fn arr_insert(&mut self, pos: usize, val: JSONValue) -> Result<()> {
         match self {
             Self::Array(arr) => {
-                let len = arr.len().clone();
+                let len = arr.len();
 
                 if pos > len {
                     Err(JSONError::IndexError(format!("index {} out of bounds for length {}", pos, len)))
@@ -270,6 +594,28 @@ impl JSONValue {
         }
     }
 
+    /// Takes the value at position `pos` out of a `JSONValue::Array`, leaving `JSONValue::Null`
+    /// in its place, so the values after it keep their positions.
+    ///
+    /// Returns:
+    /// - `Err(ValueError)` if `self` is not the `Array` enum variant,
+    /// - `Err(IndexError)` if `pos` is out of bounds for the array,
+    /// - `Ok(JSONValue)`, the value taken, otherwise.
+    pub fn arr_take(&mut self, pos: usize) -> Result<JSONValue> {
+        match self {
+            Self::Array(arr) => {
+                let len = arr.len();
+                match arr.get_mut(pos) {
+                    Some(value) => Ok(value.take()),
+                    None => Err(JSONError::IndexError(format!("index {} out of bounds for length {}", pos, len))),
+                }
+            }
+            other => {
+                Err(JSONError::ValueError(format!("expected array, found {}", other.name())))
+            }
+        }
+    }
+
     /// Removes the value at position `pos` in a `JSONValue::Array`.
     ///
     /// Returns:
@@ -279,7 +625,7 @@ impl JSONValue {
     pub fn arr_remove(&mut self, pos: usize) -> Result<JSONValue> {
         match self {
             Self::Array(arr) => {
-                let len = arr.len().clone();
+                let len = arr.len();
 
                 if pos > len {
                     Err(JSONError::IndexError(format!("index {} out of bounds for length {}", pos, len)))
@@ -293,68 +639,294 @@ impl JSONValue {
         }
     }
 
+    /// Appends every item of `items` to the end of a `JSONValue::Array`, in order.
+    ///
+    /// Returns:
+    /// - `Err(ValueError)` if `self` is not a json `Array`, in which case it is left unchanged,
+    /// - `Ok(())` otherwise.
+    pub fn arr_extend<I: IntoIterator<Item = JSONValue>>(&mut self, items: I) -> Result<()> {
+        match self {
+            Self::Array(arr) => {
+                arr.extend(items);
+                Ok(())
+            }
+            other => {
+                Err(JSONError::ValueError(format!("expected array, found {}", other.name())))
+            }
+        }
+    }
+
+    /// Iterates over the items of a `JSONValue::Array`, in order.
+    ///
+    /// Returns `Err(ValueError)` if `self` is not a json `Array`.
+    pub fn elements(&self) -> Result<impl Iterator<Item = &JSONValue>> {
+        match self {
+            Self::Array(arr) => Ok(arr.iter()),
+            other => Err(JSONError::ValueError(format!("expected array, found {}", other.name()))),
+        }
+    }
+
+    /// Iterates over mutable references to the items of a `JSONValue::Array`, in order.
+    ///
+    /// Returns `Err(ValueError)` if `self` is not a json `Array`.
+    pub fn elements_mut(&mut self) -> Result<impl Iterator<Item = &mut JSONValue>> {
+        match self {
+            Self::Array(arr) => Ok(arr.iter_mut()),
+            other => Err(JSONError::ValueError(format!("expected array, found {}", other.name()))),
+        }
+    }
+
+    /// Takes this value, leaving `JSONValue::Null` in its place, e.g. to move a value out of a
+    /// document without copying it. The same as `std::mem::take`.
+    pub fn take(&mut self) -> JSONValue {
+        std::mem::replace(self, Self::Null)
+    }
+
     /// Constructs a JSON null value.
     #[inline]
     pub const fn null() -> Self {
         Self::Null
     }
 
-    // helper function to assist with <JSONValue as Display>::fmt(). Allows printed
-    // JSON text to auto-format spacing. 
-    fn fmt_recursive(&self, f: &mut std::fmt::Formatter<'_>, level: usize) -> std::fmt::Result {
+    /// The type of this value, without its contents.
+    pub fn json_type(&self) -> JSONType {
         match self {
-            Self::Bool(b) => { write!(f, "{}", b)?; }
-            Self::Number(n) => { write!(f, "{}", n)?; }
-            Self::String(s) => { write!(f, "\"{}\"", s)?; }
-            Self::Array(arr) => {
-                let tab_width = level * 4;
-                write!(f, "[\n")?;
-                for i in 0..arr.len() {
-                    write!(f, "    {: <1$}", "", tab_width)?;
-                    arr[i].fmt_recursive(f, level + 1)?;
-                    if i != arr.len() - 1 {
-                        write!(f, ",")?;
-                    }
-                    write!(f, "\n")?;
-                }
-                write!(f, "{: <1$}]", "", tab_width)?;
-            }
-            Self::Object(obj) => {
-                let tab_width = level * 4;
-                write!(f, "{{\n")?;
-                let mut i = 0;
-                for key in obj.keys() {
-                    write!(f, "    {: <1$}", "", tab_width)?;
-                    write!(f, "\"{}\": ", key)?;
-                    obj[key].fmt_recursive(f, level + 1)?;
-                    if i != obj.len() - 1 {
-                        write!(f, ",")?;
-                        i += 1;
-                    }
-                    write!(f, "\n")?;
-                }
-                write!(f, "{: <1$}}}", "", tab_width)?;
-            }
-            Self::Null => { write!(f, "null")?; }
+            Self::Bool(_) => JSONType::Bool,
+            Self::Number(_) => JSONType::Number,
+            Self::String(_) => JSONType::String,
+            Self::Array(_) => JSONType::Array,
+            Self::Object(_) => JSONType::Object,
+            Self::Null => JSONType::Null,
         }
+    }
+
+    /// Whether this value is a `JSONValue::Object`.
+    pub fn is_object(&self) -> bool {
+        matches!(self, Self::Object(_))
+    }
+
+    /// Whether this value is a `JSONValue::Array`.
+    pub fn is_array(&self) -> bool {
+        matches!(self, Self::Array(_))
+    }
 
+    /// Whether this value is a `JSONValue::String`.
+    pub fn is_string(&self) -> bool {
+        matches!(self, Self::String(_))
+    }
+
+    /// Whether this value is a `JSONValue::Number`.
+    pub fn is_number(&self) -> bool {
+        matches!(self, Self::Number(_))
+    }
 
-        Ok(())
+    /// Whether this value is a `JSONValue::Bool`.
+    pub fn is_bool(&self) -> bool {
+        matches!(self, Self::Bool(_))
+    }
+
+    /// Whether this value is `JSONValue::Null`.
+    pub fn is_null(&self) -> bool {
+        matches!(self, Self::Null)
+    }
+
+    /// The number of entries in an object, items in an array, or characters (not bytes) in a
+    /// string.
+    ///
+    /// Returns `Err(ValueError)` naming the type of this value if it is a number, boolean or
+    /// `null`.
+    pub fn len(&self) -> Result<usize> {
+        match self {
+            Self::Object(map) => Ok(map.len()),
+            Self::Array(arr) => Ok(arr.len()),
+            Self::String(s) => Ok(s.chars().count()),
+            other => Err(JSONError::ValueError(format!("expected object, array or string, found {:?}", other.name()))),
+        }
+    }
+
+    /// Whether this value is an empty object, array or string. Numbers, booleans and `null`
+    /// have no length, and are never empty.
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Self::Object(map) => map.is_empty(),
+            Self::Array(arr) => arr.is_empty(),
+            Self::String(s) => s.is_empty(),
+            _ => false,
+        }
+    }
+
+    /// The entries of this value if it is a `JSONValue::Object`.
+    pub fn as_object(&self) -> Option<&Map> {
+        match self {
+            Self::Object(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    /// The items of this value if it is a `JSONValue::Array`.
+    pub fn as_array(&self) -> Option<&Vec<JSONValue>> {
+        match self {
+            Self::Array(arr) => Some(arr),
+            _ => None,
+        }
+    }
+
+    /// The entries of this value if it is a `JSONValue::Object`, to edit in place.
+    pub fn as_object_mut(&mut self) -> Option<&mut Map> {
+        match self {
+            Self::Object(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    /// The items of this value if it is a `JSONValue::Array`, to edit in place.
+    pub fn as_array_mut(&mut self) -> Option<&mut Vec<JSONValue>> {
+        match self {
+            Self::Array(arr) => Some(arr),
+            _ => None,
+        }
+    }
+
+    /// The text of this value if it is a `JSONValue::String`, to edit in place.
+    pub fn as_string_mut(&mut self) -> Option<&mut String> {
+        match self {
+            Self::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// The text of this value if it is a `JSONValue::String`, borrowed rather than cloned as
+    /// casting to a `String` would.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// The number this value holds if it is a `JSONValue::Number`.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// The boolean this value holds if it is a `JSONValue::Bool`.
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    /// Takes the entries out of this value without copying them, if it is a `JSONValue::Object`.
+    ///
+    /// Returns `Err(ValueError)` naming the type of this value otherwise.
+    pub fn into_object(self) -> Result<Map> {
+        match self {
+            Self::Object(map) => Ok(map),
+            other => Err(JSONError::ValueError(format!("expected object, found {:?}", other.name()))),
+        }
+    }
+
+    /// Takes the items out of this value without copying them, if it is a `JSONValue::Array`.
+    ///
+    /// Returns `Err(ValueError)` naming the type of this value otherwise.
+    pub fn into_array(self) -> Result<Vec<JSONValue>> {
+        match self {
+            Self::Array(arr) => Ok(arr),
+            other => Err(JSONError::ValueError(format!("expected array, found {:?}", other.name()))),
+        }
+    }
+
+    /// Takes the text out of this value without copying it, if it is a `JSONValue::String`.
+    ///
+    /// Returns `Err(ValueError)` naming the type of this value otherwise.
+    pub fn into_string(self) -> Result<String> {
+        match self {
+            Self::String(s) => Ok(s),
+            other => Err(JSONError::ValueError(format!("expected string, found {:?}", other.name()))),
+        }
+    }
+
+    /// `Some(())` if this value is `JSONValue::Null`.
+    pub fn as_null(&self) -> Option<()> {
+        match self {
+            Self::Null => Some(()),
+            _ => None,
+        }
+    }
+
+    /// This value, or `fallback` if it is `JSONValue::Null`.
+    pub fn or(self, fallback: JSONValue) -> JSONValue {
+        match self {
+            Self::Null => fallback,
+            other => other,
+        }
+    }
+
+    /// This value, or the result of `fallback` if it is `JSONValue::Null`. `fallback` is only
+    /// called then.
+    pub fn or_else(self, fallback: impl FnOnce() -> JSONValue) -> JSONValue {
+        match self {
+            Self::Null => fallback(),
+            other => other,
+        }
+    }
+
+    /// An Array of `items`, each converted with `JSONValue::from`, e.g. `array_of(vec![1, 2, 3])`.
+    /// Equivalent to collecting them.
+    pub fn array_of<T: Into<JSONValue>>(items: impl IntoIterator<Item = T>) -> JSONValue {
+        items.into_iter().collect()
+    }
+
+    /// An Object of `entries`, with each key and value converted, e.g.
+    /// `object_of([("host", "localhost"), ("scheme", "https")])`. A repeated key keeps the place
+    /// of its first occurrence and the value of its last.
+    pub fn object_of<K: Into<String>, V: Into<JSONValue>>(entries: impl IntoIterator<Item = (K, V)>) -> JSONValue {
+        entries.into_iter().collect()
     }
 
     // used for debug messages
     fn name(&self) -> &'static str {
+        self.json_type().as_str()
+    }
+}
+
+/// # JSONType
+///
+/// The kinds of value a `JSONValue` can be, one for each of its variants.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum JSONType {
+    Object,
+    Array,
+    String,
+    Number,
+    Bool,
+    Null,
+}
+
+impl JSONType {
+    /// The name of this type as error messages give it, e.g. `"boolean"` for `JSONType::Bool`.
+    pub fn as_str(&self) -> &'static str {
         match self {
-            Self::Bool(_) => "boolean",
-            Self::Number(_) => "number",
-            Self::String(_) => "string",
-            Self::Array(_) => "array",
-            Self::Object(_) => "object",
+            Self::Object => "object",
+            Self::Array => "array",
+            Self::String => "string",
+            Self::Number => "number",
+            Self::Bool => "boolean",
             Self::Null => "null",
         }
     }
 }
 
+impl Display for JSONType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 ///////////////////////////////////
 // JSON-to-Rust Type Conversions //
 ///////////////////////////////////
@@ -363,41 +935,131 @@ impl JSONValue {
 /// JSONValues to Rust primitive types.
 pub trait Cast<T> {
     fn cast(&self) -> Result<T>;
+
+    /// Casts like `cast`, but a failed cast's message says where the value is, given as `path`
+    /// from the outermost step in, like `["key \"hosts\"", "index 0"]`. The default appends the
+    /// path to the message `cast` returns.
+    fn cast_at(&self, path: &[&str]) -> Result<T> {
+        self.cast().map_err(|err| match err {
+            JSONError::ValueError(message) if !path.is_empty() => {
+                JSONError::ValueError(format!("{} for {}", message, describe_path(path)))
+            }
+            other => other,
+        })
+    }
+}
+
+/// Names a `path` innermost step first, like `index 0 in key "hosts"`
+fn describe_path(path: &[&str]) -> String {
+    path.iter().rev().copied().collect::<Vec<_>>().join(" in ")
+}
+
+/// The error for a value at `path` that isn't the `expected` type, where `found` describes it
+fn cast_error(expected: &str, path: &[&str], found: std::fmt::Arguments) -> JSONError {
+    JSONError::ValueError(match path {
+        [] => format!("expected {}, found {}", expected, found),
+        path => format!("expected {} for {}, found {}", expected, describe_path(path), found),
+    })
 }
 
 impl Cast<bool> for JSONValue {
     fn cast(&self) -> Result<bool> {
+        self.cast_at(&[])
+    }
+
+    fn cast_at(&self, path: &[&str]) -> Result<bool> {
         match self {
             Self::Bool(b) => Ok(*b),
-            other => Err(JSONError::ValueError(format!("expected boolean, found {:?}", other.name())))
+            other => Err(cast_error("boolean", path, format_args!("{:?}", other.name())))
         }
     }
 }
 
 impl Cast<f64> for JSONValue {
     fn cast(&self) -> Result<f64> {
+        self.cast_at(&[])
+    }
+
+    fn cast_at(&self, path: &[&str]) -> Result<f64> {
         match self {
             Self::Number(v) => Ok(*v),
-            other => Err(JSONError::ValueError(format!("expected number, found {:?}", other.name())))
+            other => Err(cast_error("number", path, format_args!("{:?}", other.name())))
         }
     }
 }
 
 impl Cast<String> for JSONValue {
     fn cast(&self) -> Result<String> {
+        self.cast_at(&[])
+    }
+
+    fn cast_at(&self, path: &[&str]) -> Result<String> {
         match self {
             Self::String(s) => Ok(s.clone()),
-            other => Err(JSONError::ValueError(format!("expected string, found {:?}", other.name())))
+            other => Err(cast_error("string", path, format_args!("{:?}", other.name())))
         }
     }
 }
 
+// an Array casts when each of its elements does, and the error for the first one that doesn't
+// gives its index; the index is only formatted once an element fails
+impl<T> Cast<Vec<T>> for JSONValue where JSONValue: Cast<T> {
+    fn cast(&self) -> Result<Vec<T>> {
+        Cast::<Vec<T>>::cast_at(self, &[])
+    }
+
+    fn cast_at(&self, path: &[&str]) -> Result<Vec<T>> {
+        match self {
+            Self::Array(items) => items.iter().enumerate().map(|(i, item)| {
+                item.cast().or_else(|_| {
+                    let index = format!("index {}", i);
+                    item.cast_at(&[path, &[index.as_str()]].concat())
+                })
+            }).collect(),
+            other => Err(cast_error("array", path, format_args!("{:?}", other.name())))
+        }
+    }
+}
+
+/// Like `Cast`, but converting numbers with `as`, the way `Cast` once did: integers saturate at
+/// the bounds of their type and drop any fraction, and an `f32` may round to infinity.
+pub trait CastLossy<T> {
+    fn cast_lossy(&self) -> Result<T>;
+}
+
+// integer casts succeed only for integral numbers in the type's range, so nothing is truncated or
+// wrapped; the range runs up to but excluding the power of two past `MAX`, which `f64` holds exactly
 macro_rules! impl_cast_int {
     {$($type_name:ty) +} => {
         $(impl Cast<$type_name> for JSONValue {
             fn cast(&self) -> crate::json::Result<$type_name> {
+                self.cast_at(&[])
+            }
+
+            fn cast_at(&self, path: &[&str]) -> crate::json::Result<$type_name> {
+                let v: f64 = self.cast_at(path)?;
+                let signed = <$type_name>::MIN != 0;
+                let upper = 2f64.powi(<$type_name>::BITS as i32 - signed as i32);
+                let lower = if signed { -upper } else { 0.0 };
+                if v < lower || v >= upper {
+                    Err(cast_error(stringify!($type_name), path, format_args!("{}, which is out of range", v)))
+                } else if v.fract() != 0.0 {
+                    Err(cast_error(stringify!($type_name), path, format_args!("{}, which is not an integer", v)))
+                } else {
+                    Ok(v as $type_name)
+                }
+            }
+        })+
+        impl_cast_lossy!($($type_name)+);
+    }
+}
+
+macro_rules! impl_cast_lossy {
+    {$($type_name:ty) +} => {
+        $(impl CastLossy<$type_name> for JSONValue {
+            fn cast_lossy(&self) -> crate::json::Result<$type_name> {
                 match self {
-                    Self::Number(v) => Ok(v.clone() as $type_name),
+                    Self::Number(v) => Ok(*v as $type_name),
                     other => Err(JSONError::ValueError(format!("expected number, found {:?}", other.name()))),
                 }
             }
@@ -405,7 +1067,50 @@ macro_rules! impl_cast_int {
     }
 }
 
-impl_cast_int!(i8 i16 i32 i64 i128 isize u8 u16 u32 u64 u128 usize f32);
+impl_cast_int!(i8 i16 i32 i64 i128 isize u8 u16 u32 u64 u128 usize);
+impl_cast_lossy!(f32 f64);
+
+// an f32 cast only fails when the number is too large for an f32; precision is lost silently
+impl Cast<f32> for JSONValue {
+    fn cast(&self) -> Result<f32> {
+        self.cast_at(&[])
+    }
+
+    fn cast_at(&self, path: &[&str]) -> Result<f32> {
+        let v: f64 = self.cast_at(path)?;
+        match v as f32 {
+            rounded if rounded.is_infinite() && v.is_finite() => {
+                Err(cast_error("f32", path, format_args!("{}, which is out of range", v)))
+            }
+            rounded => Ok(rounded),
+        }
+    }
+}
+
+// consuming conversions, equivalent to JSONValue::into_object and friends
+impl TryFrom<JSONValue> for Map {
+    type Error = JSONError;
+
+    fn try_from(value: JSONValue) -> std::result::Result<Self, Self::Error> {
+        value.into_object()
+    }
+}
+
+impl TryFrom<JSONValue> for Vec<JSONValue> {
+    type Error = JSONError;
+
+    fn try_from(value: JSONValue) -> std::result::Result<Self, Self::Error> {
+        value.into_array()
+    }
+}
+
+impl TryFrom<JSONValue> for String {
+    type Error = JSONError;
+
+    fn try_from(value: JSONValue) -> std::result::Result<Self, Self::Error> {
+        value.into_string()
+    }
+}
 
 ///////////////////////////////////
 // Rust-to-JSON Type Conversions //
@@ -416,9 +1121,7 @@ impl TryFrom<Vec<u8>> for JSONValue {
     type Error = JSONError;
 
     fn try_from(value: Vec<u8>) -> std::result::Result<Self, Self::Error> {
-        Parser::from(
-            Lexer::new(value).tokenify()?
-        ).parse()
+        Self::from_slice(&value)
     }
 }
 
@@ -450,6 +1153,25 @@ impl From<String> for JSONValue {
     }
 }
 
+impl From<&str> for JSONValue {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_string())
+    }
+}
+
+impl From<Cow<'_, str>> for JSONValue {
+    fn from(value: Cow<'_, str>) -> Self {
+        Self::String(value.into_owned())
+    }
+}
+
+// a char becomes a string of that one character
+impl From<char> for JSONValue {
+    fn from(value: char) -> Self {
+        Self::String(value.to_string())
+    }
+}
+
 impl From<bool> for JSONValue {
     fn from(value: bool) -> Self {
         Self::Bool(value)
@@ -462,6 +1184,35 @@ impl From<Vec<JSONValue>> for JSONValue {
     }
 }
 
+// a HashMap has no order of its own, so the Object gets its keys in the map's iteration order
+impl<K: Into<String>, V: Into<JSONValue>> From<HashMap<K, V>> for JSONValue {
+    fn from(value: HashMap<K, V>) -> Self {
+        value.into_iter().collect()
+    }
+}
+
+impl<K: Into<String>, V: Into<JSONValue>> From<BTreeMap<K, V>> for JSONValue {
+    fn from(value: BTreeMap<K, V>) -> Self {
+        value.into_iter().collect()
+    }
+}
+
+// the pairs become an Object in their order, and a repeated key keeps the place of its first
+// occurrence and the value of its last
+impl<K: Into<String>, V: Into<JSONValue>> From<Vec<(K, V)>> for JSONValue {
+    fn from(value: Vec<(K, V)>) -> Self {
+        value.into_iter().collect()
+    }
+}
+
+
+// `Vec<T>` can't get the same blanket impl, as it would overlap with `From<Vec<JSONValue>>`;
+// `JSONValue::array_of` takes any iterable instead
+impl<T: Clone + Into<JSONValue>> From<&[T]> for JSONValue {
+    fn from(value: &[T]) -> Self {
+        value.iter().cloned().collect()
+    }
+}
 
 /// Constructs a JSON null value. Equivalent to Self::null()
 impl From<()> for JSONValue {
@@ -479,21 +1230,156 @@ impl<T> From<Option<T>> for JSONValue where JSONValue: From<T> {
     }
 } 
 
+// extending with items appends them to an Array, and panics on any other value, as indexing does;
+// `JSONValue::arr_extend` is the checked version
+impl Extend<JSONValue> for JSONValue {
+    fn extend<I: IntoIterator<Item = JSONValue>>(&mut self, iter: I) {
+        self.arr_extend(iter).unwrap()
+    }
+}
+
+// extending with key-value pairs inserts them into an Object, and panics on any other value;
+// `JSONValue::obj_extend` is the checked version
+impl Extend<(String, JSONValue)> for JSONValue {
+    fn extend<I: IntoIterator<Item = (String, JSONValue)>>(&mut self, iter: I) {
+        self.obj_extend(iter).unwrap()
+    }
+}
+
+// collecting items gives an Array, e.g. `(0..10).map(JSONValue::from).collect::<JSONValue>()`
+impl<T: Into<JSONValue>> FromIterator<T> for JSONValue {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self::Array(iter.into_iter().map(Into::into).collect())
+    }
+}
+
+// collecting key-value pairs gives an Object, where a repeated key keeps the place of its first
+// occurrence and the value of its last, as with `Map::insert`
+impl<K: Into<String>, V: Into<JSONValue>> FromIterator<(K, V)> for JSONValue {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Self::Object(iter.into_iter().map(|(key, value)| (key.into(), value.into())).collect())
+    }
+}
+
 ///////////////////////////////
 // JSON-Text I/O Conversions //
 ///////////////////////////////
 
+impl JSONValue {
+    /// Parses a buffer of JSON text according to `options`, without copying it first. This is
+    /// the primitive behind every other way of parsing a buffer.
+    ///
+    /// Tokens are pulled from the lexer as the parser needs them, so no token vector is built.
+    pub fn parse_with(bytes: &[u8], options: &Options) -> Result<Self> {
+        Parser::with_options(Lexer::with_options(bytes, options), options).parse_document()
+    }
+
+    /// Parses a buffer of JSON text with the default [`Options`], without copying it first. This
+    /// is what `TryFrom<Vec<u8>>` and `FromStr` do.
+    pub fn from_slice(bytes: &[u8]) -> Result<Self> {
+        Self::parse_with(bytes, &Options::default())
+    }
+
+    /// Parses JSON text from `reader` a chunk at a time, so the whole input never has to be held
+    /// in memory at once. Wrapping `reader` in a `BufReader` is unnecessary.
+    ///
+    /// Returns:
+    /// - `Err(IoError)` if reading from `reader` fails,
+    /// - any error `from_slice` would return, at the same position,
+    /// - `Ok(JSONValue)` with the parsed document otherwise.
+    pub fn from_reader<R: std::io::Read>(reader: R) -> Result<Self> {
+        Parser::new(ReaderTokens::new(reader)).parse_document()
+    }
+
+    /// Parses JSON text from an iterator of bytes, e.g. the output of a decompressor, pulling
+    /// bytes from it only as the lexer needs them. Like [`JSONValue::from_reader`], only the
+    /// bytes that haven't been lexed yet are buffered, a small chunk at a time.
+    ///
+    /// Returns any error `from_slice` would return, at the same position.
+    pub fn from_byte_iter<I: IntoIterator<Item = u8>>(bytes: I) -> Result<Self> {
+        Parser::new(ReaderTokens::new(IterReader(bytes.into_iter()))).parse_document()
+    }
+
+    /// Reads the file at `path` into memory and parses it.
+    ///
+    /// Returns:
+    /// - `Err(IoError)` naming `path` if the file cannot be read,
+    /// - any error `from_slice` would return on the file's contents,
+    /// - `Ok(JSONValue)` with the parsed document otherwise.
+    pub fn from_file(path: impl AsRef<std::path::Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(|source| JSONError::IoError {
+            message: format!("failed to read {}: {}", path.display(), source),
+            source,
+        })?;
+        Self::from_slice(&bytes)
+    }
+
+    /// Parses the file at `path` through a read-only memory map, so the OS pages the text in as
+    /// the lexer reaches it and can drop it again afterwards, rather than it all being copied
+    /// into memory first.
+    ///
+    /// Returns:
+    /// - `Err(IoError)` if the file cannot be opened or mapped,
+    /// - any error `from_slice` would return on the file's contents,
+    /// - `Ok(JSONValue)` with the parsed document otherwise.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be truncated or modified, by this process or any other, until this
+    /// returns. The lexer reads the mapping as an ordinary byte slice, so a change to the file
+    /// underneath it is undefined behaviour, and reading past the end of a truncated file raises
+    /// `SIGBUS`. Use [`JSONValue::from_file`] when that can't be ruled out.
+    #[cfg(feature = "mmap")]
+    pub unsafe fn from_file_mmap(path: impl AsRef<std::path::Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let map = crate::mmap::Mmap::open(path).map_err(|source| JSONError::IoError {
+            message: format!("failed to map {}: {}", path.display(), source),
+            source,
+        })?;
+        Self::from_slice(map.as_slice())
+    }
+
+    /// Parses JSON text like [`JSONValue::from_slice`], but if the root is an array, its elements
+    /// are parsed in parallel on rayon's global thread pool. The elements are found with a quick
+    /// scan that only balances brackets and braces, so this pays off for arrays of many sizable
+    /// elements.
+    ///
+    /// Documents with any other root, and documents with errors, are parsed on the current
+    /// thread, so the result is always the same as that of `from_slice`.
+    #[cfg(feature = "rayon")]
+    pub fn par_from_slice(bytes: &[u8]) -> Result<Self> {
+        crate::parallel::par_from_slice(bytes)
+    }
+
+    /// Parses JSON text like [`JSONValue::from_str`], but additionally requires the root of the
+    /// document to be an object or an array, as older (RFC 4627) consumers expect. Scalars nested
+    /// inside the root are unaffected.
+    ///
+    /// Returns:
+    /// - `Err(SyntaxError)` if the document root is a string, number, boolean or null,
+    /// - any error `from_str` would return,
+    /// - `Ok(JSONValue)` with the parsed document otherwise.
+    pub fn from_str_strict_root(s: &str) -> Result<Self> {
+        Self::parse_with(s.as_bytes(), &Options::default().strict_root(true))
+    }
+}
+
 // conversion from raw json text into a JSONValue
 impl FromStr for JSONValue {
     type Err = JSONError;
     fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
-        Self::try_from(s.as_bytes().to_vec())
+        Self::from_slice(s.as_bytes())
     }
 }
 
+/// `{}` writes the indented layout of `FormatOptions::default()`, which is what `to_string()`
+/// returns, and `{:#}` writes compact JSON with no whitespace, as `to_string_compact()` does.
 impl Display for JSONValue {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        self.fmt_recursive(f, 0)
+        let opts = if f.alternate() { FormatOptions::compact() } else { FormatOptions::default() };
+        // NaN and the infinities become `null`, since there's no way to report them here
+        self.write_fmt_into(f, &opts)
     }
 }
 
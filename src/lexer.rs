@@ -1,42 +1,158 @@
-use std::u8;
+use std::borrow::Cow;
 
-use crate::json::{self, JSONError};
+use crate::json::{self, JSONError, Options, Position};
+#[cfg(feature = "simd")]
+use crate::simd::{digit_run, plain_string_run, whitespace_run};
+#[cfg(not(feature = "simd"))]
+use scalar::{digit_run, plain_string_run, whitespace_run};
 
+/// # Token
+///
+/// The smallest meaningful pieces of JSON text, as produced by [`Lexer::tokenify`]. Number
+/// literals and unknown tokens keep their source text, and string literals hold their contents
+/// with any escape sequences already decoded. Text is borrowed from the buffer being lexed
+/// whenever it outlives the token and there is nothing to decode.
 #[derive(Clone, Debug, PartialEq)]
-pub enum Token {
+pub enum Token<'a> {
     OpenBrace,
     CloseBrace,
     OpenBracket,
     CloseBracket,
     Colon,
     Comma,
-    StringLiteral(String),
-    NumericLiteral(String),
+    StringLiteral(Cow<'a, str>),
+    NumericLiteral(Cow<'a, str>),
     True,
     False,
     Null,
-    Unknown(String),
+    Unknown(Cow<'a, str>),
 }
 
+impl Token<'_> {
+    /// Copies the text of a literal out of the buffer it borrows from, if any.
+    pub fn into_owned(self) -> Token<'static> {
+        match self {
+            Self::OpenBrace => Token::OpenBrace,
+            Self::CloseBrace => Token::CloseBrace,
+            Self::OpenBracket => Token::OpenBracket,
+            Self::CloseBracket => Token::CloseBracket,
+            Self::Colon => Token::Colon,
+            Self::Comma => Token::Comma,
+            Self::StringLiteral(text) => Token::StringLiteral(Cow::Owned(text.into_owned())),
+            Self::NumericLiteral(text) => Token::NumericLiteral(Cow::Owned(text.into_owned())),
+            Self::True => Token::True,
+            Self::False => Token::False,
+            Self::Null => Token::Null,
+            Self::Unknown(text) => Token::Unknown(Cow::Owned(text.into_owned())),
+        }
+    }
+}
+
+/// The UTF-8 byte order mark, which some tools write at the start of text files.
+pub(crate) const BOM: [u8; 3] = [0xEF, 0xBB, 0xBF];
+
+/// # Span
+///
+/// The half-open byte range `start..end` of the source buffer that a token was read from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    /// The number of characters in the range, which is how many columns it takes up
+    pub chars: usize,
+}
+
+impl Span {
+    pub fn range(&self) -> std::ops::Range<usize> {
+        self.start..self.end
+    }
+}
+
+/// # TokenPos
+///
+/// A token along with the position of its first byte and the span of source text it covers.
 #[derive(Clone, Debug)]
-pub struct TokenPos(pub Token, pub usize, pub usize);
+pub struct TokenPos<'a>(pub Token<'a>, pub Position, pub Span);
 
-pub struct Lexer {
-    buffer: Vec<u8>,
+impl TokenPos<'_> {
+    /// The position just past the last character of the token.
+    pub fn end(&self) -> Position {
+        // tokens never span lines, so the end is on the same line as the start
+        Position { line: self.1.line, column: self.1.column + self.2.chars, offset: self.2.end }
+    }
+
+    /// Copies the text of the token out of the buffer it borrows from, if any.
+    pub fn into_owned(self) -> TokenPos<'static> {
+        TokenPos(self.0.into_owned(), self.1, self.2)
+    }
+}
+
+/// # Lexer
+///
+/// Splits a buffer of JSON text into [`Token`]s. Insignificant whitespace is skipped and does not
+/// appear in the output, but every token records exactly which bytes it came from.
+pub struct Lexer<'a> {
+    buffer: &'a [u8],
     pos: usize,
     marker: usize, 
     line: usize,
     column: usize,
+    /// Set once the token iterator has returned an error
+    failed: bool,
+    /// Offset of the first byte of `buffer` in the whole input, when lexing it a chunk at a time
+    base: usize,
+    /// Whether more input may follow the buffer, in which case a token that runs into its end
+    /// is left for the next chunk instead of being lexed or reported as an error
+    partial: bool,
+    /// Extensions to the JSON grammar; only `comments` and `lenient_numbers` concern the lexer
+    options: Options,
 }
 
-impl Lexer {
-    pub fn new(buffer: Vec<u8>) -> Lexer {
+impl<'a> Lexer<'a> {
+    pub fn new(buffer: &'a [u8]) -> Lexer<'a> {
+        Self::with_options(buffer, &Options::default())
+    }
+
+    /// Creates a lexer that accepts the extensions enabled in `options`.
+    pub fn with_options(buffer: &'a [u8], options: &Options) -> Lexer<'a> {
+        // skip a UTF-8 byte order mark; it isn't part of the document, so the first token still
+        // sits at line 1, column 1
+        let start = if buffer.starts_with(&BOM) { BOM.len() } else { 0 };
         Lexer {
             buffer,
-            pos: 0,
-            marker: 0,
+            pos: start,
+            marker: start,
             line: 1,
             column: 1,
+            failed: false,
+            base: 0,
+            partial: false,
+            options: options.clone(),
+        }
+    }
+
+    /// Rewinds the lexer to the start of `buffer`, keeping its options, so one lexer can be used
+    /// for several documents in turn. [`Session`](crate::json::Session) does the same for
+    /// documents of any lifetime, and keeps its token vector's allocation between them.
+    pub fn reset(&mut self, buffer: &'a [u8]) {
+        let options = std::mem::take(&mut self.options);
+        *self = Self::with_options(buffer, &options);
+    }
+
+    /// Creates a lexer for one chunk of a larger input, whose first byte sits at `start`. When
+    /// `partial` is set, [`Lexer::next_token`] returns `Ok(None)` as soon as the next token could
+    /// continue past the end of the chunk, and [`Lexer::offset`] tells where that token begins.
+    pub(crate) fn resume(buffer: &'a [u8], start: Position, partial: bool) -> Lexer<'a> {
+        Lexer {
+            buffer,
+            pos: 0,
+            marker: 0,
+            line: start.line,
+            column: start.column,
+            failed: false,
+            base: start.offset,
+            partial,
+            options: Options::default(),
         }
     }
 
@@ -49,11 +165,36 @@ impl Lexer {
         self.buffer[self.marker]
     }
 
+    /// The position of the byte currently under the lexer
+    #[inline]
+    pub(crate) fn current_position(&self) -> Position {
+        Position { line: self.line, column: self.column, offset: self.base + self.pos }
+    }
+
+    /// The span of the `len` bytes starting at the current position
+    #[inline]
+    fn span(&self, len: usize) -> Span {
+        Span {
+            start: self.base + self.pos,
+            end: self.base + self.pos + len,
+            chars: chars(&self.buffer[self.pos..self.pos + len]),
+        }
+    }
+
+    fn error(&self, message: String) -> JSONError {
+        JSONError::LexerError { message, position: self.current_position() }
+    }
+
+    /// The error for a token that runs off the end of the buffer, pointing just past its last byte
+    fn eof_error(&self, message: String) -> JSONError {
+        JSONError::UnexpectedEof { message, position: self.position_at(self.buffer.len()) }
+    }
+
     /// Advance lexer by `len` bytes, adjusting column and line positions as necessary
     fn advance(&mut self, len: usize) -> json::Result<()> {
         // err if out of bounds
         if self.pos + len > self.buffer.len() {
-            return Err(JSONError::SyntaxError(
+            return Err(self.error(
                 format!(
                     "new position {} out of bounds for buffer length {}",
                     self.pos + len,
@@ -62,29 +203,27 @@ impl Lexer {
             ));
         }
 
-        // find locations of all the line breaks
-        let mut line_breaks: Vec<usize> = vec![];
-        for i in self.pos..self.pos + len {
-            if self.buffer[i] == b'\n' {
-                line_breaks.push(i);
+        // count the line breaks, and find the last one
+        let mut last_break = None;
+        for (i, &b) in self.buffer[self.pos..self.pos + len].iter().enumerate() {
+            if b == b'\n' {
+                self.line += 1;
+                last_break = Some(i);
             }
         }
 
+        let advanced = &self.buffer[self.pos..self.pos + len];
+        match last_break {
+            // set column pos to the number of characters since the last line break
+            Some(i) => self.column = 1 + chars(&advanced[i + 1..]),
+            // advance column position
+            None => self.column += chars(advanced),
+        }
+
         // advance raw character position
         self.pos += len;
         self.marker = self.pos;
 
-        // if line breaks were found...
-        if line_breaks.len() > 0 {
-            // increment line count by number of '\n' chars found
-            self.line += line_breaks.len();
-            // set column pos to the offset from the last line break
-            self.column = self.pos - line_breaks.pop().unwrap();
-        } else {
-            // advance column position
-            self.column += len;
-        }
-
         Ok(())
     }
 
@@ -93,10 +232,10 @@ impl Lexer {
     fn seek(&mut self, codepoint: u8) -> json::Result<()> {
         // this ensures that we don't select the current position
         self.marker = self.pos + 1;
-        while self.mark() != codepoint {
+        while self.peek_marker() != Some(codepoint) {
             self.marker += 1;
             if self.marker >= self.buffer.len() {
-                return Err(JSONError::SyntaxError(
+                return Err(self.eof_error(
                     format!(
                         "codepoint {} never found",
                         codepoint as char,
@@ -110,183 +249,448 @@ impl Lexer {
         Ok(())
     }
 
-    fn seek_in(&mut self, low: u8, high: u8) {
-        while self.marker < self.buffer.len() && self.mark() >= low && self.mark() <= high {
+    /// Moves the marker just past the closing quote of the string literal starting at `pos`,
+    /// skipping over escape sequences. A line break inside the string is reported at the opening
+    /// quote, since that is the useful location when the closing quote is missing; running off
+    /// the end of the buffer is reported there, naming where the string started.
+    fn seek_string_end(&mut self) -> json::Result<()> {
+        self.marker = self.pos + 1;
+        loop {
+            self.marker += plain_string_run(&self.buffer[self.marker..]);
+            if self.marker >= self.buffer.len() {
+                return Err(self.eof_error(format!("unterminated string (opened at {})", self.current_position())));
+            }
+            match self.mark() {
+                b'"' => break,
+                b'\\' => {
+                    if self.marker + 1 >= self.buffer.len() {
+                        self.marker = self.buffer.len();
+                        return Err(self.eof_error(format!(
+                            "unterminated escape sequence in string (opened at {})", self.current_position(),
+                        )));
+                    }
+                    self.marker += 2;
+                }
+                // a raw line break most likely means the closing quote is missing
+                b'\n' | b'\r' => return Err(self.error("unterminated string".to_string())),
+                // RFC 8259 requires control characters to be escaped
+                0x00..=0x1F => return Err(self.marker_error(format!(
+                    "unescaped control character {:?} in string", self.mark() as char,
+                ))),
+                _ => self.marker += 1,
+            }
+        }
+        // to include the closing quote
+        self.marker += 1;
+
+        Ok(())
+    }
+
+    /// Moves the marker past a run of ASCII letters, the only bytes that can make up a keyword.
+    fn seek_letters(&mut self) {
+        while self.marker < self.buffer.len() && self.mark().is_ascii_alphabetic() {
             self.marker += 1;
         }
     }
 
-    fn seek_all(&mut self, values: &[u8]) {
-        while self.marker < self.buffer.len() {
-            if values.iter().any(|&val| val == self.mark()) {
+    /// Moves the marker past the number starting at `pos`, enforcing the RFC 8259 grammar
+    /// `-?(0|[1-9][0-9]*)(\.[0-9]+)?([eE][+-]?[0-9]+)?`. Errors point at the offending byte.
+    fn seek_number(&mut self) -> json::Result<()> {
+        self.marker = self.pos;
+
+        let lenient = self.options.lenient_numbers;
+        if self.peek_marker() == Some(b'-') || (lenient && self.peek_marker() == Some(b'+')) {
+            self.marker += 1;
+        }
+
+        // integer part
+        match self.peek_marker() {
+            Some(b'0') => {
+                self.marker += 1;
+                if matches!(self.peek_marker(), Some(b'0'..=b'9')) {
+                    if !lenient {
+                        return Err(self.marker_error("leading zeros are not allowed in numbers".to_string()));
+                    }
+                    self.seek_digits();
+                }
+            }
+            Some(b'1'..=b'9') => self.seek_digits(),
+            _ => return Err(self.number_error("a digit")),
+        }
+
+        // fraction part
+        if self.peek_marker() == Some(b'.') {
+            self.marker += 1;
+            if !matches!(self.peek_marker(), Some(b'0'..=b'9')) {
+                return Err(self.number_error("a digit after the decimal point"));
+            }
+            self.seek_digits();
+        }
+
+        // exponent part
+        if matches!(self.peek_marker(), Some(b'e' | b'E')) {
+            self.marker += 1;
+            if matches!(self.peek_marker(), Some(b'+' | b'-')) {
                 self.marker += 1;
-            } else {
-                break;
             }
+            if !matches!(self.peek_marker(), Some(b'0'..=b'9')) {
+                return Err(self.number_error("a digit in the exponent"));
+            }
+            self.seek_digits();
+        }
+
+        // anything number-like left over means the number was malformed, e.g. `1.2.3` or `1e5e5`
+        if matches!(self.peek_marker(), Some(b'0'..=b'9' | b'.' | b'e' | b'E' | b'+' | b'-')) {
+            return Err(self.number_error("the end of the number"));
+        }
+
+        Ok(())
+    }
+
+    fn seek_digits(&mut self) {
+        self.marker += digit_run(&self.buffer[self.marker..]);
+    }
+
+    #[inline]
+    fn peek_marker(&self) -> Option<u8> {
+        self.buffer.get(self.marker).copied()
+    }
+
+    /// The position of the byte at `offset`, which must not be before `pos`.
+    fn position_at(&self, offset: usize) -> Position {
+        let skipped = &self.buffer[self.pos..offset];
+        match skipped.iter().rposition(|&b| b == b'\n') {
+            Some(last_break) => Position {
+                line: self.line + skipped.iter().filter(|&&b| b == b'\n').count(),
+                column: 1 + chars(&skipped[last_break + 1..]),
+                offset: self.base + offset,
+            },
+            None => Position { line: self.line, column: self.column + chars(skipped), offset: self.base + offset },
         }
     }
 
-    fn highlighted(&self) -> &str {
-        core::str::from_utf8(&self.buffer[self.pos..self.marker]).unwrap()
+    fn error_at(&self, offset: usize, message: String) -> JSONError {
+        JSONError::LexerError { message, position: self.position_at(offset) }
     }
 
-    pub fn tokenify(&mut self) -> json::Result<Vec<TokenPos>> {
-        // quick and dirty; will switch to better system later
-        const ALPHABET: [u8; 52] = [
-            b'a', b'b', b'c', b'd', b'e', b'f', b'g',
-            b'h', b'i', b'j', b'k', b'l', b'm', b'n',
-            b'o', b'p', b'q', b'r', b's', b't', b'u',
-            b'v', b'w', b'x', b'y', b'z',
-            b'A', b'B', b'C', b'D', b'E', b'F', b'G',
-            b'H', b'I', b'J', b'K', b'L', b'M', b'N',
-            b'O', b'P', b'Q', b'R', b'S', b'T', b'U',
-            b'V', b'W', b'X', b'Y', b'Z',
-        ];
+    fn marker_error(&self, message: String) -> JSONError {
+        self.error_at(self.marker, message)
+    }
 
-        self.pos = 0;
+    fn number_error(&self, expected: &str) -> JSONError {
+        match self.peek_marker() {
+            Some(c) => self.marker_error(format!("invalid character '{}' in number, expected {}", c as char, expected)),
+            None => self.eof_error(format!("unexpected end of number, expected {}", expected)),
+        }
+    }
+
+    /// The text between `pos` and the marker. Only string literals can contain non-ASCII bytes,
+    /// so this is where invalid UTF-8 gets reported, at the offset of the first bad sequence.
+    fn highlighted(&self) -> json::Result<&'a str> {
+        core::str::from_utf8(&self.buffer[self.pos..self.marker]).map_err(|e| {
+            self.error_at(self.pos + e.valid_up_to(), "invalid UTF-8 sequence".to_string())
+        })
+    }
 
-        let mut tokens: Vec<TokenPos> = vec![];
+    /// The contents of the string literal `literal`, which starts at `pos`, with its escape
+    /// sequences decoded. Contents without any are borrowed as they are.
+    fn string_contents(&self, literal: &'a str) -> json::Result<Cow<'a, str>> {
+        let contents = &literal[1..literal.len() - 1];
+        if !contents.contains('\\') {
+            return Ok(Cow::Borrowed(contents));
+        }
+        unescape(contents, self.position_at(self.pos + 1)).map(Cow::Owned)
+    }
+
+    /// The offset of the first byte the lexer has not consumed yet.
+    pub fn offset(&self) -> usize {
+        self.base + self.pos
+    }
+
+    /// Lexes the rest of the buffer into a vector of tokens.
+    pub fn tokenify(&mut self) -> json::Result<Vec<TokenPos<'a>>> {
+        let mut tokens: Vec<TokenPos<'a>> = vec![];
+        self.tokenify_into(&mut tokens)?;
+        Ok(tokens)
+    }
+
+    /// Like [`Lexer::tokenify`], but appends the tokens to an existing vector so its allocation
+    /// can be reused between documents.
+    pub fn tokenify_into(&mut self, tokens: &mut Vec<TokenPos<'a>>) -> json::Result<()> {
+        while let Some(token) = self.next_token()? {
+            tokens.push(token);
+        }
+        Ok(())
+    }
 
+    /// Whether every byte of the buffer has been consumed.
+    pub(crate) fn is_at_end(&self) -> bool {
+        self.pos == self.buffer.len()
+    }
+
+    /// Consumes the whitespace under the lexer, if any, along with comments if they are allowed.
+    pub(crate) fn skip_whitespace(&mut self) -> json::Result<()> {
         loop {
-            if self.pos == self.buffer.len() {
-                break Ok(tokens);
+            let len = whitespace_run(&self.buffer[self.pos..]);
+            self.advance(len)?;
+
+            if !self.options.comments || self.buffer.get(self.pos) != Some(&b'/') {
+                return Ok(());
             }
-            match self.curr() {
-                b'{' => {
-                    tokens.push(TokenPos(Token::OpenBrace, self.line, self.column));
-                    self.advance(1)?;
-                },
-                b'}' => {
-                    tokens.push(TokenPos(Token::CloseBrace, self.line, self.column));
-                    self.advance(1)?;
-                },
-                b'[' => {
-                    tokens.push(TokenPos(Token::OpenBracket, self.line, self.column));
-                    self.advance(1)?;
-                },
-                b']' => {
-                    tokens.push(TokenPos(Token::CloseBracket, self.line, self.column));
-                    self.advance(1)?;
-                },
-                b':' => {
-                    tokens.push(TokenPos(Token::Colon, self.line, self.column));
-                    self.advance(1)?;
-                },
-                b',' => {
-                    tokens.push(TokenPos(Token::Comma, self.line, self.column));
-                    self.advance(1)?;
-                },
-                b' ' => {
-                    self.advance(1)?;
-                },
-                b'\n' => {
-                    self.advance(1)?;
+            let rest = &self.buffer[self.pos..];
+            let len = match rest.get(1) {
+                // a line comment runs up to the line break, which is whitespace
+                Some(b'/') => rest.iter().position(|&b| b == b'\n').unwrap_or(rest.len()),
+                Some(b'*') => match rest[2..].windows(2).position(|w| w == b"*/") {
+                    Some(end) => end + 4,
+                    None => return Err(self.eof_error(format!("unterminated block comment (opened at {})", self.current_position()))),
                 },
-                b'"' => {
-                    // this ensures that we don't select the current position
-                    self.marker = self.pos + 1;
-                    loop {
-                        self.marker += 1;
-                        if self.mark() == b'"' {
-                            if self.buffer[self.marker - 1] != b'\\' {
-                                break;
-                            }
-                        }
-                        if self.marker >= self.buffer.len() {
-                            return Err(JSONError::SyntaxError(
-                                "ending \" never found".to_string()
-                            ));
+                // a lone slash is reported as an invalid character by next_token
+                _ => return Ok(()),
+            };
+            self.advance(len)?;
+        }
+    }
+
+    /// Whether the token being seeked ran into the end of a chunk that more input may follow.
+    #[inline]
+    fn incomplete(&self) -> bool {
+        self.partial && self.marker >= self.buffer.len()
+    }
+
+    /// Reads the next token, skipping any whitespace before it. Returns `Ok(None)` once the end
+    /// of the buffer is reached.
+    pub fn next_token(&mut self) -> json::Result<Option<TokenPos<'a>>> {
+        self.skip_whitespace()?;
+        if self.is_at_end() {
+            return Ok(None);
+        }
+        let token = match self.curr() {
+            b'{' => {
+                self.marker = self.pos + 1;
+                Token::OpenBrace
+            },
+            b'}' => {
+                self.marker = self.pos + 1;
+                Token::CloseBrace
+            },
+            b'[' => {
+                self.marker = self.pos + 1;
+                Token::OpenBracket
+            },
+            b']' => {
+                self.marker = self.pos + 1;
+                Token::CloseBracket
+            },
+            b':' => {
+                self.marker = self.pos + 1;
+                Token::Colon
+            },
+            b',' => {
+                self.marker = self.pos + 1;
+                Token::Comma
+            },
+            b'"' => {
+                let seeked = self.seek_string_end();
+                if seeked.is_err() && self.incomplete() {
+                    return Ok(None);
+                }
+                seeked?;
+                Token::StringLiteral(self.string_contents(self.highlighted()?)?)
+            },
+            b'a'..=b'z' | b'A'..=b'Z' => {
+                self.seek_letters();
+                if self.incomplete() {
+                    return Ok(None);
+                }
+                match self.highlighted()? {
+                    "true" => Token::True,
+                    "false" => Token::False,
+                    "null" => Token::Null,
+                    // a keyword cut short by the end of the input, e.g. `tru`
+                    other if self.marker == self.buffer.len() => {
+                        match ["true", "false", "null"].iter().find(|keyword| keyword.starts_with(other)) {
+                            Some(keyword) => return Err(self.eof_error(format!("unexpected end of input, expected `{}`", keyword))),
+                            None => Token::Unknown(Cow::Borrowed(other)),
                         }
                     }
-                    // to include seeked-for character
-                    self.marker += 1;
-                    tokens.push(TokenPos(
-                        Token::StringLiteral(self.highlighted().to_owned()),
-                        self.line,
-                        self.column,
-                    ));
-                    self.advance(self.marker - self.pos)?;
-                },
-                b't' => {
-                    self.seek_all(&ALPHABET);
-
-                    if self.highlighted() == "true" {
-                        tokens.push(TokenPos(Token::True, self.line, self.column));
-                    } else {
-                        tokens.push(TokenPos(
-                            Token::Unknown(self.highlighted().to_owned()),
-                            self.line,
-                            self.column,
-                        ));
-                    }
+                    other => Token::Unknown(Cow::Borrowed(other)),
+                }
+            },
+            b'0'..=b'9' | b'-' | b'+' | b'.' => {
+                let seeked = self.seek_number();
+                if self.incomplete() {
+                    return Ok(None);
+                }
+                seeked?;
+                Token::NumericLiteral(Cow::Borrowed(self.highlighted()?))
+            },
+            _ => {
+                return Err(self.error(
+                    format!("invalid character '{}'", self.curr() as char)
+                ));
+            }
+        };
 
-                    self.advance(self.marker - self.pos)?;
-                },
-                b'f' => {
-                    self.seek_all(&ALPHABET);
-
-                    if self.highlighted() == "false" {
-                        tokens.push(TokenPos(Token::False, self.line, self.column));
-                    } else {
-                        tokens.push(TokenPos(
-                            Token::Unknown(self.highlighted().to_owned()),
-                            self.line,
-                            self.column,
-                        ));
-                    }
+        let token = TokenPos(token, self.current_position(), self.span(self.marker - self.pos));
+        self.advance(self.marker - self.pos)?;
+        Ok(Some(token))
+    }
 
-                    self.advance(self.marker - self.pos)?;
-                },
-                b'n' => {
-                    self.seek_all(&ALPHABET);
-
-                    if self.highlighted() == "null" {
-                        tokens.push(TokenPos(Token::Null, self.line, self.column));
-                    } else {
-                        tokens.push(TokenPos(
-                            Token::Unknown(self.highlighted().to_owned()),
-                            self.line,
-                            self.column,
-                        ));
-                    }
+    /// Moves past the token that [`Lexer::next_token`] just failed on, so lexing can carry on
+    /// after it. A broken string literal is cut off at its closing quote, or else at the end of
+    /// its line, and handed back with any invalid UTF-8 replaced, and its escape sequences decoded
+    /// unless one of them is invalid; anything else is skipped up to the next whitespace or
+    /// structural character.
+    pub(crate) fn recover(&mut self) -> Option<TokenPos<'a>> {
+        if self.is_at_end() {
+            return None;
+        }
+        if self.curr() != b'"' {
+            let len = 1 + self.buffer[self.pos + 1..].iter()
+                .take_while(|b| !matches!(b, b' ' | b'\t' | b'\n' | b'\r' | b'{' | b'}' | b'[' | b']' | b':' | b',' | b'"'))
+                .count();
+            self.advance(len).ok()?;
+            return None;
+        }
 
-                    self.advance(self.marker - self.pos)?;
-                },
-                b'A'..=b'z' => {
-                    self.seek_in(b'A', b'z');
-                    tokens.push(TokenPos(
-                        Token::Unknown(self.highlighted().to_owned()),
-                        self.line,
-                        self.column,
-                    ));
-                    self.advance(self.marker - self.pos)?;
-                },
-                b'0'..=b'9' | b'-' | b'+' | b'.' => {
-                    const NUM_CHARS: [u8; 15] = [
-                        b'0', b'1', b'2', b'3',
-                        b'4', b'5', b'6', b'7',
-                        b'8', b'9', b'.', b'e',
-                        b'E', b'+', b'-',
-                    ];
-                    self.seek_all(&NUM_CHARS);
-                    tokens.push(TokenPos(
-                        Token::NumericLiteral(self.highlighted().to_owned()),
-                        self.line,
-                        self.column,
-                    ));
-                    self.advance(self.marker - self.pos)?;
-                },
-                _ => {
-                    break Err(JSONError::SyntaxError(
-                        format!(
-                            "invalid character '{}' at line {}, column {}",
-                            self.curr() as char,
-                            self.line,
-                            self.column,
-                        )
-                    ));
-                }
+        let mut end = self.pos + 1;
+        let len = loop {
+            match self.buffer.get(end) {
+                Some(b'"') => break end + 1 - self.pos,
+                Some(b'\\') if !matches!(self.buffer.get(end + 1), None | Some(b'\n' | b'\r')) => end += 2,
+                // a backslash right before the cut escapes nothing, so it is left out
+                Some(b'\\') => break end + 1 - self.pos,
+                None | Some(b'\n' | b'\r') => break end - self.pos,
+                Some(_) => end += 1,
             }
+        };
+        let raw = String::from_utf8_lossy(&self.buffer[self.pos + 1..end]);
+        let contents = unescape(&raw, self.position_at(self.pos + 1)).unwrap_or_else(|_| raw.into_owned());
+        let token = TokenPos(
+            Token::StringLiteral(Cow::Owned(contents)),
+            self.current_position(),
+            self.span(len),
+        );
+        self.advance(len).ok()?;
+        Some(token)
+    }
+}
+
+/// Lazily produces the tokens of the buffer. After an error, the iterator ends.
+impl<'a> Iterator for Lexer<'a> {
+    type Item = json::Result<TokenPos<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let next = self.next_token().transpose();
+        self.failed = matches!(next, Some(Err(_)));
+        next
+    }
+}
+
+/// The number of characters in `bytes`, counting the first byte of each UTF-8 sequence. Columns
+/// count characters rather than bytes, to match what a text editor shows.
+#[inline]
+pub(crate) fn chars(bytes: &[u8]) -> usize {
+    bytes.iter().filter(|&&b| b & 0xC0 != 0x80).count()
+}
+
+/// Byte-at-a-time scanning, which the `simd` feature speeds up and falls back on.
+pub(crate) mod scalar {
+    /// The length of the run of whitespace at the start of `bytes`.
+    pub(crate) fn whitespace_run(bytes: &[u8]) -> usize {
+        bytes.iter().take_while(|b| matches!(b, b' ' | b'\t' | b'\r' | b'\n')).count()
+    }
+
+    /// The length of the run of ASCII digits at the start of `bytes`.
+    pub(crate) fn digit_run(bytes: &[u8]) -> usize {
+        bytes.iter().take_while(|b| b.is_ascii_digit()).count()
+    }
+
+    /// The length of the run of bytes at the start of `bytes` that can appear in a string
+    /// literal as they are, i.e. anything but a quote, a backslash or a control character.
+    pub(crate) fn plain_string_run(bytes: &[u8]) -> usize {
+        bytes.iter().take_while(|&&b| b != b'"' && b != b'\\' && b >= 0x20).count()
+    }
+}
+
+/// Decodes the escape sequences in the contents of a string literal, i.e. the text between its
+/// quotes. `position` is the position of the first byte of `contents`, and is used to point
+/// errors at the exact escape sequence that caused them.
+pub(crate) fn unescape(contents: &str, position: Position) -> json::Result<String> {
+    // string literals never span lines, so every character of them is on the same line
+    let error_at = |index: usize, message: String| JSONError::LexerError {
+        message,
+        position: Position {
+            line: position.line,
+            column: position.column + contents[..index].chars().count(),
+            offset: position.offset + index,
+        },
+    };
+    // reads the four hex digits of a `\u` escape starting at `index`
+    let hex_at = |index: usize| -> json::Result<u16> {
+        let digits = contents.get(index + 2..index + 6)
+            .ok_or_else(|| error_at(index, "incomplete unicode escape, expected 4 hexadecimal digits".to_string()))?;
+        // from_str_radix alone would also accept a sign
+        match u16::from_str_radix(digits, 16) {
+            Ok(code) if digits.bytes().all(|b| b.is_ascii_hexdigit()) => Ok(code),
+            _ => Err(error_at(index, format!("invalid hexadecimal code: {}", digits))),
         }
+    };
+
+    let mut formatted = String::with_capacity(contents.len());
+    let bytes = contents.as_bytes();
+    let mut i = 0;
+    while let Some(run) = bytes[i..].iter().position(|&b| b == b'\\') {
+        // text between escapes is copied in one piece
+        formatted.push_str(&contents[i..i + run]);
+        i += run;
+
+        let Some(&escaped) = bytes.get(i + 1) else {
+            return Err(error_at(i, "unterminated escape sequence in string".to_string()));
+        };
+        let (c, len) = match escaped {
+            b'"' => ('"', 2),
+            b'\\' => ('\\', 2),
+            b'/' => ('/', 2),
+            b'b' => ('\u{0008}', 2),
+            b'f' => ('\u{000c}', 2),
+            b'n' => ('\u{000a}', 2),
+            b'r' => ('\u{000d}', 2),
+            b't' => ('\u{0009}', 2),
+            b'u' => {
+                let code = hex_at(i)?;
+                let low = match contents.get(i + 6..) {
+                    Some(rest) if (0xD800..=0xDBFF).contains(&code) && rest.starts_with("\\u") => hex_at(i + 6).ok(),
+                    _ => None,
+                };
+                // a surrogate is only valid as the high half of a pair, immediately followed by
+                // the escaped low half
+                match char::decode_utf16([code].into_iter().chain(low)).next() {
+                    Some(Ok(c)) if c.len_utf16() == 2 => (c, 12),
+                    Some(Ok(c)) => (c, 6),
+                    _ if code >= 0xDC00 => return Err(error_at(i, format!(
+                        "unpaired low surrogate \\u{:04X}, expected it to follow a high surrogate escape \\uD800-\\uDBFF",
+                        code,
+                    ))),
+                    _ => return Err(error_at(i, format!(
+                        "unpaired high surrogate \\u{:04X}, expected a low surrogate escape \\uDC00-\\uDFFF to follow",
+                        code,
+                    ))),
+                }
+            }
+            _ => {
+                let other = contents[i + 1..].chars().next().unwrap_or(escaped as char);
+                return Err(error_at(i, format!("invalid escape char: {}", other)));
+            }
+        };
+        formatted.push(c);
+        i += len;
     }
+    formatted.push_str(&contents[i..]);
+
+    Ok(formatted)
 }
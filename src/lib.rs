@@ -1,12 +1,42 @@
+mod macros;
 mod lexer;
+mod map;
 mod parser;
+mod display;
+mod format;
+mod canonical;
+mod preview;
+mod case;
+mod patch;
+mod merge;
+mod writer;
+mod pointer;
+mod session;
+mod sync;
+mod events;
+mod options;
+mod minify;
+mod stream;
+mod tolerant;
+mod document;
+#[cfg(feature = "audit")]
+mod audit;
+#[cfg(feature = "mmap")]
+mod mmap;
+#[cfg(feature = "rayon")]
+mod parallel;
+#[cfg(feature = "simd")]
+mod simd;
 pub mod json;
+pub mod compat;
 
 #[cfg(test)]
 mod tests {
-    use std::{collections::HashMap, time};
+    use std::{str::FromStr, time};
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
 
-    use crate::json::{JSONValue, self, Cast};
+    use crate::json::{JSONValue, JSONError, Position, self, Cast};
 
     use super::lexer::Lexer;
 
@@ -19,7 +49,7 @@ mod tests {
     \"baz\": 134.0e-3,
     \"beanz\": $ [false, true]
 }";
-        let mut lexer: Lexer = Lexer::new(new_buf.as_bytes().to_vec());
+        let mut lexer: Lexer = Lexer::new(new_buf.as_bytes());
 
         lexer.tokenify().expect_err("this should error");
     }
@@ -58,7 +88,7 @@ mod tests {
         assert_eq!(a, b);
 
         let bool: bool = value["baz"][0].cast()?;
-        assert_eq!(bool, true);
+        assert!(bool);
         let bool_2: bool = value.get("baz")?.try_index(0)?.cast()?;
         assert_eq!(bool, bool_2);
 
@@ -71,7 +101,7 @@ mod tests {
 
     #[test]
     fn serialize_test() -> json::Result<()> {
-        let mut values: JSONValue = JSONValue::Object(HashMap::new());
+        let mut values: JSONValue = JSONValue::Object(json::Map::new());
         values.obj_insert("foo", JSONValue::from(vec![
             JSONValue::from(23.4),
             JSONValue::from("asdfasdf".to_owned()),
@@ -89,6 +119,10 @@ mod tests {
         null
     ]
 }", format!("{string}"));
+        // the alternate flag picks the compact layout
+        assert_eq!(r#"{"foo":[23.4,"asdfasdf",true,null]}"#, format!("{values:#}"));
+        assert_eq!(values.to_string_compact(), format!("{values:#}"));
+        assert_eq!(string, format!("{values}"));
 
         Ok(())
     }
@@ -105,7 +139,7 @@ mod tests {
         s.push('}');
 
         let t0 = time::Instant::now();
-        let value = JSONValue::try_from(s.as_bytes().to_vec())?;
+        let value = JSONValue::from_slice(s.as_bytes())?;
         let t1 = time::Instant::now();
 
         for i in 0..=2<<16 {
@@ -117,6 +151,4667 @@ mod tests {
         println!("time to complete iteration: {}", (t2-t1).as_secs_f64());
         println!("total time: {}", (t2-t0).as_secs_f64());
 
+        // tokens borrow their text, so lexing only allocates the token vector as it grows
+        let (tokens, allocations) = count_allocations(|| Lexer::new(s.as_bytes()).tokenify());
+        assert_eq!(4 * (2<<16) + 5, tokens?.len());
+        assert!(allocations < 64, "{allocations}");
+
+        // parsing pulls tokens straight from the lexer, skipping that vector altogether
+        use super::parser::Parser;
+        let t0 = time::Instant::now();
+        let (two_phase, two_phase_bytes) = count_allocated_bytes(|| {
+            Parser::from(Lexer::new(s.as_bytes()).tokenify()?).parse_document()
+        });
+        let t1 = time::Instant::now();
+        let (fused, fused_bytes) = count_allocated_bytes(|| JSONValue::from_slice(s.as_bytes()));
+        let t2 = time::Instant::now();
+        println!("two-phase parse: {} bytes in {}s", two_phase_bytes, (t1-t0).as_secs_f64());
+        println!("fused parse: {} bytes in {}s", fused_bytes, (t2-t1).as_secs_f64());
+        assert_eq!(two_phase?, fused?);
+        let token_bytes = (4 * (2<<16) + 5) * std::mem::size_of::<json::TokenPos>();
+        assert!(fused_bytes + token_bytes <= two_phase_bytes, "{fused_bytes} + {token_bytes} > {two_phase_bytes}");
+
+        Ok(())
+    }
+
+    #[test]
+    fn error_position_test() {
+        // invalid character, caught by the lexer
+        let err = JSONValue::try_from(b"{\n    \"foo\": $\n}".to_vec()).unwrap_err();
+        assert!(matches!(err, JSONError::LexerError { .. }));
+        assert_eq!(Some(Position { line: 2, column: 12, offset: 13 }), err.position());
+        assert_eq!("invalid character '$'", err.message());
+
+        // missing colon, caught by the parser
+        let err = JSONValue::try_from(b"{\n    \"foo\": 1,\n    \"bar\" 2\n}".to_vec()).unwrap_err();
+        assert!(matches!(err, JSONError::SyntaxError { .. }));
+        assert_eq!(Some(Position { line: 3, column: 11, offset: 26 }), err.position());
+        assert_eq!("JSON Syntax Error: expected Colon, found NumericLiteral(\"2\") at line 3, column 11", err.to_string());
+
+        // errors unrelated to JSON text have no position
+        assert_eq!(None, JSONValue::Null.get("foo").unwrap_err().position());
+    }
+
+    #[test]
+    fn display_string_test() -> json::Result<()> {
+        let value: JSONValue = "{
+            \"name\": \"quarterly report\",
+            \"revenue\": 1234567.891,
+            \"losses\": -9876,
+            \"owner\": null,
+            \"regions\": [\"north\", {\"south\": 1000, \"tags\": []}],
+            \"audited\": false
+        }".parse()?;
+
+        assert_eq!("\
+audited: false
+losses: -9,876
+name: quarterly report
+owner: —
+regions:
+  - north
+  -
+    south: 1,000
+    tags: (empty)
+revenue: 1,234,567.891
+", value.to_display_string(json::DisplayOptions::default()));
+
+        let opts = json::DisplayOptions {
+            thousands_separator: Some('.'),
+            decimal_separator: ',',
+            decimal_places: Some(2),
+            null_placeholder: "n/a".to_string(),
+            indent: 4,
+        };
+        assert_eq!("\
+audited: false
+losses: -9.876,00
+name: quarterly report
+owner: n/a
+regions:
+    - north
+    -
+        south: 1.000,00
+        tags: (empty)
+revenue: 1.234.567,89
+", value.to_display_string(opts.clone()));
+
+        assert_eq!("0,00", JSONValue::Number(-0.001).to_display_string(opts.clone()));
+        assert_eq!("n/a", JSONValue::Null.to_display_string(opts));
+
+        Ok(())
+    }
+
+    #[test]
+    fn unterminated_string_test() {
+        // a multi-line array of numbers, so nothing after the broken string contains a quote
+        let rows: Vec<String> = (0..20).map(|i| format!("    {i}, {i}.5, -{i}e2,")).collect();
+        let body = rows.join("\n");
+        let assert_start = |source: &str, line: usize, column: usize| {
+            let err = JSONValue::try_from(source.as_bytes().to_vec()).unwrap_err();
+            assert!(matches!(err, JSONError::LexerError { .. }));
+            let position = err.position().unwrap();
+            assert_eq!((line, column), (position.line, position.column), "{err}");
+            assert_eq!(b'"', source.as_bytes()[position.offset]);
+            err
+        };
+
+        // at the start of the document
+        let source = format!("\"[\n{body}\n    0\n]");
+        assert_eq!("unterminated string", assert_start(&source, 1, 1).message());
+
+        // in the middle
+        let source = format!("[\n{body}\n    \"middle,\n{body}\n    0\n]");
+        assert_start(&source, 22, 5);
+
+        // at the end, where the error points at the end of input and names the opening quote
+        let assert_eof = |source: &str, message: &str| {
+            let err = JSONValue::try_from(source.as_bytes().to_vec()).unwrap_err();
+            assert!(matches!(err, JSONError::UnexpectedEof { .. }), "{err}");
+            assert_eq!(message, err.message());
+            assert_eq!(source.len(), err.position().unwrap().offset);
+        };
+        assert_eof(&format!("[\n{body}\n    \"tail"), "unterminated string (opened at line 22, column 5)");
+
+        // cut off in the middle of an escape sequence
+        assert_eof(
+            &format!("[\n{body}\n    \"tail\\"),
+            "unterminated escape sequence in string (opened at line 22, column 5)",
+        );
+
+        // escaped quotes and backslashes don't end or extend a string
+        let value = JSONValue::try_from(b"[\"a\\\\\", \"\\\"\", \"\"]".to_vec()).unwrap();
+        assert_eq!(JSONValue::Array(vec![
+            JSONValue::from("a\\".to_owned()),
+            JSONValue::from("\"".to_owned()),
+            JSONValue::from(String::new()),
+        ]), value);
+    }
+
+    #[test]
+    fn keyword_boundary_test() {
+        let unexpected = |source: &str| {
+            let err = JSONValue::from_str(source).unwrap_err();
+            (err.message().to_owned(), err.position().unwrap().column)
+        };
+
+        // an unknown keyword must not swallow the closing bracket or brace
+        assert_eq!(("unexpected token `nullx`".to_owned(), 2), unexpected("[nullx]"));
+        assert_eq!(("unexpected token `tru`".to_owned(), 7), unexpected("{\"a\": tru}"));
+        assert_eq!(("unexpected token `falsey`".to_owned(), 5), unexpected("[1, falsey]"));
+        assert_eq!(("unexpected token `foo`".to_owned(), 5), unexpected("[1, foo]"));
+
+        // punctuation between 'Z' and 'a' is not part of a keyword
+        let err = JSONValue::from_str("[_foo]").unwrap_err();
+        assert!(matches!(err, JSONError::LexerError { .. }));
+        assert_eq!("invalid character '_'", err.message());
+        let err = JSONValue::from_str("[true^]").unwrap_err();
+        assert_eq!("invalid character '^'", err.message());
+
+        assert_eq!(JSONValue::Array(vec![JSONValue::Null, JSONValue::Bool(true)]), JSONValue::from_str("[null,true]").unwrap());
+    }
+
+    #[test]
+    fn sync_json_test() -> json::Result<()> {
+        const THREADS: usize = 8;
+        const INCREMENTS: usize = 10_000;
+
+        let workers: Vec<JSONValue> = (0..THREADS).map(|_| {
+            let mut worker = JSONValue::Object(json::Map::new());
+            worker.obj_insert("count", JSONValue::from(0)).unwrap();
+            worker
+        }).collect();
+        let mut document = JSONValue::Object(json::Map::new());
+        document.obj_insert("workers", JSONValue::from(workers))?;
+        let document = json::SyncJson::new(document);
+
+        std::thread::scope(|scope| {
+            for id in 0..THREADS {
+                let document = &document;
+                scope.spawn(move || {
+                    let ptr = format!("/workers/{id}/count");
+                    for _ in 0..INCREMENTS {
+                        document.with_pointer_mut(&ptr, |count| {
+                            if let JSONValue::Number(n) = count {
+                                *n += 1.0;
+                            }
+                        }).unwrap();
+                    }
+                });
+            }
+
+            // a snapshot taken mid-run is a complete, serializable document
+            let snapshot = document.snapshot().unwrap();
+            let reparsed = JSONValue::from_str(&snapshot.to_string()).unwrap();
+            assert_eq!(snapshot, reparsed);
+        });
+
+        for id in 0..THREADS {
+            let count: usize = document.get_cloned(&format!("/workers/{id}/count"))?.cast()?;
+            assert_eq!(INCREMENTS, count);
+        }
+        assert!(matches!(document.with_pointer("/workers/8", |_| ()), Err(JSONError::IndexError(_))));
+        assert!(matches!(document.with_pointer("/nobody", |_| ()), Err(JSONError::KeyError(_))));
+
+        // a panic while holding the lock poisons the document instead of propagating
+        let result = std::thread::scope(|scope| {
+            scope.spawn(|| document.with_pointer_mut("", |_| panic!("worker died"))).join()
+        });
+        assert!(result.is_err());
+        assert!(matches!(document.snapshot(), Err(JSONError::PoisonError(_))));
+        assert!(matches!(document.into_inner(), Err(JSONError::PoisonError(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn number_grammar_test() {
+        let valid: [(&str, f64); 14] = [
+            ("0", 0.0),
+            ("-0", -0.0),
+            ("7", 7.0),
+            ("-12", -12.0),
+            ("0.5", 0.5),
+            ("-0.25", -0.25),
+            ("10.125", 10.125),
+            ("1e3", 1e3),
+            ("1E3", 1e3),
+            ("1e+3", 1e3),
+            ("1e-3", 1e-3),
+            ("0e0", 0.0),
+            ("-2.5E-2", -2.5e-2),
+            ("123456789", 123456789.0),
+        ];
+        for (source, expected) in valid {
+            let value = JSONValue::from_str(source).unwrap_or_else(|e| panic!("{source}: {e}"));
+            assert_eq!(JSONValue::Number(expected), value, "{source}");
+        }
+
+        // (source, column of the offending character)
+        let invalid: [(&str, usize); 17] = [
+            ("-", 2),
+            ("+5", 1),
+            (".5", 1),
+            ("5.", 3),
+            ("1e", 3),
+            ("1e+", 4),
+            ("1E-", 4),
+            ("e5", 1),
+            ("--3", 2),
+            ("-.5", 2),
+            ("1.2e3.4", 6),
+            ("1..2", 3),
+            ("1.e3", 3),
+            ("1e5e5", 4),
+            ("1+2", 2),
+            ("00", 2),
+            ("[1, 2, -]", 9),
+        ];
+        for (source, column) in invalid {
+            let err = JSONValue::from_str(source).expect_err(source);
+            assert_eq!(Some(column), err.position().map(|p| p.column), "{source}: {err}");
+        }
+
+        let err = JSONValue::from_str("[5.]").unwrap_err();
+        assert_eq!("invalid character ']' in number, expected a digit after the decimal point", err.message());
+        let err = JSONValue::from_str("1e").unwrap_err();
+        assert_eq!("unexpected end of number, expected a digit in the exponent", err.message());
+    }
+
+    #[test]
+    fn leading_zero_and_plus_test() {
+        for (source, column, message) in [
+            ("07", 2, "leading zeros are not allowed in numbers"),
+            ("0123", 2, "leading zeros are not allowed in numbers"),
+            ("-007", 3, "leading zeros are not allowed in numbers"),
+            ("+1", 1, "invalid character '+' in number, expected a digit"),
+            ("{\"a\": +1}", 7, "invalid character '+' in number, expected a digit"),
+        ] {
+            let err = JSONValue::from_str(source).expect_err(source);
+            assert!(matches!(err, JSONError::LexerError { .. }));
+            assert_eq!(message, err.message());
+            assert_eq!(Some(column), err.position().map(|p| p.column), "{source}");
+        }
+
+        assert_eq!(JSONValue::Number(0.0), JSONValue::from_str("0").unwrap());
+        assert_eq!(JSONValue::Number(0.5), JSONValue::from_str("0.5").unwrap());
+        assert_eq!(JSONValue::Number(-0.25), JSONValue::from_str("-0.25").unwrap());
+        assert_eq!(JSONValue::Number(100.0), JSONValue::from_str("100").unwrap());
+    }
+
+    #[test]
+    fn bom_test() -> json::Result<()> {
+        let with_bom = std::fs::read("tests/bom.json").unwrap();
+        assert!(with_bom.starts_with(&[0xEF, 0xBB, 0xBF]));
+        let without_bom = with_bom[3..].to_vec();
+        assert_eq!(JSONValue::try_from(without_bom)?, JSONValue::try_from(with_bom)?);
+
+        // positions after the BOM are unaffected by it
+        let err = JSONValue::try_from(b"\xEF\xBB\xBF{\"a\" 1}".to_vec()).unwrap_err();
+        assert_eq!(Some(json::Position { line: 1, column: 6, offset: 8 }), err.position());
+
+        // only a BOM at the very start is skipped
+        let err = JSONValue::try_from(std::fs::read("tests/bom_mid.json").unwrap()).unwrap_err();
+        assert!(matches!(err, JSONError::LexerError { .. }));
+        assert_eq!((2, 12), err.position().map(|p| (p.line, p.column)).unwrap());
+
+        Ok(())
+    }
+
+    /// Counts heap allocations made by the current thread, so tests can compare how much work
+    /// two code paths do without relying on timing.
+    struct CountingAllocator;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+        static ALLOCATED_BYTES: Cell<usize> = const { Cell::new(0) };
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+            let _ = ALLOCATED_BYTES.try_with(|bytes| bytes.set(bytes.get() + layout.size()));
+            unsafe { System.alloc(layout) }
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            unsafe { System.dealloc(ptr, layout) }
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+            let _ = ALLOCATED_BYTES.try_with(|bytes| bytes.set(bytes.get() + new_size));
+            unsafe { System.realloc(ptr, layout, new_size) }
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    /// Runs `f`, returning its result and the number of allocations it made.
+    fn count_allocations<T>(f: impl FnOnce() -> T) -> (T, usize) {
+        let before = ALLOCATIONS.with(Cell::get);
+        let result = f();
+        (result, ALLOCATIONS.with(Cell::get) - before)
+    }
+
+    /// Runs `f`, returning its result and the number of bytes it allocated.
+    fn count_allocated_bytes<T>(f: impl FnOnce() -> T) -> (T, usize) {
+        let before = ALLOCATED_BYTES.with(Cell::get);
+        let result = f();
+        (result, ALLOCATED_BYTES.with(Cell::get) - before)
+    }
+
+    /// A small deterministic xorshift generator, so randomized tests are reproducible.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+
+        fn bytes(&mut self, len: usize) -> Vec<u8> {
+            (0..len).map(|_| self.next() as u8).collect()
+        }
+    }
+
+    #[test]
+    fn invalid_utf8_test() {
+        // truncated and malformed multi-byte sequences inside strings
+        for (source, offset) in [
+            (&b"\"\xE2\x82\""[..], 1),
+            (b"[\"ok\", \"caf\xC3\"]", 11),
+            (b"{\"na\xEFve\": 1}", 4),
+            (b"\"\xF0\x9F\x98\"", 1),
+            (b"\"\xFF\"", 1),
+        ] {
+            let err = JSONValue::try_from(source.to_vec()).unwrap_err();
+            assert!(matches!(err, JSONError::LexerError { .. }), "{err}");
+            assert_eq!("invalid UTF-8 sequence", err.message());
+            assert_eq!(Some(offset), err.position().map(|p| p.offset));
+        }
+        let err = JSONValue::try_from(b"[\n  \"\xC3\"]".to_vec()).unwrap_err();
+        assert_eq!((2, 4), err.position().map(|p| (p.line, p.column)).unwrap());
+
+        // valid multi-byte text survives
+        let value = JSONValue::try_from("\"naïve 日本語 😀\"".as_bytes().to_vec()).unwrap();
+        assert_eq!(JSONValue::String("naïve 日本語 😀".to_owned()), value);
+
+        // random bytes never panic the lexer, and random string contents only fail on bad UTF-8
+        // or unescaped control characters
+        let mut rng = Rng(0x5eed);
+        for _ in 0..2000 {
+            let len = rng.below(64);
+            let bytes = rng.bytes(len);
+            let _ = Lexer::new(&bytes).tokenify();
+
+            let contents: Vec<u8> = bytes.into_iter().filter(|&b| b != b'"' && b != b'\\').collect();
+            let mut source = vec![b'"'];
+            source.extend_from_slice(&contents);
+            source.push(b'"');
+            let result = JSONValue::try_from(source);
+            let valid = std::str::from_utf8(&contents).is_ok() && contents.iter().all(|&b| b >= 0x20);
+            assert_eq!(valid, result.is_ok(), "{contents:?}");
+        }
+    }
+
+    #[test]
+    fn surrogate_escape_test() {
+        let error = |source: &str| {
+            let err = JSONValue::from_str(source).unwrap_err();
+            assert!(matches!(err, JSONError::LexerError { .. }), "{err}");
+            let position = err.position().unwrap();
+            (err.message().to_owned(), position.line, position.column)
+        };
+
+        assert_eq!((
+            "unpaired low surrogate \\uDEAD, expected it to follow a high surrogate escape \\uD800-\\uDBFF".to_owned(), 1, 2,
+        ), error(r#""\uDEAD""#));
+        assert_eq!((
+            "unpaired high surrogate \\uD800, expected a low surrogate escape \\uDC00-\\uDFFF to follow".to_owned(), 2, 6,
+        ), error("[1,\n  \"ab\\uD800x\"]"));
+        assert_eq!(
+            "unpaired high surrogate \\uD83D, expected a low surrogate escape \\uDC00-\\uDFFF to follow",
+            error(r#""\uD83DA""#).0,
+        );
+        assert_eq!((
+            "incomplete unicode escape, expected 4 hexadecimal digits".to_owned(), 1, 4,
+        ), error(r#"["a\u12"]"#));
+        assert_eq!(("invalid hexadecimal code: 12zz".to_owned(), 1, 2), error(r#""\u12zz""#));
+        assert_eq!(("invalid escape char: q".to_owned(), 1, 3), error(r#""a\q""#));
+
+        assert_eq!(JSONValue::String("😀 é".to_owned()), JSONValue::from_str(r#""\uD83D\uDE00 \u00e9""#).unwrap());
+    }
+
+    #[test]
+    fn strict_root_test() -> json::Result<()> {
+        for source in ["\"hello\"", "42", "true", "null", "\n  -1.5e3"] {
+            let err = JSONValue::from_str_strict_root(source).unwrap_err();
+            assert!(matches!(err, JSONError::SyntaxError { .. }), "{err}");
+            assert!(err.message().starts_with("expected an object or array at the document root"));
+            // accepted by default
+            JSONValue::from_str(source)?;
+        }
+        let err = JSONValue::from_str_strict_root("\n  42").unwrap_err();
+        assert_eq!((2, 3), err.position().map(|p| (p.line, p.column)).unwrap());
+
+        // containers are accepted, along with any scalars nested in them
+        let source = std::fs::read_to_string("tests/test.json").unwrap();
+        assert_eq!(JSONValue::from_str(&source)?, JSONValue::from_str_strict_root(&source)?);
+        assert_eq!(JSONValue::from_str("[1, \"a\", null]")?, JSONValue::from_str_strict_root("[1, \"a\", null]")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn parser_error_position_test() {
+        let source = std::fs::read_to_string("tests/test.json").unwrap();
+        let planted = |from: &str, to: &str| {
+            let broken = source.replacen(from, to, 1);
+            assert_ne!(source, broken);
+            let err = JSONValue::from_str(&broken).unwrap_err();
+            assert!(matches!(err, JSONError::SyntaxError { .. }), "{err}");
+            let position = err.position().unwrap();
+            (err.message().to_owned(), position.line, position.column)
+        };
+
+        // missing comma between members
+        assert_eq!(("expected Comma, found StringLiteral(\"tmp\")".to_owned(), 3, 5), planted("\"bar\",", "\"bar\""));
+        // non-string key, deep inside the document
+        assert_eq!(("expected string literal, found NumericLiteral(\"5\")".to_owned(), 12, 9), planted("\"c\":", "5:"));
+        // missing colon
+        assert_eq!(("expected Colon, found NumericLiteral(\"42\")".to_owned(), 20, 30), planted("\"the_answer\":", "\"the_answer\""));
+        // unexpected tokens in value position
+        assert_eq!(("unexpected token `Comma`".to_owned(), 16, 26), planted("[2, 3,", "[2, 3,,"));
+        assert_eq!(("unexpected token `CloseBracket`".to_owned(), 6, 15), planted("false,", "false,]"));
+    }
+
+    #[test]
+    fn render_error_test() {
+        let render = |source: &str| JSONValue::from_str(source).unwrap_err().render(source.as_bytes());
+
+        // parser error in the middle of a document
+        assert_eq!("\
+JSON Syntax Error: expected Colon, found NumericLiteral(\"2\") at line 3, column 11
+  |
+3 |     \"bar\" 2
+  |           ^", render("{\n    \"foo\": 1,\n    \"bar\" 2\n}\n"));
+
+        // lexer error on the last line, with no trailing newline, past line 9
+        let source = format!("[{}\n  $]", "\n  1,".repeat(10));
+        assert_eq!("\
+JSON Lexer Error: invalid character '$' at line 12, column 3
+   |
+12 |   $]
+   |   ^", render(&source));
+
+        // tabs are kept in the caret line
+        assert_eq!("\
+JSON Syntax Error: unexpected token `Colon` at line 2, column 3
+  |
+2 | \t\t:]
+  | \t\t^", render("[\n\t\t:]"));
+
+        // long lines are cut down around the caret
+        let source = format!("[{}tru, {}]", "1, ".repeat(40), "2, ".repeat(40));
+        assert_eq!(format!("\
+JSON Syntax Error: unexpected token `tru` at line 1, column 122
+  |
+1 | ... {}tru, {}2,...
+  |    {}^", "1, ".repeat(13), "2, ".repeat(11), " ".repeat(40)), render(&source));
+
+        // errors at the end of input point just past the last character
+        let source = "[1, \"abc";
+        assert_eq!("\
+JSON Unexpected EOF: unterminated string (opened at line 1, column 5) at line 1, column 9
+  |
+1 | [1, \"abc
+  |         ^", render(source));
+
+        // errors without a position render as their message
+        let err = JSONValue::Null.get("a").unwrap_err();
+        assert_eq!(err.to_string(), err.render(b"null"));
+    }
+
+    #[test]
+    fn session_test() -> json::Result<()> {
+        let messages: Vec<String> = (0..1000)
+            .map(|i| format!("{{\"id\": {i}, \"ok\": true, \"tags\": [1, 2, 3], \"extra\": null}}"))
+            .collect();
+
+        let (fresh, fresh_allocations) = count_allocations(|| {
+            messages.iter().map(|m| JSONValue::try_from(m.as_bytes().to_vec())).collect::<json::Result<Vec<_>>>()
+        });
+        let mut session = json::Session::new();
+        let (reused, reused_allocations) = count_allocations(|| {
+            messages.iter().map(|m| session.parse(m.as_bytes())).collect::<json::Result<Vec<_>>>()
+        });
+        assert_eq!(fresh?, reused?);
+        println!("allocations with try_from: {fresh_allocations}, with a session: {reused_allocations}");
+        assert!(reused_allocations < fresh_allocations);
+
+        // once its token vector has grown, a session allocates only what the values need, as
+        // parsing without a token vector does
+        let (borrowed, borrowed_allocations) = count_allocations(|| {
+            messages.iter().map(|m| JSONValue::from_slice(m.as_bytes())).collect::<json::Result<Vec<_>>>()
+        });
+        let (reused, reused_allocations) = count_allocations(|| {
+            messages.iter().map(|m| session.parse(m.as_bytes())).collect::<json::Result<Vec<_>>>()
+        });
+        assert_eq!(borrowed?, reused?);
+        assert_eq!(borrowed_allocations, reused_allocations);
+
+        // state never leaks from one document into the next
+        let mut session = json::Session::default();
+        let long = std::fs::read("tests/test.json").unwrap();
+        assert_eq!(JSONValue::try_from(long.clone())?, session.parse(&long)?);
+        assert_eq!(JSONValue::Array(vec![JSONValue::Number(1.0)]), session.parse(b"[1]")?);
+        let err = session.parse(b"\n [1 2]").unwrap_err();
+        assert_eq!(Some(json::Position { line: 2, column: 5, offset: 5 }), err.position());
+        assert_eq!(JSONValue::try_from(long.clone())?, session.parse(&long)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn borrowed_input_test() -> json::Result<()> {
+        for fixture in ["tests/test.json", "tests/array.json", "tests/string.json", "tests/bom.json"] {
+            let bytes = std::fs::read(fixture).unwrap();
+            assert_eq!(JSONValue::try_from(bytes.clone())?, JSONValue::from_slice(&bytes)?);
+        }
+        let source = std::fs::read_to_string("tests/test.json").unwrap();
+        assert_eq!(JSONValue::from_slice(source.as_bytes())?, JSONValue::from_str(&source)?);
+
+        // the borrowed path never copies the input buffer
+        let big = format!("[{}0]", "\"0123456789abcdef\", ".repeat(20_000));
+        let (owned, owned_bytes) = count_allocated_bytes(|| JSONValue::try_from(big.as_bytes().to_vec()));
+        let (borrowed, borrowed_bytes) = count_allocated_bytes(|| JSONValue::from_slice(big.as_bytes()));
+        assert_eq!(owned?, borrowed?);
+        assert!(borrowed_bytes + big.len() <= owned_bytes, "{borrowed_bytes} + {} > {owned_bytes}", big.len());
+
+        Ok(())
+    }
+
+    #[test]
+    fn token_span_test() -> json::Result<()> {
+        let mut sources: Vec<Vec<u8>> = ["tests/test.json", "tests/array.json", "tests/string.json", "tests/bom.json"]
+            .iter()
+            .map(|fixture| std::fs::read(fixture).unwrap())
+            .collect();
+        sources.push(b" {\"a\\\"b\": [-1.5e3,\ttrue , null,false]}\r\n".to_vec());
+
+        for source in sources {
+            let tokens = json::Lexer::new(&source).tokenify()?;
+            let mut rebuilt: Vec<u8> = vec![];
+            for json::TokenPos(token, position, span) in &tokens {
+                assert_eq!(position.offset, span.start);
+                // everything skipped between tokens is a BOM or whitespace
+                let gap = &source[rebuilt.len()..span.start];
+                assert!(gap.iter().all(|b| b" \t\r\n\xEF\xBB\xBF".contains(b)), "{gap:?}");
+                rebuilt.extend_from_slice(gap);
+                rebuilt.extend_from_slice(&source[span.range()]);
+
+                let text = std::str::from_utf8(&source[span.range()]).unwrap();
+                match token {
+                    // the span covers the quotes and escapes the contents were decoded from
+                    json::Token::StringLiteral(contents) => {
+                        assert_eq!(JSONValue::String(contents.to_string()), JSONValue::from_str(text)?)
+                    }
+                    json::Token::NumericLiteral(literal) => assert_eq!(literal, text),
+                    json::Token::OpenBrace => assert_eq!("{", text),
+                    json::Token::Colon => assert_eq!(":", text),
+                    json::Token::Null => assert_eq!("null", text),
+                    _ => {}
+                }
+            }
+            rebuilt.extend_from_slice(&source[rebuilt.len()..]);
+            assert_eq!(source, rebuilt);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn byte_offset_test() -> json::Result<()> {
+        let source = "{\"名前\": \"日本語 😀\", \"é\": [1, \"ü\\u00fc\", true], \"🎉\": null}";
+        let tokens = Lexer::new(source.as_bytes()).tokenify()?;
+        for json::TokenPos(token, position, _) in &tokens {
+            let rest = &source[position.offset..];
+            let text = match token {
+                json::Token::StringLiteral(_) => "\"",
+                json::Token::NumericLiteral(text) => text,
+                json::Token::OpenBrace => "{",
+                json::Token::CloseBrace => "}",
+                json::Token::OpenBracket => "[",
+                json::Token::CloseBracket => "]",
+                json::Token::Colon => ":",
+                json::Token::Comma => ",",
+                json::Token::True => "true",
+                json::Token::Null => "null",
+                other => panic!("unexpected token {other:?}"),
+            };
+            assert!(rest.starts_with(text), "{token:?} at {}: {rest}", position.offset);
+        }
+        let offset = |text: &str| source.find(text).unwrap();
+        let null = tokens.iter().find(|token| token.0 == json::Token::Null).unwrap();
+        assert_eq!(offset("null"), null.1.offset);
+
+        // errors from the lexer, the parser and unescaping all point at the right byte
+        for (source, at) in [("[\"日本\", $]", "$"), ("{\"é😀\": 1 2}", "2"), ("[\"😀\", \"日\\q\"]", "\\q")] {
+            let err = JSONValue::from_str(source).unwrap_err();
+            assert_eq!(Some(source.find(at).unwrap()), err.position().map(|p| p.offset), "{err}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn lazy_tokens_test() -> json::Result<()> {
+        use super::parser::Parser;
+
+        for fixture in ["tests/test.json", "tests/array.json", "tests/string.json", "tests/bom.json"] {
+            let bytes = std::fs::read(fixture).unwrap();
+            let eager = Parser::from(Lexer::new(&bytes).tokenify()?).parse()?;
+            assert_eq!(eager, Parser::new(Lexer::new(&bytes)).parse()?);
+        }
+
+        // the first error ends the stream
+        let mut lexer = Lexer::new(b"[1, $, 2]");
+        assert!(matches!(lexer.next(), Some(Ok(json::TokenPos(json::Token::OpenBracket, _, _)))));
+        assert!(matches!(lexer.next(), Some(Ok(json::TokenPos(json::Token::NumericLiteral(_), _, _)))));
+        assert!(matches!(lexer.next(), Some(Ok(json::TokenPos(json::Token::Comma, _, _)))));
+        let err = lexer.next().unwrap().unwrap_err();
+        assert_eq!(Some(json::Position { line: 1, column: 5, offset: 4 }), err.position());
+        assert!(lexer.next().is_none());
+
+        // nothing past the tokens a consumer asks for gets lexed
+        let big = std::fs::read("tests/test.json").unwrap();
+        let mut lexer = Lexer::new(&big);
+        assert_eq!(3, lexer.by_ref().take(3).count());
+        assert!(lexer.offset() < 64, "{}", lexer.offset());
+        // ...so a syntax error near the start is reported without reading the rest
+        let broken = format!("[1 2, {}]", "\"unterminated".repeat(1000));
+        let mut lexer = Lexer::new(broken.as_bytes());
+        let err = Parser::new(lexer.by_ref()).parse().unwrap_err();
+        assert!(matches!(err, JSONError::SyntaxError { .. }), "{err}");
+        assert_eq!(4, lexer.offset());
+
+        // running out of tokens is an error, not a panic
+        let err = JSONValue::from_str("{\"a\": [1,").unwrap_err();
+        assert_eq!(Some(json::Position { line: 1, column: 10, offset: 9 }), err.position());
+        assert!(JSONValue::from_str("").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_events_test() -> json::Result<()> {
+        use super::events::TreeBuilder;
+
+        for fixture in ["tests/test.json", "tests/array.json", "tests/string.json", "tests/bom.json"] {
+            let bytes = std::fs::read(fixture).unwrap();
+            let mut builder = TreeBuilder::default();
+            json::parse_events(&bytes, &mut builder)?;
+            assert_eq!(Some(JSONValue::try_from(bytes)?), builder.into_value());
+        }
+
+        // errors point at the same place as they do when building a value
+        for source in ["[1, 2", "{\"a\" 1}", "{\"a\": 1,}", "[1 2]", "[1}", "}", "{1: 2}", "[nul]", "[\"\\x\"]"] {
+            let expected = JSONValue::from_str(source).unwrap_err();
+            let err = json::parse_events(source.as_bytes(), &mut TreeBuilder::default()).unwrap_err();
+            assert_eq!(expected.to_string(), err.to_string());
+        }
+
+        // a handler can abort with its own errors, positioned at the offending value
+        struct NoNegatives {
+            seen: usize,
+        }
+        impl json::Handler for NoNegatives {
+            fn number(&mut self, value: f64, position: Position) -> json::Result<()> {
+                self.seen += 1;
+                if value < 0.0 {
+                    return Err(JSONError::ValueError(format!("negative number at {position}")));
+                }
+                Ok(())
+            }
+        }
+        let mut handler = NoNegatives { seen: 0 };
+        let err = json::parse_events(b"{\"a\": [1, 2],\n \"b\": -3, \"c\": 4}", &mut handler).unwrap_err();
+        assert_eq!("negative number at line 2, column 7", err.message());
+        assert_eq!(3, handler.seen);
+
+        Ok(())
+    }
+
+    #[test]
+    fn json_reader_test() -> json::Result<()> {
+        use json::{Event, JsonReader};
+
+        // a large document whose interesting field sits at the very end, under a few levels
+        let mut rng = Rng(0x5eed);
+        let mut source = String::from("{\"items\": [");
+        for i in 0..5000 {
+            let flag = rng.below(2) == 0;
+            source.push_str(&format!(
+                "{{\"id\": {i}, \"name\": \"item \\\"{i}\\\" \\u00e9\", \"tags\": [\"a\", \"b\\\\\"], \"ok\": {flag}, \"next\": null}},"
+            ));
+        }
+        source.push_str("{}], \"meta\": {\"skip\": [[[\"]]]\"]]], \"deep\": {\"a\": {\"b\": {\"target\": [42, \"x\"]}}}}}");
+        let bytes = source.as_bytes();
+
+        let extract = || -> json::Result<JSONValue> {
+            let mut reader = JsonReader::new(bytes);
+            assert_eq!(Some(Event::StartObject), reader.next_event()?);
+            reader.expect_key("items")?;
+            reader.skip_value()?;
+            reader.expect_key("meta")?;
+            assert_eq!(Some(Event::StartObject), reader.next_event()?);
+            reader.expect_key("skip")?;
+            reader.skip_value()?;
+            reader.expect_key("deep")?;
+            for key in ["a", "b"] {
+                assert_eq!(Some(Event::StartObject), reader.next_event()?);
+                reader.expect_key(key)?;
+            }
+            assert_eq!(Some(Event::StartObject), reader.next_event()?);
+            reader.expect_key("target")?;
+            reader.read_value()
+        };
+
+        let (full, full_allocations) = count_allocations(|| JSONValue::from_slice(bytes));
+        let (target, reader_allocations) = count_allocations(extract);
+        let target = target?;
+        assert_eq!(full?["meta"]["deep"]["a"]["b"]["target"], target);
+        assert_eq!(JSONValue::Array(vec![JSONValue::Number(42.0), JSONValue::String("x".to_string())]), target);
+        println!("allocations parsing everything: {full_allocations}, with a reader: {reader_allocations}");
+        assert!(reader_allocations * 2 < full_allocations);
+
+        // skipping still checks the structure of what it skips
+        let mut reader = JsonReader::new(b"[[1, {\"a\" 2}], 3]");
+        assert_eq!(Some(Event::StartArray), reader.next_event()?);
+        let err = reader.skip_value().unwrap_err();
+        assert_eq!(Some(json::Position { line: 1, column: 11, offset: 10 }), err.position());
+
+        // keys and closing brackets are not values
+        let mut reader = JsonReader::new(b"{\"a\": []}");
+        assert_eq!(Some(Event::StartObject), reader.next_event()?);
+        assert!(reader.skip_value().is_err());
+        let mut reader = JsonReader::new(b"{\"a\": []}");
+        assert_eq!(Some(Event::StartObject), reader.next_event()?);
+        let err = reader.expect_key("b").unwrap_err();
+        assert_eq!(Some(json::Position { line: 1, column: 2, offset: 1 }), err.position());
+        assert_eq!(Some(Event::StartArray), reader.next_event()?);
+        assert_eq!(Some(Event::EndArray), reader.next_event()?);
+        assert_eq!(Some(Event::EndObject), reader.next_event()?);
+        assert_eq!(None, reader.next_event()?);
+        assert!(reader.skip_value().is_err());
+
+        Ok(())
+    }
+
+    // hands out at most `step` bytes per read, then fails once `fail_at` bytes have been read
+    struct TrickleReader<'a> {
+        bytes: &'a [u8],
+        step: usize,
+        fail_at: Option<usize>,
+        read: usize,
+    }
+
+    impl std::io::Read for TrickleReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.fail_at.is_some_and(|at| self.read >= at) {
+                return Err(std::io::Error::new(std::io::ErrorKind::ConnectionReset, "connection reset"));
+            }
+            let len = self.step.min(buf.len()).min(self.bytes.len() - self.read);
+            buf[..len].copy_from_slice(&self.bytes[self.read..self.read + len]);
+            self.read += len;
+            Ok(len)
+        }
+    }
+
+    #[test]
+    fn from_reader_test() -> json::Result<()> {
+        let mut sources: Vec<Vec<u8>> = ["tests/test.json", "tests/array.json", "tests/string.json", "tests/bom.json"]
+            .iter()
+            .map(|fixture| std::fs::read(fixture).unwrap())
+            .collect();
+        sources.push(br#"{"esc\"aped": "\u00e9\ud83d\ude00 \\", "n": [-0.5e-3, 1234567, 0], "k": [true, false, null]}"#.to_vec());
+        sources.push(b"12345".to_vec());
+
+        for source in &sources {
+            let expected = JSONValue::from_slice(source)?;
+            for step in [1, 2, 3, 7, 4096, usize::MAX] {
+                let reader = TrickleReader { bytes: source, step, fail_at: None, read: 0 };
+                assert_eq!(expected, JSONValue::from_reader(reader)?, "{step} bytes per read");
+            }
+        }
+
+        // errors are reported at the same place however the input is split
+        for source in ["[1, 2", "{\"a\": tru}", "[1.]", "[\"abc\\", "\n\n  [01]", "{\"a\" 1}", "[\"\\x\"]"] {
+            let expected = JSONValue::from_str(source).unwrap_err();
+            for step in [1, 2, 5] {
+                let reader = TrickleReader { bytes: source.as_bytes(), step, fail_at: None, read: 0 };
+                let err = JSONValue::from_reader(reader).unwrap_err();
+                assert_eq!(expected.to_string(), err.to_string(), "{source:?} in {step} byte reads");
+            }
+        }
+
+        // failures of the reader itself are errors, not panics
+        let source = std::fs::read("tests/test.json").unwrap();
+        let reader = TrickleReader { bytes: &source, step: 16, fail_at: Some(64), read: 0 };
+        let err = JSONValue::from_reader(reader).unwrap_err();
+        assert!(matches!(&err, JSONError::IoError { source, .. } if source.kind() == std::io::ErrorKind::ConnectionReset), "{err}");
+        assert!(std::error::Error::source(&err).is_some());
+
+        // a token spanning many chunks is lexed a handful of times, not once per chunk
+        let source = format!("[\"{}\"]", "x".repeat(4 << 20));
+        let start = time::Instant::now();
+        let expected = JSONValue::from_str(&source)?;
+        let slice_time = start.elapsed();
+        let start = time::Instant::now();
+        let reader = TrickleReader { bytes: source.as_bytes(), step: 4096, fail_at: None, read: 0 };
+        assert_eq!(expected, JSONValue::from_reader(reader)?);
+        let reader_time = start.elapsed();
+        assert!(reader_time < slice_time * 20, "{reader_time:?} from a reader, {slice_time:?} from a slice");
+
+        Ok(())
+    }
+
+    #[test]
+    fn stream_parser_test() -> json::Result<()> {
+        fn parse_in_chunks(source: &[u8], mut chunk_len: impl FnMut() -> usize) -> json::Result<JSONValue> {
+            let mut parser = json::StreamParser::new();
+            let mut rest = source;
+            while !rest.is_empty() {
+                let (chunk, tail) = rest.split_at(chunk_len().min(rest.len()));
+                rest = tail;
+                if let Some(value) = parser.feed(chunk)? {
+                    assert!(rest.iter().all(u8::is_ascii_whitespace), "value returned before the end of the input");
+                    return Ok(value);
+                }
+            }
+            parser.finish()
+        }
+
+        let mut sources: Vec<Vec<u8>> = ["tests/test.json", "tests/array.json", "tests/string.json", "tests/bom.json"]
+            .iter()
+            .map(|fixture| std::fs::read(fixture).unwrap())
+            .collect();
+        sources.push(br#"[true, false, null, "a\"b\\c\ud83d\ude00", 1.5e-3, -0, 123]"#.to_vec());
+        sources.push(b"-12.5e+3".to_vec());
+
+        let mut rng = Rng(0xc0ffee);
+        for source in &sources {
+            let expected = JSONValue::from_slice(source)?;
+            assert_eq!(expected, parse_in_chunks(source, || 1)?);
+            assert_eq!(expected, parse_in_chunks(source, || 3)?);
+            for _ in 0..10 {
+                assert_eq!(expected, parse_in_chunks(source, || 1 + rng.below(16))?);
+            }
+        }
+
+        // a value is returned as soon as it closes, and whatever follows starts the next one
+        let mut parser = json::StreamParser::new();
+        assert_eq!(None, parser.feed(b"{\"a\": [tr")?);
+        assert_eq!(None, parser.feed(b"ue, \"x\\")?);
+        let first = parser.feed(b"u00e9\"]} [1")?.unwrap();
+        assert_eq!(JSONValue::from_str(r#"{"a": [true, "x\u00e9"]}"#)?, first);
+        assert_eq!(None, parser.feed(b"2")?);
+        assert_eq!(Some(JSONValue::from_str("[12]")?), parser.feed(b"]")?);
+        // a number can only be known to have ended once something follows it
+        assert_eq!(None, parser.feed(b"\n 42")?);
+        assert_eq!(JSONValue::Number(42.0), parser.finish()?);
+
+        // errors are reported by the chunk that causes them, at their position in the stream
+        let mut parser = json::StreamParser::new();
+        assert_eq!(None, parser.feed(b"[1,\n")?);
+        let err = parser.feed(b" 2 3]").unwrap_err();
+        assert_eq!(Some(json::Position { line: 2, column: 4, offset: 7 }), err.position());
+        let mut parser = json::StreamParser::new();
+        assert_eq!(None, parser.feed(b"{\"a\": ")?);
+        let err = parser.finish().unwrap_err();
+        assert_eq!(JSONValue::from_str("{\"a\": ").unwrap_err().to_string(), err.to_string());
+        assert!(matches!(err, JSONError::UnexpectedEof { .. }), "{err}");
+        let err = json::StreamParser::new().finish().unwrap_err();
+        assert!(matches!(err, JSONError::UnexpectedEof { .. }), "{err}");
+        let mut parser = json::StreamParser::new();
+        assert_eq!(None, parser.feed(b"  [1, {\"a\": null}")?);
+        let err = parser.finish().unwrap_err();
+        assert!(matches!(err, JSONError::UnexpectedEof { .. }), "{err}");
+        assert_eq!(JSONValue::from_str("  [1, {\"a\": null}").unwrap_err().position(), err.position());
+
+        Ok(())
+    }
+
+    #[test]
+    fn json_lines_test() -> json::Result<()> {
+        let source = std::fs::read("tests/records.jsonl").unwrap();
+        let file = std::fs::File::open("tests/records.jsonl").unwrap();
+        let from_reader: Vec<json::Result<JSONValue>> = json::lines(std::io::BufReader::new(file)).collect();
+        let from_slice: Vec<json::Result<JSONValue>> = json::lines_from_slice(&source).collect();
+
+        for records in [from_reader, from_slice] {
+            assert_eq!(7, records.len());
+            assert_eq!(JSONValue::from_str(r#"{"id": 1, "tags": ["a", "b"]}"#)?, *records[0].as_ref().unwrap());
+            assert_eq!(JSONValue::from_str("[1, 2, 3]")?, *records[1].as_ref().unwrap());
+            assert!(records[2].as_ref().unwrap()["nested"]["ok"] == true);
+
+            // the malformed record is reported at its line in the file...
+            let err = records[3].as_ref().unwrap_err();
+            let offset = source.windows(3).position(|w| w == b"1 2").unwrap() + 2;
+            assert_eq!(Some(json::Position { line: 5, column: 24, offset }), err.position());
+            // ...and the records after it still parse
+            assert_eq!(JSONValue::from_str(r#"["after", "the", "error"]"#)?, *records[4].as_ref().unwrap());
+            let err = records[5].as_ref().unwrap_err();
+            assert_eq!(Some((8, 11)), err.position().map(|p| (p.line, p.column)));
+            assert_eq!(JSONValue::String("last".to_string()), *records[6].as_ref().unwrap());
+        }
+
+        // a failing reader ends the iterator after reporting the error
+        let reader = TrickleReader { bytes: &source, step: 8, fail_at: Some(40), read: 0 };
+        let records: Vec<json::Result<JSONValue>> = json::lines(std::io::BufReader::with_capacity(8, reader)).collect();
+        assert_eq!(3, records.len());
+        assert!(records[0].is_ok() && records[1].is_ok());
+        assert!(matches!(records[2], Err(JSONError::IoError { .. })));
+
+        Ok(())
+    }
+
+    #[test]
+    fn concatenated_documents_test() -> json::Result<()> {
+        let expected = vec![
+            JSONValue::from_str(r#"{"a": 1}"#)?,
+            JSONValue::from_str(r#"{"a": 2}"#)?,
+            JSONValue::from_str("[3]")?,
+            JSONValue::String("four".to_string()),
+            JSONValue::Number(5.0),
+            JSONValue::Null,
+        ];
+        for source in [
+            r#"{"a":1}{"a":2}[3]"four"5 null"#,
+            r#"{"a":1} {"a":2}  [3] "four" 5 null "#,
+            "{\"a\":1}\n{\"a\":2}\r\n[3]\n\n\"four\"\n5\nnull\n",
+        ] {
+            let values: json::Result<Vec<JSONValue>> = json::Deserializer::from_slice(source.as_bytes()).collect();
+            assert_eq!(expected, values?, "{source:?}");
+        }
+
+        // every item consumes one value and the whitespace after it
+        let source = b" [1] {}\n\"x\" ";
+        let mut stream = json::Deserializer::from_slice(source);
+        let offsets: Vec<usize> = std::iter::from_fn(|| stream.next().map(|_| stream.byte_offset())).collect();
+        assert_eq!(vec![5, 8, source.len()], offsets);
+        assert!(json::Deserializer::from_slice(b" \n ").next().is_none());
+
+        // a malformed document is reported at its absolute offset, and ends the stream
+        let mut stream = json::Deserializer::from_slice(b"{\"a\": 1}\n{\"a\" 2}\n[3]");
+        assert_eq!(JSONValue::from_str(r#"{"a": 1}"#)?, stream.next().unwrap()?);
+        let err = stream.next().unwrap().unwrap_err();
+        assert_eq!(Some(json::Position { line: 2, column: 6, offset: 14 }), err.position());
+        assert!(stream.next().is_none());
+
+        // single-document parsing no longer ignores whatever follows the value
+        for source in ["{} {}", "[1] 2", "1 2", "null x"] {
+            let err = JSONValue::from_str(source).unwrap_err();
+            assert!(matches!(err, JSONError::SyntaxError { .. }), "{source:?}: {err}");
+            let err = json::parse_events(source.as_bytes(), &mut super::events::TreeBuilder::default()).unwrap_err();
+            assert!(matches!(err, JSONError::SyntaxError { .. }), "{source:?}: {err}");
+        }
+        assert_eq!(JSONValue::Number(1.0), JSONValue::from_str(" 1 \n")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_options_test() -> json::Result<()> {
+        use json::{DuplicateKeys, Options};
+
+        // each switch, with a document that only parses when it is on
+        type Switch = fn(Options, bool) -> Options;
+        let switches: [(Switch, &str); 3] = [
+            (Options::comments, "{\"a\": 1 /* one */, // two\n \"b\": [2]}"),
+            (Options::trailing_commas, "{\"a\": 1, \"b\": [2,],}"),
+            (Options::lenient_numbers, "{\"a\": +1, \"b\": [0002]}"),
+        ];
+        let expected = JSONValue::from_str(r#"{"a": 1, "b": [2]}"#)?;
+        for (i, (switch, source)) in switches.iter().enumerate() {
+            assert!(JSONValue::parse_with(source.as_bytes(), &Options::default()).is_err());
+            assert_eq!(expected, JSONValue::parse_with(source.as_bytes(), &switch(Options::default(), true))?);
+            // turning on every other switch doesn't help
+            let others = switches.iter().enumerate()
+                .filter(|(j, _)| *j != i)
+                .fold(Options::default(), |options, (_, (other, _))| other(options, true));
+            assert!(JSONValue::parse_with(source.as_bytes(), &others).is_err(), "{source:?}");
+            assert_eq!(expected, JSONValue::parse_with(source.as_bytes(), &Options::relaxed())?);
+        }
+        let err = JSONValue::parse_with(b"[1] /* open", &Options::relaxed()).unwrap_err();
+        assert!(matches!(err, JSONError::UnexpectedEof { .. }), "{err}");
+        assert_eq!("unterminated block comment (opened at line 1, column 5)", err.message());
+        assert_eq!(Some(json::Position { line: 1, column: 12, offset: 11 }), err.position());
+        assert!(JSONValue::parse_with(b"[1] / 2", &Options::relaxed()).is_err());
+
+        // duplicate keys
+        let source = b"{\"a\": 1,\n \"a\": 2}";
+        assert_eq!(JSONValue::Number(2.0), JSONValue::from_slice(source)?["a"]);
+        let first = JSONValue::parse_with(source, &Options::default().duplicate_keys(DuplicateKeys::First))?;
+        assert_eq!(JSONValue::Number(1.0), first["a"]);
+        let err = JSONValue::parse_with(source, &Options::strict()).unwrap_err();
+        assert_eq!(Some(json::Position { line: 2, column: 2, offset: 10 }), err.position());
+        assert!(JSONValue::parse_with(source, &Options::relaxed()).is_ok());
+
+        // nesting depth
+        let source = b"[{\"a\": [1]}, [[]]]";
+        assert!(JSONValue::parse_with(source, &Options::default().max_depth(3)).is_ok());
+        let err = JSONValue::parse_with(source, &Options::default().max_depth(2)).unwrap_err();
+        assert_eq!(Some(json::Position { line: 1, column: 8, offset: 7 }), err.position());
+        assert!(JSONValue::parse_with(source, &Options::default().max_depth(2).max_depth(None)).is_ok());
+
+        // document root
+        let root = Options::default().strict_root(true);
+        assert!(JSONValue::parse_with(b"1", &root).is_err());
+        assert!(JSONValue::parse_with(b"[1]", &root).is_ok());
+        assert!(JSONValue::parse_with(b"1", &Options::strict()).is_ok());
+
+        // the shorthand entry points are the defaults
+        let source = std::fs::read("tests/test.json").unwrap();
+        assert_eq!(JSONValue::parse_with(&source, &Options::default())?, JSONValue::try_from(source.clone())?);
+        assert_eq!(JSONValue::parse_with(&source, &Options::strict())?, JSONValue::try_from(source)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn validate_test() -> json::Result<()> {
+        for fixture in ["tests/test.json", "tests/array.json", "tests/string.json", "tests/bom.json", "tests/bom_mid.json"] {
+            let bytes = std::fs::read(fixture).unwrap();
+            match JSONValue::try_from(bytes.clone()) {
+                Ok(_) => json::validate(&bytes)?,
+                Err(expected) => assert_eq!(expected.to_string(), json::validate(&bytes).unwrap_err().to_string()),
+            }
+        }
+        for source in [
+            "", "[1, 2", "{\"a\" 1}", "{\"a\": 1,}", "[1 2]", "[1}", "}", "{1: 2}", "[nul]", "[\"\\x\"]",
+            "[\"\\ud800\"]", "[01]", "[1.]", "{} {}", "[1] x", "\"unterminated",
+        ] {
+            let expected = JSONValue::from_str(source).unwrap_err();
+            assert_eq!(expected.to_string(), json::validate(source.as_bytes()).unwrap_err().to_string(), "{source:?}");
+        }
+
+        // validating skips all the work of building the value
+        let big = format!("[{}]", std::iter::repeat_n(r#"{"key": "value \u00e9", "list": [1, 2.5, true, null]}"#, 20_000)
+            .collect::<Vec<_>>()
+            .join(","));
+        let start = time::Instant::now();
+        let (parsed, parse_bytes) = count_allocated_bytes(|| JSONValue::from_str(&big));
+        let parse_time = start.elapsed();
+        let start = time::Instant::now();
+        let (validated, validate_bytes) = count_allocated_bytes(|| json::validate(big.as_bytes()));
+        let validate_time = start.elapsed();
+        parsed?;
+        validated?;
+        println!("try_from: {parse_time:?}, {parse_bytes} bytes allocated; validate: {validate_time:?}, {validate_bytes} bytes allocated");
+        assert!(validate_bytes * 4 < parse_bytes, "{validate_bytes} * 4 >= {parse_bytes}");
+
+        Ok(())
+    }
+
+    #[test]
+    fn minify_test() -> json::Result<()> {
+        // true for every byte outside of string literals that is whitespace
+        fn whitespace_outside_strings(bytes: &[u8]) -> bool {
+            let (mut in_string, mut escaped) = (false, false);
+            for &b in bytes {
+                match b {
+                    _ if escaped => escaped = false,
+                    b'\\' if in_string => escaped = true,
+                    b'"' => in_string = !in_string,
+                    b' ' | b'\t' | b'\r' | b'\n' if !in_string => return true,
+                    _ => {}
+                }
+            }
+            false
+        }
+
+        let mut sources: Vec<Vec<u8>> = ["tests/test.json", "tests/array.json", "tests/string.json", "tests/bom.json"]
+            .iter()
+            .map(|fixture| std::fs::read(fixture).unwrap())
+            .collect();
+        sources.push(b" { \"a b\" : [ 1.50 , -0e+00 , \"\\u00e9 \\\" \\n\" ] ,\r\n\t\"c\":null } ".to_vec());
+
+        for source in &sources {
+            let mut minified = vec![];
+            json::minify(source, &mut minified)?;
+            assert_eq!(JSONValue::from_slice(source)?, JSONValue::from_slice(&minified)?);
+            assert!(!whitespace_outside_strings(&minified), "{}", String::from_utf8_lossy(&minified));
+
+            let mut written = vec![];
+            json::minify_to(source, &mut written)?;
+            assert_eq!(minified, written);
+        }
+
+        // strings and numbers are copied exactly as written
+        let mut minified = b"prefix:".to_vec();
+        json::minify(sources.last().unwrap(), &mut minified)?;
+        assert_eq!(br#"prefix:{"a b":[1.50,-0e+00,"\u00e9 \" \n"],"c":null}"#.to_vec(), minified);
+
+        // errors are the usual ones, and leave nothing behind
+        for source in ["[1, 2", "{\"a\" 1}", "[1 2]", "[\"\\x\"]", "{} {}", ""] {
+            let expected = JSONValue::from_str(source).unwrap_err().to_string();
+            let mut output = b"kept".to_vec();
+            assert_eq!(expected, json::minify(source.as_bytes(), &mut output).unwrap_err().to_string());
+            assert_eq!(b"kept".to_vec(), output);
+            let mut written = vec![];
+            assert_eq!(expected, json::minify_to(source.as_bytes(), &mut written).unwrap_err().to_string());
+            assert!(written.is_empty());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn json_test_suite() {
+        let mut cases: Vec<_> = std::fs::read_dir("tests/JSONTestSuite")
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .collect();
+        cases.sort();
+        assert!(cases.len() > 300);
+
+        let mut failures = vec![];
+        for path in &cases {
+            let name = path.file_stem().unwrap().to_str().unwrap().to_owned();
+            let source = std::fs::read(path).unwrap();
+            let results = std::panic::catch_unwind(|| [
+                JSONValue::from_slice(&source).map(drop),
+                JSONValue::from_reader(&source[..]).map(drop),
+                json::validate(&source),
+            ]);
+            let Ok(results) = results else {
+                failures.push(format!("{name}: panicked"));
+                continue;
+            };
+            let accepted: Vec<bool> = results.iter().map(Result::is_ok).collect();
+            match &name[..2] {
+                "y_" if accepted != [true; 3] => failures.push(format!("{name}: rejected {results:?}")),
+                "n_" if accepted != [false; 3] => failures.push(format!("{name}: accepted {results:?}")),
+                // implementation defined, but every entry point must agree
+                "i_" if accepted[1..].iter().any(|&ok| ok != accepted[0]) => {
+                    failures.push(format!("{name}: inconsistent {results:?}"))
+                }
+                _ => {}
+            }
+        }
+        assert!(failures.is_empty(), "{}", failures.join("\n"));
+
+        // fixes the corpus turned up
+        let err = JSONValue::from_str("[\"a\tb\"]").unwrap_err();
+        assert_eq!("unescaped control character '\\t' in string", err.message());
+        assert_eq!(Some(Position { line: 1, column: 4, offset: 3 }), err.position());
+        assert_eq!(Some(&JSONValue::Null), JSONValue::from_str(r#"{"a\u0062\n": null}"#).unwrap().get("ab\n").ok());
+        let deep = format!("{}{}", "[".repeat(129), "]".repeat(129));
+        assert_eq!(
+            json::validate(deep.as_bytes()).unwrap_err().to_string(),
+            JSONValue::from_str(&deep).unwrap_err().to_string(),
+        );
+    }
+
+    #[test]
+    fn get_path_raw_test() -> json::Result<()> {
+        let results: Vec<String> = (0..5000)
+            .map(|i| format!(r#"{{"id": {i}, "name": "result \"{i}\"", "tags": ["a", "b", {{"c": [{i}]}}], "ok": {}}}"#, i % 3 == 0))
+            .collect();
+        let source = format!(r#"{{"meta": {{"count": 5000, "\u00e9/~": null}}, "results": [{}], "last": "x"}}"#, results.join(",\n"));
+        let source = source.as_bytes();
+
+        let (document, full) = count_allocations(|| JSONValue::from_slice(source).unwrap());
+        for ptr in ["", "/meta", "/meta/count", "/meta/é~1~0", "/results/1523", "/results/1523/id", "/results/4999/tags/2/c/0", "/last"] {
+            assert_eq!(crate::pointer::resolve(&document, ptr)?, &json::get_path_raw(source, ptr)?, "{ptr}");
+        }
+        let (id, raw) = count_allocations(|| json::get_path_raw(source, "/results/1523/id").unwrap());
+        assert_eq!(JSONValue::Number(1523.0), id);
+        assert!(raw * 10 < full, "{raw} allocations vs {full}");
+
+        // the same errors as looking the path up in the parsed document
+        for ptr in ["/nothing", "/results/5000", "/results/-1", "/results/1/name/x", "/meta/count/0", "/results/7/tags/3", "meta"] {
+            let expected = crate::pointer::resolve(&document, ptr).unwrap_err().to_string();
+            assert_eq!(expected, json::get_path_raw(source, ptr).unwrap_err().to_string(), "{ptr}");
+        }
+
+        // input is checked up to the match, but not after it
+        assert!(json::get_path_raw(br#"{"a": [1, 2, x], "b": 3}"#, "/b").is_err());
+        assert_eq!(JSONValue::Number(1.0), json::get_path_raw(br#"{"a": 1, "b": x"#, "/a")?);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "audit")]
+    #[test]
+    fn audit_test() -> json::Result<()> {
+        use crate::json::audit::{chain_digest, verify_chain};
+
+        let seed = [7u8; 32];
+        let log = JSONValue::from_str(r#"[
+            {"event": "login", "user": "ana", "at": 1},
+            {"event": "grant", "user": "ana", "role": "admin", "at": 2},
+            {"event": "export", "rows": 1.5e3, "at": 3},
+            {"event": "logout", "user": "ana", "at": 4},
+            {"event": "login", "user": "bo", "at": 5}
+        ]"#)?;
+        let (head, hashes) = chain_digest(&log, seed)?;
+        assert_eq!(5, hashes.len());
+        assert_eq!(Some(&head), hashes.last());
+        let report = verify_chain(&log, seed, &hashes)?;
+        assert!(report.is_valid());
+        assert_eq!(head, report.head);
+
+        // hashes follow the canonical form, not the text the log was parsed from
+        let reordered = JSONValue::from_str(r#"[{"at": 1, "user": "ana", "event": "login"}]"#)?;
+        assert_eq!(hashes[0], chain_digest(&reordered, seed)?.0);
+        assert_ne!(hashes[0], chain_digest(&reordered, [0; 32])?.0);
+
+        // a changed element is found by its index, and breaks the head
+        let mut tampered = log.clone();
+        tampered[2].obj_set("rows", JSONValue::from(15))?;
+        let report = verify_chain(&tampered, seed, &hashes)?;
+        assert_eq!(Some(2), report.first_invalid);
+        assert_ne!(head, report.head);
+
+        // so are removed and added elements
+        let mut truncated = log.clone();
+        truncated.as_array_mut().unwrap().pop();
+        assert_eq!(Some(4), verify_chain(&truncated, seed, &hashes)?.first_invalid);
+        let mut extended = log.clone();
+        extended.as_array_mut().unwrap().push(JSONValue::Null);
+        assert_eq!(Some(5), verify_chain(&extended, seed, &hashes)?.first_invalid);
+
+        // appending only needs the previous head
+        let appended = JSONValue::from_str(r#"[{"event": "logout", "user": "bo", "at": 6}, null]"#)?;
+        let (new_head, new_hashes) = chain_digest(&appended, head)?;
+        let mut whole = log.clone();
+        whole.as_array_mut().unwrap().extend(appended.into_array()?);
+        let (whole_head, whole_hashes) = chain_digest(&whole, seed)?;
+        assert_eq!(whole_head, new_head);
+        assert_eq!(whole_hashes, [hashes, new_hashes].concat());
+        assert_eq!((seed, vec![]), chain_digest(&JSONValue::Array(vec![]), seed)?);
+
+        assert!(matches!(chain_digest(&JSONValue::Null, seed), Err(JSONError::ValueError(message)) if message == "expected array, found \"null\""));
+        assert!(matches!(verify_chain(&JSONValue::array_of([f64::NAN]), seed, &[]), Err(JSONError::ValueError(_))));
+
+        Ok(())
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn mmap_test() -> json::Result<()> {
+        for fixture in ["tests/test.json", "tests/array.json", "tests/string.json", "tests/bom.json"] {
+            let expected = JSONValue::from_slice(&std::fs::read(fixture).unwrap())?;
+            // SAFETY: nothing writes to the fixtures or files of this test while they are mapped
+            assert_eq!(expected, unsafe { JSONValue::from_file_mmap(fixture)? });
+        }
+
+        // errors, and empty files, are the same as for the bytes in memory
+        let dir = std::env::temp_dir().join(format!("json-rs-mmap-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for (name, contents) in [("empty.json", ""), ("broken.json", "{\n  \"a\": [1, 2,]\n}")] {
+            let path = dir.join(name);
+            std::fs::write(&path, contents).unwrap();
+            let expected = JSONValue::from_str(contents).unwrap_err();
+            assert_eq!(expected.to_string(), unsafe { JSONValue::from_file_mmap(&path) }.unwrap_err().to_string());
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let err = unsafe { JSONValue::from_file_mmap(dir.join("missing.json")) }.unwrap_err();
+        assert!(matches!(&err, JSONError::IoError { source, .. } if source.kind() == std::io::ErrorKind::NotFound), "{err}");
+        assert!(err.message().contains("missing.json"), "{err}");
+
+        Ok(())
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn par_from_slice_test() -> json::Result<()> {
+        use crate::parallel::split_array;
+
+        fn elements(source: &str) -> Option<Vec<&str>> {
+            split_array(source.as_bytes()).map(|ranges| ranges.into_iter().map(|range| source[range].trim()).collect())
+        }
+        assert_eq!(Some(vec!["1", "\"]\"", "[2, [3]]", "{\"a]\": \"}\"}"]), elements(r#" [1, "]", [2, [3]], {"a]": "}"}] "#));
+        assert_eq!(Some(vec![r#""\"],""#, r#""\\""#, r#""\\\"]""#]), elements(r#"["\"],", "\\", "\\\"]"]"#));
+        assert_eq!(Some(vec![]), elements("\u{feff}[ \n]"));
+        for source in ["{}", "1", "[1, 2", "[1,,2]", "[1,]", "[,]", "[1] 2", "[1}", "", "[\u{feff}1, 2]", "[1, \u{feff}2]"] {
+            assert_eq!(None, elements(source), "{source}");
+        }
+
+        let records: Vec<String> = (0..100_000)
+            .map(|i| format!(r#"{{"id": {i}, "tags": ["x]", "{{y"], "nested": {{"ok": {}, "v": [{i}.5, null]}}}}"#, i % 2 == 0))
+            .collect();
+        let source = format!("[\n{}\n]", records.join(",\n"));
+        assert_eq!(JSONValue::from_slice(source.as_bytes())?, JSONValue::par_from_slice(source.as_bytes())?);
+
+        // other roots and broken documents behave exactly like from_slice
+        for source in ["{\"a\": [1, 2]}", "\"[1, 2]\"", "[1, 2, {\"a\": tru}]", "[1, [2, 3}]", "[\"a\tb\"]", "[1] 2", "[\u{feff}1, 2]", "[1, \u{feff}2]", "[\"\u{feff}\"]"] {
+            assert_eq!(
+                format!("{:?}", JSONValue::from_slice(source.as_bytes())),
+                format!("{:?}", JSONValue::par_from_slice(source.as_bytes())),
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn number_hook_test() -> json::Result<()> {
+        // rejects integers an f64 can't hold exactly, and keeps decimals as written
+        let options = json::Options::default().number_hook(|literal, _| {
+            if literal.contains(['.', 'e', 'E']) {
+                return Ok(JSONValue::String(literal.to_owned()));
+            }
+            match literal.parse::<i64>() {
+                Ok(n) if n.unsigned_abs() <= 1 << 53 => Ok(JSONValue::Number(n as f64)),
+                _ => Err(JSONError::ValueError(format!("{literal} loses precision as a number"))),
+            }
+        });
+
+        let value = JSONValue::parse_with(br#"{"price": 12345678901234.5678, "n": [-9007199254740992, [1e3]]}"#, &options)?;
+        assert_eq!(JSONValue::String("12345678901234.5678".to_owned()), value["price"]);
+        assert_eq!(JSONValue::Number(-9007199254740992.0), value["n"][0]);
+        assert_eq!(JSONValue::String("1e3".to_owned()), value["n"][1][0]);
+
+        let err = JSONValue::parse_with(b"[1,\n {\"id\": 9007199254740993}]", &options).unwrap_err();
+        assert!(matches!(err, JSONError::SyntaxError { .. }), "{err}");
+        assert_eq!("9007199254740993 loses precision as a number", err.message());
+        assert_eq!(Some(Position { line: 2, column: 9, offset: 12 }), err.position());
+
+        // the hook sees every number, and positioned errors pass through untouched
+        let seen = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+        let log = seen.clone();
+        let options = json::Options::default().number_hook(move |literal, position| {
+            log.lock().unwrap().push((literal.to_owned(), position.offset));
+            match literal {
+                "0" => Err(JSONError::SyntaxError { message: "no zeros".to_owned(), position: Position { line: 9, column: 9, offset: 99 } }),
+                _ => Ok(JSONValue::Null),
+            }
+        });
+        assert_eq!(
+            JSONValue::from_str("[null, {\"a\": null}, [[null]]]")?,
+            JSONValue::parse_with(b"[-1.5, {\"a\": 2E+2}, [[3]]]", &options)?,
+        );
+        let expected = [("-1.5", 1), ("2E+2", 13), ("3", 22)].map(|(literal, offset)| (literal.to_owned(), offset));
+        assert_eq!(expected.to_vec(), *seen.lock().unwrap());
+        assert_eq!(Some(99), JSONValue::parse_with(b"[0]", &options).unwrap_err().position().map(|p| p.offset));
+
+        // without a hook, numbers are still f64s
+        assert_eq!(JSONValue::Number(12345678901234.568), JSONValue::parse_with(b"12345678901234.5678", &json::Options::default())?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn token_position_test() -> json::Result<()> {
+        // the line and column of every byte, counted independently of the lexer; every byte of a
+        // character is in the character's column
+        fn positions(source: &[u8]) -> Vec<(usize, usize)> {
+            let (mut line, mut column) = (1, 1);
+            std::str::from_utf8(source).unwrap().chars().flat_map(|c| {
+                let position = (line, column);
+                (line, column) = if c == '\n' { (line + 1, 1) } else { (line, column + 1) };
+                std::iter::repeat_n(position, c.len_utf8())
+            }).collect()
+        }
+
+        let mut sources: Vec<Vec<u8>> = ["tests/test.json", "tests/array.json", "tests/string.json"]
+            .iter()
+            .map(|fixture| std::fs::read(fixture).unwrap())
+            .collect();
+        sources.push(b"\n\n  [\"caf\xC3\xA9\",\r\n\t{\"a\" :\n\n\n  null}  ,\n-1.5e3\n\n]\n".to_vec());
+
+        for source in &sources {
+            let expected = positions(source);
+            let tokens = Lexer::new(source).tokenify()?;
+            assert!(!tokens.is_empty());
+            for json::TokenPos(token, position, _) in &tokens {
+                assert_eq!(expected[position.offset], (position.line, position.column), "{token:?}");
+            }
+        }
+
+        // errors after line breaks, in and out of tokens
+        let source = b"[\n  1,\n\n  \"a\", $\n]";
+        let err = Lexer::new(source).tokenify().unwrap_err();
+        assert_eq!(Some(Position { line: 4, column: 8, offset: 15 }), err.position());
+        let source = b"[\n  1,\n\n  \"a\\q\", $\n]";
+        let err = Lexer::new(source).tokenify().unwrap_err();
+        assert_eq!(Some(Position { line: 4, column: 5, offset: 12 }), err.position());
+        assert_eq!(err.to_string(), JSONValue::from_slice(source).unwrap_err().to_string());
+
+        // positions are tracked without allocating
+        let (_, allocations) = count_allocations(|| {
+            let mut lexer = Lexer::new(&sources[0]);
+            while lexer.next_token().unwrap().is_some() {}
+        });
+        assert_eq!(0, allocations);
+
+        Ok(())
+    }
+
+    #[test]
+    fn owned_tokens_test() -> json::Result<()> {
+        use super::parser::Parser;
+        use std::borrow::Cow;
+
+        let mut sources: Vec<Vec<u8>> = ["tests/test.json", "tests/array.json", "tests/string.json", "tests/bom.json"]
+            .iter()
+            .map(|fixture| std::fs::read(fixture).unwrap())
+            .collect();
+        let keys: Vec<String> = (0..1000).map(|i| format!(r#""key {i}": ["value {i}", {i}]"#)).collect();
+        sources.push(format!("{{{}}}", keys.join(", ")).into_bytes());
+
+        for source in &sources {
+            let borrowed = Lexer::new(source).tokenify()?;
+            let owned: Vec<_> = borrowed.iter().cloned().map(json::TokenPos::into_owned).collect();
+
+            // the parser allocates for the value it builds and nothing else, and moves strings
+            // out of tokens that own them instead of copying
+            let copied = borrowed.iter()
+                .filter(|token| matches!(&token.0, json::Token::StringLiteral(Cow::Borrowed(text)) if !text.is_empty()))
+                .count();
+            let (from_borrowed, borrowed_allocations) = count_allocations(|| Parser::from(borrowed).parse());
+            let (from_owned, owned_allocations) = count_allocations(|| Parser::from(owned).parse());
+            assert_eq!(from_borrowed?, from_owned?);
+            assert_eq!(borrowed_allocations, owned_allocations + copied);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn unescape_test() -> json::Result<()> {
+        let string = JSONValue::from_str;
+        let s = |text: &str| JSONValue::String(text.to_owned());
+
+        // no escapes, only escapes, and a mix of both
+        assert_eq!(s("plain text, naïve 日本語 😀"), string(r#""plain text, naïve 日本語 😀""#)?);
+        assert_eq!(s(""), string(r#""""#)?);
+        assert_eq!(s("\"\\/\u{8}\u{c}\n\r\té😀"), string(r#""\"\\\/\b\f\n\r\t\u00e9\uD83D\uDE00""#)?);
+        assert_eq!(s("\\\\"), string(r#""\\\\""#)?);
+        assert_eq!(s("a\nb é c\\d 😀e"), string(r#""a\nb \u00E9 c\\d \ud83d\ude00e""#)?);
+        assert_eq!(s("\u{0}x\u{1f}"), string(r#""\u0000x\u001F""#)?);
+
+        // the digits of a unicode escape are hexadecimal digits, and nothing else
+        for bad in ["+FFF", "-001", " 123", "12G4"] {
+            let err = JSONValue::from_str(&format!("\"\\u{bad}\"")).unwrap_err();
+            assert_eq!(format!("invalid hexadecimal code: {bad}"), err.message());
+        }
+
+        // thousands of long strings, with and without escapes
+        let plain = "the quick brown fox jumps over the lazy dog ".repeat(8);
+        let escaped = r#"tab\tquote\"unicode\u00e9\uD83D\uDE00 "#.repeat(8);
+        let strings: Vec<String> = (0..5000)
+            .map(|i| if i % 2 == 0 { format!("\"{plain}{i}\"") } else { format!("\"{escaped}{i}\"") })
+            .collect();
+        let source = format!("[{}]", strings.join(","));
+
+        let t0 = time::Instant::now();
+        let (value, allocations) = count_allocations(|| JSONValue::from_str(&source));
+        println!("time to unescape strings: {}", t0.elapsed().as_secs_f64());
+        let decoded = "tab\tquote\"unicodeé😀 ".repeat(8);
+        let JSONValue::Array(items) = value? else { panic!("expected an array") };
+        for (i, item) in items.into_iter().enumerate() {
+            let expected = if i % 2 == 0 { format!("{plain}{i}") } else { format!("{decoded}{i}") };
+            assert_eq!(JSONValue::String(expected), item);
+        }
+        // one allocation per string, plus a few for the token and value vectors
+        assert!(allocations < 5000 + 64, "{allocations}");
+
+        Ok(())
+    }
+
+    #[test]
+    fn deep_nesting_test() -> json::Result<()> {
+        const DEPTH: usize = 1_000_000;
+        let source = format!("{}{}", "[".repeat(DEPTH), "]".repeat(DEPTH));
+        let mut value = JSONValue::parse_with(source.as_bytes(), &json::Options::default().max_depth(None))?;
+        // dropping the value would recurse once per level, so take it apart from the outside in
+        let mut depth = 0;
+        while let JSONValue::Array(mut items) = value {
+            depth += 1;
+            value = items.pop().unwrap_or(JSONValue::Null);
+            assert!(items.is_empty());
+        }
+        assert_eq!(DEPTH, depth);
+
+        // objects too, with the same limit check at every level
+        let source = format!("{}1{}", "{\"a\":".repeat(DEPTH), "}".repeat(DEPTH));
+        let options = json::Options::default().max_depth(DEPTH);
+        let mut value = JSONValue::parse_with(source.as_bytes(), &options)?;
+        let mut depth = 0;
+        while let JSONValue::Object(mut entries) = value {
+            depth += 1;
+            value = entries.remove("a").unwrap();
+        }
+        assert_eq!((DEPTH, JSONValue::Number(1.0)), (depth, value));
+
+        let source = format!("[{source}]");
+        let err = JSONValue::parse_with(source.as_bytes(), &options).unwrap_err();
+        assert_eq!(Some(Position { line: 1, column: DEPTH * 5 - 3, offset: DEPTH * 5 - 4 }), err.position());
+
+        Ok(())
+    }
+
+    #[test]
+    fn control_character_escape_test() -> json::Result<()> {
+        let value = JSONValue::from_slice(br#"{"a\u0000b": "c\u0000d\u001F"}"#)?;
+        let (key, string) = match &value {
+            JSONValue::Object(map) => map.iter().next().unwrap(),
+            _ => panic!("expected an object"),
+        };
+        assert_eq!(b"a\0b", key.as_bytes());
+        assert_eq!(JSONValue::String("c\0d\u{1f}".to_string()), *string);
+
+        let text = value.to_string();
+        assert!(text.contains(r#""a\u0000b""#), "{}", text);
+        assert!(text.contains(r#""c\u0000d\u001f""#), "{}", text);
+        assert_eq!(value, JSONValue::from_slice(text.as_bytes())?);
+
+        // every control character, plus the characters with short escapes, survives a round trip
+        let all: String = ('\0'..='\u{1f}').chain(['"', '\\', '/', '\u{7f}', 'é']).collect();
+        let value = JSONValue::String(all);
+        let text = value.to_string();
+        assert!(text[1..text.len() - 1].bytes().all(|b| b >= 0x20), "{}", text);
+        assert!(text.contains(r#"\b\t\n"#) && text.contains(r#"\f\r"#), "{}", text);
+        assert_eq!(value, JSONValue::from_slice(text.as_bytes())?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn truncated_document_test() {
+        let mut sources: Vec<Vec<u8>> = ["tests/test.json", "tests/array.json", "tests/string.json"]
+            .iter()
+            .map(|fixture| std::fs::read(fixture).unwrap())
+            .collect();
+        sources.push(r#"{"é\"": ["é😀 \\", -0.5e-3, 1E+2, true, false, null, {}, []]}"#.into());
+        sources.push(b"-12.5e+3".to_vec());
+
+        // every prefix of a document is either a complete document itself, or cut short
+        let assert_eof = |prefix: &[u8], err: JSONError| {
+            assert!(matches!(err, JSONError::UnexpectedEof { .. }), "{:?}: {err}", String::from_utf8_lossy(prefix));
+            let offset = err.position().unwrap().offset;
+            assert!(prefix[offset..].iter().all(u8::is_ascii_whitespace), "{:?}: {err}", String::from_utf8_lossy(prefix));
+        };
+        for source in &sources {
+            for len in 0..source.len() {
+                let prefix = &source[..len];
+                match JSONValue::from_slice(prefix) {
+                    Ok(_) => {
+                        assert!(json::validate(prefix).is_ok());
+                        continue;
+                    }
+                    Err(err) => assert_eof(prefix, err),
+                }
+                let reader = TrickleReader { bytes: prefix, step: 1, fail_at: None, read: 0 };
+                assert_eof(prefix, JSONValue::from_reader(reader).unwrap_err());
+                assert_eof(prefix, json::validate(prefix).unwrap_err());
+                assert_eof(prefix, JSONValue::parse_with(prefix, &json::Options::relaxed()).unwrap_err());
+            }
+        }
+
+        // the error names what was expected next
+        for (source, message) in [
+            ("", "unexpected end of input, expected a value"),
+            ("[", "unexpected end of input, expected a value or CloseBracket"),
+            ("[1", "unexpected end of input, expected Comma or CloseBracket"),
+            ("{", "unexpected end of input, expected a key or CloseBrace"),
+            ("{\"a\"", "unexpected end of input, expected Colon"),
+            ("{\"a\": ", "unexpected end of input, expected a value"),
+            ("{\"a\": 1, ", "unexpected end of input, expected a key"),
+            ("[fals", "unexpected end of input, expected `false`"),
+            ("[1.", "unexpected end of number, expected a digit after the decimal point"),
+        ] {
+            assert_eq!(message, JSONValue::from_str(source).unwrap_err().message(), "{source:?}");
+            assert_eq!(message, json::validate(source.as_bytes()).unwrap_err().message(), "{source:?}");
+        }
+
+        // malformed documents are still syntax errors, even at the end of the input
+        assert!(matches!(JSONValue::from_str("[1 2"), Err(JSONError::SyntaxError { .. })));
+        assert!(matches!(JSONValue::from_str("[1, trux"), Err(JSONError::SyntaxError { .. })));
+    }
+
+    #[test]
+    fn parse_tolerant_test() -> json::Result<()> {
+        let source = std::fs::read_to_string("tests/test.json").unwrap();
+        let full = JSONValue::from_str(&source)?;
+        let (value, errors) = json::parse_tolerant(source.as_bytes());
+        assert_eq!(full, value);
+        assert!(errors.is_empty(), "{errors:?}");
+
+        // cut short anywhere, a document has exactly one problem: its end
+        for len in 0..source.len() {
+            let (_, errors) = json::parse_tolerant(&source.as_bytes()[..len]);
+            assert_eq!(1, errors.len(), "{:?}: {:?}", &source[..len], errors);
+            assert!(matches!(errors[0], JSONError::UnexpectedEof { .. }), "{:?}: {}", &source[..len], errors[0]);
+        }
+        // and everything before the cut is kept
+        let cut = |text: &str| json::parse_tolerant(&source.as_bytes()[..source.find(text).unwrap() + text.len()]).0;
+        let value = cut("fal");
+        assert_eq!(JSONValue::from_str(r#"{"foo": "bar", "tmp": "lmao", "baz": [true, null]}"#)?, value);
+        let value = cut("\"as");
+        assert_eq!(full["obj"], value["obj"]);
+        assert_eq!(JSONValue::from_str(r#"{"qux_null": null, "qux_arr": [2, 3, false, "as"]}"#)?, value["qux"]);
+        let value = cut("\"oof\"");
+        assert_eq!(JSONValue::from_str(r#"[4, 5, 6, {"the_answer": 42, "oof": null}]"#)?, value["qux"]["qux_obj"]["nest_arr"]);
+
+        // corrupted in several places at once, every problem is reported in order
+        let mut expected = full.clone();
+        let corruptions = [
+            // an unfinished string, which runs up to the line break and is missing its comma
+            ("\"bar\",", "\"bar,", 2),
+            // a missing colon
+            ("\"tmp\": ", "\"tmp\" ", 1),
+            // a missing value
+            ("\"a\": 550", "\"a\": ", 1),
+            // a token that can't be lexed
+            ("\"b\": 2.0", "\"b\": 2.0.0", 1),
+            // an unknown keyword standing in for a key
+            ("\"c\":", "c:", 1),
+            // a dangling comma
+            ("\"asdf\", []", "\"asdf\", [],", 1),
+            // a missing closing brace
+            ("\"oof\": {}", "\"oof\": {", 1),
+        ];
+        let mut corrupted = source.clone();
+        for (from, to, _) in corruptions {
+            assert_eq!(1, corrupted.matches(from).count(), "{from}");
+            corrupted = corrupted.replace(from, to);
+        }
+        expected["foo"] = JSONValue::from("bar,".to_string());
+        expected["obj"]["a"] = JSONValue::Null;
+        expected["obj"]["b"] = JSONValue::Null;
+        let JSONValue::Object(obj) = &mut expected["obj"] else { panic!("expected an object") };
+        obj.remove("c");
+
+        let (value, errors) = json::parse_tolerant(corrupted.as_bytes());
+        assert_eq!(expected, value);
+        assert_eq!(corruptions.iter().map(|(.., count)| count).sum::<usize>(), errors.len(), "{errors:#?}");
+        assert!(errors.windows(2).all(|pair| pair[0].position().unwrap().offset <= pair[1].position().unwrap().offset));
+        let messages: Vec<&str> = errors.iter().map(JSONError::message).collect();
+        assert_eq!(vec![
+            "unterminated string",
+            "expected Comma, found StringLiteral(\"tmp\")",
+            "expected Colon, found StringLiteral(\"lmao\")",
+            "expected a value, found Comma",
+            "invalid character '.' in number, expected the end of the number",
+            "expected string literal, found Unknown(\"c\")",
+            "trailing comma before CloseBracket",
+            "expected Comma or CloseBrace, found CloseBracket",
+        ], messages);
+
+        // any single byte changed, the document is recovered, with errors exactly when it is invalid
+        for i in 0..source.len() {
+            for byte in [b'{', b'}', b'[', b']', b':', b',', b'"', b'\\', b'x', b'1', b'\n'] {
+                let mut bytes = source.clone().into_bytes();
+                bytes[i] = byte;
+                let (value, errors) = json::parse_tolerant(&bytes);
+                match JSONValue::from_slice(&bytes) {
+                    Ok(parsed) => assert_eq!((parsed, 0), (value, errors.len())),
+                    Err(_) => assert!(!errors.is_empty()),
+                }
+            }
+        }
+
+        // nothing but problems still gives a value
+        assert_eq!(JSONValue::Null, json::parse_tolerant(b"").0);
+        assert_eq!(JSONValue::Null, json::parse_tolerant(b"@").0);
+        let (value, errors) = json::parse_tolerant(b"[1}, :2 3]]");
+        assert_eq!(JSONValue::from_str("[1, 2, 3]")?, value);
+        assert_eq!(4, errors.len(), "{errors:#?}");
+
+        Ok(())
+    }
+
+    #[test]
+    fn from_byte_iter_test() -> json::Result<()> {
+        let mut sources: Vec<Vec<u8>> = ["tests/test.json", "tests/array.json", "tests/string.json", "tests/bom.json"]
+            .iter()
+            .map(|fixture| std::fs::read(fixture).unwrap())
+            .collect();
+        let doc = r#"{"esc\"aped": "\u00e9\ud83d\ude00 \\", "n": [-0.5e-3, 1234567, 0], "k": [true, false, null]}"#;
+        sources.push(doc.into());
+        sources.push(b"12345".to_vec());
+
+        // one byte at a time, from an iterator that doesn't know its length
+        fn trickle(source: &[u8]) -> impl Iterator<Item = u8> + '_ {
+            let mut bytes = source.iter().copied();
+            std::iter::from_fn(move || bytes.next())
+        }
+        for source in &sources {
+            assert_eq!(JSONValue::from_slice(source)?, JSONValue::from_byte_iter(trickle(source))?);
+        }
+
+        // with the end of the first chunk read falling on every byte of the document, in the
+        // middle of every kind of token
+        for pad in 1..=doc.len() {
+            let source = format!("{}{}", " ".repeat(8 * 1024 - pad), doc);
+            assert_eq!(JSONValue::from_str(&source)?, JSONValue::from_byte_iter(source.bytes())?, "{pad}");
+        }
+
+        // errors are the same as for a slice
+        for source in ["[1, 2", "{\"a\": tru", "[1, \"abc", "[1 2]", "\u{feff}[\"\\x\"]", "[1] 2"] {
+            let expected = JSONValue::from_str(source).unwrap_err().to_string();
+            assert_eq!(expected, JSONValue::from_byte_iter(trickle(source.as_bytes())).unwrap_err().to_string());
+        }
+
+        // bytes are only pulled from the iterator as the lexer reaches them
+        let mut pulled = 0;
+        let bytes = b"[1 2".iter().chain(&[b' '; 1 << 20]).copied().inspect(|_| pulled += 1);
+        assert!(JSONValue::from_byte_iter(bytes).is_err());
+        assert!(pulled <= 8 * 1024, "{pulled}");
+
+        Ok(())
+    }
+
+    #[test]
+    fn scan_block_boundary_test() -> json::Result<()> {
+        // runs of every length up to a few blocks, starting at every offset within a block, and
+        // ended by every kind of byte that stops a scan
+        for start in 0..16 {
+            let pad = " ".repeat(start);
+            for len in 0..50 {
+                let text: String = "aé".chars().cycle().take(len).collect();
+
+                let source = format!("{pad}[\"{text}\"]");
+                assert_eq!(JSONValue::from(vec![JSONValue::from(text.clone())]), JSONValue::from_str(&source)?);
+                let source = format!("{pad}[\"{text}\\\\\"]");
+                assert_eq!(JSONValue::from(vec![JSONValue::from(format!("{text}\\"))]), JSONValue::from_str(&source)?);
+                let source = format!("{pad}[\"{text}\u{1}\"]");
+                let err = JSONValue::from_str(&source).unwrap_err();
+                assert_eq!(Some(source.find('\u{1}').unwrap()), err.position().map(|p| p.offset), "{err}");
+
+                let digits = "1234567890".repeat(5)[..len + 1].to_string();
+                let source = format!("{pad}[{digits}]");
+                assert_eq!(len + 1, Lexer::new(source.as_bytes()).nth(1).unwrap()?.2.range().len());
+                let source = format!("{pad}[{digits}.{digits}e{digits}x]");
+                assert_eq!(Some(start + 1 + 3 * (len + 1) + 2), JSONValue::from_str(&source).unwrap_err().position().map(|p| p.offset));
+
+                let space = " \t\r\n".repeat(len)[..len].to_string();
+                let source = format!("{pad}[{space}1{space}]{space}");
+                assert_eq!(JSONValue::from(vec![JSONValue::from(1)]), JSONValue::from_str(&source)?);
+                let source = format!("{pad}[{space}#");
+                assert_eq!(Some(source.len() - 1), JSONValue::from_str(&source).unwrap_err().position().map(|p| p.offset));
+            }
+        }
+
+        // the vectorized scans agree with the scalar ones on arbitrary bytes
+        #[cfg(feature = "simd")]
+        {
+            use super::lexer::scalar;
+            let mut rng = Rng(7);
+            for _ in 0..2000 {
+                // mostly bytes that continue each kind of run, so the runs get long
+                let alphabet = [b' ', b'\n', b'\t', b'\r', b'0', b'5', b'9', b'a', 0xC3, 0xA9, 0x7F];
+                let stoppers = [b'"', b'\\', 0x00, 0x1F, 0x20, b'/', b':', 0x80, 0xFF];
+                let bytes: Vec<u8> = (0..rng.below(64)).map(|_| match rng.below(40) {
+                    0 => stoppers[rng.below(stoppers.len())],
+                    _ => alphabet[rng.below(alphabet.len())],
+                }).collect();
+                for i in 0..bytes.len() {
+                    let rest = &bytes[i..];
+                    assert_eq!(scalar::whitespace_run(rest), crate::simd::whitespace_run(rest), "{rest:?}");
+                    assert_eq!(scalar::digit_run(rest), crate::simd::digit_run(rest), "{rest:?}");
+                    assert_eq!(scalar::plain_string_run(rest), crate::simd::plain_string_run(rest), "{rest:?}");
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn no_panic_test() {
+        // runs `bytes` through every entry point, which must all return rather than panic, and
+        // agree with each other
+        fn exercise(bytes: &[u8]) {
+            struct Ignore;
+            impl json::Handler for Ignore {}
+
+            let parsed = JSONValue::from_slice(bytes);
+            match &parsed {
+                Ok(value) => {
+                    value.to_string();
+                    value.to_display_string(json::DisplayOptions::default());
+                }
+                Err(err) => {
+                    err.render(bytes);
+                }
+            }
+            let expected = parsed.map_err(|err| err.to_string());
+            let reader = TrickleReader { bytes, step: 3, fail_at: None, read: 0 };
+            assert_eq!(expected, JSONValue::from_reader(reader).map_err(|err| err.to_string()));
+            assert_eq!(expected, JSONValue::from_byte_iter(bytes.iter().copied()).map_err(|err| err.to_string()));
+            assert_eq!(expected.is_ok(), json::validate(bytes).is_ok());
+            assert_eq!(expected.is_ok(), json::parse_events(bytes, &mut Ignore).is_ok());
+            assert_eq!(expected.is_ok(), json::minify(bytes, &mut vec![]).is_ok());
+            assert_eq!(expected.is_ok(), json::Session::new().parse(bytes).is_ok());
+            let (value, errors) = json::parse_tolerant(bytes);
+            if let Ok(expected) = &expected {
+                assert_eq!((expected, 0), (&value, errors.len()));
+            }
+
+            let _ = JSONValue::parse_with(bytes, &json::Options::relaxed());
+            let _ = json::JsonReader::new(bytes).read_value();
+            let _ = json::JsonReader::new(bytes).skip_value();
+            let _ = json::get_path_raw(bytes, "/0/a");
+            let _ = Lexer::new(bytes).tokenify();
+            let _ = json::Deserializer::from_slice(bytes).count();
+            let _ = json::lines_from_slice(bytes).count();
+            let mut stream = json::StreamParser::new();
+            let (head, tail) = bytes.split_at(bytes.len() / 2);
+            if stream.feed(head).is_ok() && stream.feed(tail).is_ok() {
+                let _ = stream.finish();
+            }
+        }
+
+        let mut seeds: Vec<Vec<u8>> = std::fs::read_dir("tests/JSONTestSuite")
+            .unwrap()
+            .chain(std::fs::read_dir("tests").unwrap())
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "json"))
+            .map(|path| std::fs::read(path).unwrap())
+            .collect();
+        seeds.sort();
+        assert!(seeds.len() > 300);
+
+        // bytes that mean something to the lexer or parser, or to UTF-8
+        let special = b"{}[]:,\"\\/ \n\t\r0-+.eEtfnu\x00\x1f\x7f\xc3\xa9\xed\xa0\x80\xff\xef\xbb\xbf";
+        let mut rng = Rng(0x2545F4914F6CDD1D);
+        for _ in 0..10_000 {
+            let mut bytes = seeds[rng.below(seeds.len())].clone();
+            for _ in 0..1 + rng.below(3) {
+                let i = rng.below(bytes.len() + 1);
+                match rng.below(5) {
+                    0 if i < bytes.len() => bytes[i] = special[rng.below(special.len())],
+                    1 => bytes.insert(i, special[rng.below(special.len())]),
+                    2 if i < bytes.len() => {
+                        bytes.remove(i);
+                    }
+                    3 => bytes.truncate(i),
+                    // repeat a stretch of the document, unbalancing brackets and quotes
+                    _ => {
+                        let stretch = bytes[i..(i + rng.below(16)).min(bytes.len())].to_vec();
+                        bytes.splice(i..i, stretch);
+                    }
+                }
+            }
+            if let Err(panic) = std::panic::catch_unwind(|| exercise(&bytes)) {
+                panic!("{:?} on {:?}", panic.downcast_ref::<String>(), String::from_utf8_lossy(&bytes));
+            }
+        }
+
+        // literals that didn't come from the lexer are checked rather than trusted
+        use super::lexer::unescape;
+        use super::parser::number;
+        let start = Position { line: 1, column: 1, offset: 0 };
+        for contents in ["\\", "\\u12", "\\\u{e9}", "\\uD800\\u12", "a\\"] {
+            assert!(unescape(contents, start).is_err(), "{contents}");
+        }
+        assert_eq!(Ok(String::new()), unescape("", start).map_err(|err| err.to_string()));
+        assert_eq!("invalid number `1x`", number("1x", start).unwrap_err().message());
+    }
+
+    #[test]
+    fn differential_test() {
+        // structural equality, with numbers compared as f64 and objects irrespective of order
+        fn same(ours: &JSONValue, theirs: &serde_json::Value) -> bool {
+            use serde_json::Value;
+            match (ours, theirs) {
+                (JSONValue::Null, Value::Null) => true,
+                (JSONValue::Bool(a), Value::Bool(b)) => a == b,
+                (JSONValue::Number(a), Value::Number(b)) => Some(*a) == b.as_f64(),
+                (JSONValue::String(a), Value::String(b)) => a == b,
+                (JSONValue::Array(a), Value::Array(b)) => {
+                    a.len() == b.len() && a.iter().zip(b).all(|(a, b)| same(a, b))
+                }
+                (JSONValue::Object(a), Value::Object(b)) => {
+                    a.len() == b.len() && a.iter().all(|(key, a)| b.get(key).is_some_and(|b| same(a, b)))
+                }
+                _ => false,
+            }
+        }
+
+        // a random document, with arbitrary whitespace, escapes and number forms
+        fn document(rng: &mut Rng, depth: usize, out: &mut String) {
+            const WHITESPACE: [&str; 5] = ["", "", " ", "\n", "\t\r\n "];
+            const PIECES: [&str; 12] = ["a", "Z", " ", "\\\"", "\\\\", "\\/", "\\n", "\\u00e9", "\\uD83D\\uDE00", "é", "😀", "\\u0000"];
+            out.push_str(WHITESPACE[rng.below(WHITESPACE.len())]);
+            let string = |rng: &mut Rng, out: &mut String| {
+                out.push('"');
+                for _ in 0..rng.below(8) {
+                    out.push_str(PIECES[rng.below(PIECES.len())]);
+                }
+                out.push('"');
+            };
+            match rng.below(if depth == 0 { 5 } else { 7 }) {
+                0 => out.push_str(["null", "true", "false"][rng.below(3)]),
+                1 | 2 => {
+                    if rng.below(2) == 0 {
+                        out.push('-');
+                    }
+                    match rng.below(3) {
+                        0 => out.push('0'),
+                        1 => out.push_str(&rng.below(1000).to_string()),
+                        _ => out.push_str(&rng.next().to_string()),
+                    }
+                    if rng.below(2) == 0 {
+                        out.push_str(&format!(".{}", rng.next() % 1_000_000_007));
+                    }
+                    if rng.below(3) == 0 {
+                        out.push_str(["e", "E", "e+", "e-", "E-"][rng.below(5)]);
+                        // not so far that the number overflows, which is covered by the allowlist
+                        out.push_str(&rng.below(280).to_string());
+                    }
+                }
+                3 | 4 => string(rng, out),
+                5 => {
+                    out.push('[');
+                    for i in 0..rng.below(5) {
+                        if i > 0 {
+                            out.push(',');
+                        }
+                        document(rng, depth - 1, out);
+                    }
+                    out.push(']');
+                }
+                _ => {
+                    out.push('{');
+                    for i in 0..rng.below(5) {
+                        if i > 0 {
+                            out.push(',');
+                        }
+                        out.push_str(WHITESPACE[rng.below(WHITESPACE.len())]);
+                        string(rng, out);
+                        out.push(':');
+                        document(rng, depth - 1, out);
+                    }
+                    out.push('}');
+                }
+            }
+            out.push_str(WHITESPACE[rng.below(WHITESPACE.len())]);
+        }
+
+        let mut corpus: Vec<(String, Vec<u8>)> = std::fs::read_dir("tests/JSONTestSuite")
+            .unwrap()
+            .chain(std::fs::read_dir("tests").unwrap())
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "json"))
+            .map(|path| (path.file_stem().unwrap().to_str().unwrap().to_owned(), std::fs::read(path).unwrap()))
+            .collect();
+        corpus.sort();
+        assert!(corpus.len() > 300);
+
+        let mut rng = Rng(0x9E3779B97F4A7C15);
+        for i in 0..5_000 {
+            let mut source = String::new();
+            document(&mut rng, 4, &mut source);
+            corpus.push((format!("random {i}"), source.into_bytes()));
+        }
+
+        let escapes = "\\\"\\\\\\/\\b\\f\\n\\r\\t\\u00e9\\uD834\\uDD1E".repeat(10_000);
+        let edge_cases = [
+            ("nested 127", format!("{}{}", "[".repeat(127), "]".repeat(127))),
+            ("nested 128", format!("{}{}", "[".repeat(128), "]".repeat(128))),
+            ("nested 129", format!("{}{}", "[".repeat(129), "]".repeat(129))),
+            ("nested objects", format!("{}0{}", "{\"a\":".repeat(127), "}".repeat(127))),
+            ("long string", format!("[\"{escapes}\", \"{}\"]", "x".repeat(1 << 20))),
+            ("long key", format!("{{\"{escapes}\": 1}}")),
+            ("extreme numbers", "[1.7976931348623157e308, -1.7976931348623157e308, 5e-324, 2.2250738585072014e-308, \
+                9007199254740993, 18446744073709551615, 18446744073709551616, -9223372036854775809, \
+                0.1e1, 123456789012345678901234567890e-20, 1e-400, -0.0, 0E0]".to_owned()),
+            ("many digits", format!("0.{}1", "0".repeat(1000))),
+        ];
+        corpus.extend(edge_cases.map(|(name, source)| (name.to_owned(), source.into_bytes())));
+
+        // where the two differ on purpose, each with the reason
+        const INTENTIONAL: [(&str, &str); 8] = [
+            ("bom", "a byte order mark at the start of the input is skipped"),
+            ("i_structure_UTF-8_BOM_empty_object", "a byte order mark at the start of the input is skipped"),
+            ("i_number_huge_exp", "numbers too large for an f64 become infinite instead of an error"),
+            ("i_number_neg_int_huge_exp", "numbers too large for an f64 become infinite instead of an error"),
+            ("i_number_pos_double_huge_exp", "numbers too large for an f64 become infinite instead of an error"),
+            ("i_number_real_neg_overflow", "numbers too large for an f64 become infinite instead of an error"),
+            ("i_number_real_pos_overflow", "numbers too large for an f64 become infinite instead of an error"),
+            ("nested 128", "the default maximum depth is 128, one more level than serde_json allows"),
+        ];
+
+        let mut failures = vec![];
+        for (name, source) in &corpus {
+            let intentional = INTENTIONAL.iter().any(|(allowed, _)| allowed == name);
+            let ours = JSONValue::from_slice(source);
+            let theirs = serde_json::from_slice::<serde_json::Value>(source);
+            let agree = match (&ours, &theirs) {
+                (Ok(ours), Ok(theirs)) => same(ours, theirs),
+                (Err(_), Err(_)) => true,
+                _ => false,
+            };
+            if agree == intentional {
+                let ours = ours.map(|value| value.to_string().chars().take(80).collect::<String>());
+                let status = if intentional { "allowlisted but agrees" } else { "disagrees" };
+                failures.push(format!("{name} {status}: {ours:?} vs {theirs:?}"));
+            }
+        }
+        assert!(failures.is_empty(), "{}", failures.join("\n"));
+    }
+
+    #[test]
+    fn lexer_unescape_test() -> json::Result<()> {
+        use std::borrow::Cow;
+
+        // string tokens hold their decoded contents, borrowed unless there was something to decode
+        let source = br#"{"plain": "a\tb", "a\u00e9": ""}"#;
+        let tokens: Vec<_> = Lexer::new(source).tokenify()?.into_iter().map(|token| token.0).collect();
+        let strings: Vec<_> = tokens.iter().filter_map(|token| match token {
+            json::Token::StringLiteral(Cow::Borrowed(text)) => Some((*text, true)),
+            json::Token::StringLiteral(Cow::Owned(text)) => Some((text.as_str(), false)),
+            _ => None,
+        }).collect();
+        assert_eq!(vec![("plain", true), ("a\tb", false), ("aé", false), ("", true)], strings);
+
+        // escape errors come from the lexer, pointing at the bad escape in keys and values alike,
+        // whichever way the document is read
+        let cases = [
+            ("{\"ab\\x\": 1}", "invalid escape char: x", 1, 5),
+            ("{\"a\": \"b\\x\"}", "invalid escape char: x", 1, 9),
+            ("[1,\n  {\"k\\u12G4\": null}]", "invalid hexadecimal code: 12G4", 2, 6),
+            ("{\"k\": [true,\n\t\"é\\uDC00\"]}", "unpaired low surrogate \\uDC00, expected it to follow a high surrogate escape \\uD800-\\uDBFF", 2, 4),
+            ("{\"日本\": \"\\uD800\"}", "unpaired high surrogate \\uD800, expected a low surrogate escape \\uDC00-\\uDFFF to follow", 1, 9),
+        ];
+        for (source, message, line, column) in cases {
+            let err = Lexer::new(source.as_bytes()).tokenify().unwrap_err();
+            assert!(matches!(err, JSONError::LexerError { .. }), "{err}");
+            assert_eq!(message, err.message());
+            let position = err.position().unwrap();
+            assert_eq!((line, column), (position.line, position.column), "{source}");
+            assert_eq!(&source[position.offset..position.offset + 1], "\\");
+
+            let expected = err.to_string();
+            assert_eq!(expected, JSONValue::from_str(source).unwrap_err().to_string());
+            assert_eq!(expected, JSONValue::from_reader(source.as_bytes()).unwrap_err().to_string());
+            assert_eq!(expected, json::validate(source.as_bytes()).unwrap_err().to_string());
+            assert_eq!(expected, json::JsonReader::new(source.as_bytes()).read_value().unwrap_err().to_string());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn char_column_test() {
+        // columns count characters, as an editor does, while offsets still count bytes
+        let cases = [
+            // lexer errors, after multi-byte text on the same line and after a line break
+            ("{\"naïve\": $}", "$", 11),
+            ("[\"日本語\",\n \"😀\", @]", "@", 7),
+            // parser errors
+            ("{\"naïve\": 1 2}", "2", 13),
+            ("{\"é\": [\"ü\" \"x\"]}", "\"x", 12),
+            // errors inside a string, past the multi-byte characters before them
+            ("[\"ä😀\\q\"]", "\\q", 5),
+            ("{\"ö\": \"ü\tx\"}", "\t", 9),
+        ];
+        for (source, at, column) in cases {
+            let err = JSONValue::from_str(source).unwrap_err();
+            let position = err.position().unwrap();
+            assert_eq!(column, position.column, "{source}: {err}");
+            assert_eq!(source.find(at).unwrap(), position.offset, "{source}: {err}");
+            assert_eq!(err.to_string(), json::validate(source.as_bytes()).unwrap_err().to_string());
+        }
+
+        // end of input right after a string points just past its closing quote
+        for source in ["{\"naïve\"", "[\"日本\",\n  \"😀\""] {
+            let expected = source.lines().last().unwrap().chars().count() + 1;
+            let err = JSONValue::from_str(source).unwrap_err();
+            assert!(matches!(err, JSONError::UnexpectedEof { .. }), "{err}");
+            assert_eq!(expected, err.position().unwrap().column, "{source}");
+            assert_eq!(err.to_string(), json::validate(source.as_bytes()).unwrap_err().to_string());
+        }
+
+        // each token ends where the next character starts
+        let source = "[\"ü\", \"日本\",\"😀\"]";
+        let tokens = Lexer::new(source.as_bytes()).tokenify().unwrap();
+        for pair in tokens.windows(2) {
+            let gap = source[pair[0].2.end..pair[1].2.start].chars().count();
+            assert_eq!(pair[0].end().column + gap, pair[1].1.column, "{:?}", pair[1].0);
+        }
+        assert_eq!([1, 2, 5, 7, 11, 12, 15], tokens.iter().map(|token| token.1.column).collect::<Vec<_>>()[..]);
+    }
+
+    #[test]
+    fn display_round_trip_test() -> json::Result<()> {
+        // every escape class, in keys as well as values, at every level of nesting
+        let strings = ["say \"hi\"", "C:\\temp\\", "two\nlines\r\n", "a\tb", "\u{8}\u{c}", "\u{1}\u{1b}\u{1f}", "\"", "\\"];
+        let mut object = json::Map::new();
+        for (i, text) in strings.iter().enumerate() {
+            object.insert(text.to_string(), JSONValue::String(text.to_string()));
+            object.insert(format!("nested {i}"), JSONValue::Array(vec![JSONValue::String(text.to_string())]));
+        }
+        object.insert("key with a \" quote".to_string(), JSONValue::Object(object.clone()));
+        let value = JSONValue::Object(object);
+
+        let text = value.to_string();
+        assert!(text.contains(r#""key with a \" quote": {"#), "{text}");
+        for escaped in [r#""say \"hi\"""#, r#""C:\\temp\\""#, r#""two\nlines\r\n""#, r#""a\tb""#, r#""\b\f""#, r#""\u0001\u001b\u001f""#, r#""\"""#, r#""\\""#] {
+            assert!(text.contains(escaped), "{escaped} in {text}");
+        }
+        assert_eq!(value, text.parse::<JSONValue>()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn format_options_test() -> json::Result<()> {
+        let value = JSONValue::from_str(r#"{"list": [1, "two", {"three": [true, null, []]}, {}]}"#)?;
+        let two_spaces = json::FormatOptions { indent_width: 2, ..Default::default() };
+        assert_eq!("\
+{
+  \"list\": [
+    1,
+    \"two\",
+    {
+      \"three\": [
+        true,
+        null,
+        []
+      ]
+    },
+    {}
+  ]
+}", value.to_string_with(&two_spaces));
+
+        let tabs = json::FormatOptions { indent_width: 1, indent_char: '\t', space_after_colon: false, ..Default::default() };
+        assert_eq!("\
+{
+\t\"list\":[
+\t\t1,
+\t\t\"two\",
+\t\t{
+\t\t\t\"three\":[
+\t\t\t\ttrue,
+\t\t\t\tnull,
+\t\t\t\t[]
+\t\t\t]
+\t\t},
+\t\t{}
+\t]
+}", value.to_string_with(&tabs));
+
+        let crlf = json::FormatOptions { newline: json::Newline::CrLf, ..Default::default() };
+        let expected = value.to_string().replace('\n', "\r\n");
+        assert_eq!(expected, value.to_string_with(&crlf));
+        assert!(expected.starts_with("{\r\n    \"list\": [\r\n        1,\r\n"), "{expected}");
+
+        // the defaults are what Display writes, and every layout parses back to the same value
+        assert_eq!(value.to_string(), value.to_string_with(&json::FormatOptions::default()));
+        let fixture = JSONValue::from_str(&std::fs::read_to_string("tests/test.json").unwrap())?;
+        for opts in [two_spaces, tabs, crlf, json::FormatOptions { indent_width: 0, ..Default::default() }] {
+            assert_eq!(fixture, JSONValue::from_str(&fixture.to_string_with(&opts))?);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn key_order_test() -> json::Result<()> {
+        // every key of `value`, depth first, in the order the maps hold them
+        fn keys(value: &JSONValue, out: &mut Vec<String>) {
+            match value {
+                JSONValue::Object(map) => map.iter().for_each(|(key, value)| {
+                    out.push(key.clone());
+                    keys(value, out);
+                }),
+                JSONValue::Array(items) => items.iter().for_each(|item| keys(item, out)),
+                _ => {}
+            }
+        }
+        let order = |value: &JSONValue| {
+            let mut out = vec![];
+            keys(value, &mut out);
+            out
+        };
+
+        // keys are the string literals followed by a colon, in document order
+        let source = std::fs::read("tests/test.json").unwrap();
+        let tokens = Lexer::new(&source).tokenify()?;
+        let expected: Vec<String> = tokens.windows(2).filter_map(|pair| match (&pair[0].0, &pair[1].0) {
+            (json::Token::StringLiteral(key), json::Token::Colon) => Some(key.to_string()),
+            _ => None,
+        }).collect();
+        assert_eq!(["foo", "tmp", "baz", "obj", "a", "b", "c", "qux"], expected[..8]);
+
+        let value = JSONValue::from_slice(&source)?;
+        assert_eq!(expected, order(&value));
+        assert_eq!(expected, order(&JSONValue::from_reader(&source[..])?));
+        assert_eq!(expected, order(&json::JsonReader::new(&source).read_value()?));
+        assert_eq!(expected, order(&json::parse_tolerant(&source).0));
+        // printing and parsing again keeps the order
+        let printed = value.to_string();
+        assert_eq!(expected, order(&JSONValue::from_str(&printed)?));
+        assert_eq!(printed, JSONValue::from_str(&printed)?.to_string());
+
+        // new keys go last, replaced values keep their place, and removing a key keeps the rest
+        // in order
+        let mut value = JSONValue::from_str(r#"{"z": 1, "a": 2, "m": 3}"#)?;
+        value.obj_insert("b", JSONValue::from(4.0))?;
+        value.obj_remove("a")?;
+        value["z"] = JSONValue::from(5.0);
+        assert_eq!(["z", "m", "b"], order(&value)[..]);
+        assert_eq!(JSONValue::from_str(r#"{"z": 5, "m": 3, "b": 4}"#)?, value);
+        assert_eq!(&JSONValue::from(3.0), value.get("m")?);
+        // a repeated key keeps the place of its first occurrence
+        let last = JSONValue::from_str(r#"{"a": 1, "b": 2, "a": 3}"#)?;
+        assert_eq!(["a", "b"], order(&last)[..]);
+        assert_eq!(JSONValue::from(3.0), last["a"]);
+
+        // equality ignores order, as objects are unordered in JSON
+        assert_eq!(JSONValue::from_str(r#"{"a": 1, "b": [{"c": 2, "d": 3}]}"#)?, JSONValue::from_str(r#"{"b": [{"d": 3, "c": 2}], "a": 1}"#)?);
+        assert_ne!(JSONValue::from_str(r#"{"a": 1, "b": 2}"#)?, JSONValue::from_str(r#"{"a": 1, "c": 2}"#)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn write_to_test() -> json::Result<()> {
+        // accepts bytes until `limit` have been written, then fails every write
+        struct FailingWriter {
+            written: Vec<u8>,
+            limit: usize,
+        }
+        impl std::io::Write for FailingWriter {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                let room = self.limit - self.written.len();
+                if room == 0 {
+                    return Err(std::io::Error::new(std::io::ErrorKind::WriteZero, "disk full"));
+                }
+                let len = buf.len().min(room);
+                self.written.extend_from_slice(&buf[..len]);
+                Ok(len)
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let value = JSONValue::from_str(&std::fs::read_to_string("tests/test.json").unwrap())?;
+        let mut sink = vec![];
+        value.write_to(&mut sink)?;
+        assert_eq!(value.to_string().as_bytes(), sink);
+        let tabs = json::FormatOptions { indent_width: 1, indent_char: '\t', newline: json::Newline::CrLf, ..Default::default() };
+        let mut sink = vec![];
+        value.write_pretty_to(&mut sink, &tabs)?;
+        assert_eq!(value.to_string_with(&tabs).as_bytes(), sink);
+
+        // the writer's error comes back as is, wherever in the output it happens
+        let text = value.to_string();
+        for limit in [0, 1, 17, text.len() - 1] {
+            let mut writer = FailingWriter { written: vec![], limit };
+            let err = value.write_to(&mut writer).unwrap_err();
+            let JSONError::IoError { source, .. } = &err else { panic!("expected an IO error, found {err}") };
+            assert_eq!(std::io::ErrorKind::WriteZero, source.kind());
+            assert_eq!("JSON IO Error: disk full", err.to_string());
+            assert_eq!(text.as_bytes()[..limit], writer.written[..]);
+        }
+        let mut writer = FailingWriter { written: vec![], limit: text.len() };
+        value.write_to(&mut writer)?;
+        assert_eq!(text.as_bytes(), writer.written);
+
+        Ok(())
+    }
+
+    #[test]
+    fn to_vec_test() -> json::Result<()> {
+        let mut values: Vec<JSONValue> = ["tests/test.json", "tests/array.json", "tests/string.json"]
+            .iter()
+            .map(|fixture| JSONValue::from_slice(&std::fs::read(fixture).unwrap()))
+            .collect::<json::Result<_>>()?;
+        values.push(JSONValue::from_str(r#"{"a\"b": ["\\", "\n\t\u0001", {"é": "日本 😀"}, [], {}], "c": -1.5e-7}"#)?);
+
+        for value in &values {
+            let compact = value.to_vec();
+            assert_eq!(value.to_string_compact().into_bytes(), compact);
+            assert_eq!(value.to_string().into_bytes(), value.to_vec_pretty());
+            // compact output is the pretty output minified
+            let mut minified = vec![];
+            json::minify(&value.to_vec_pretty(), &mut minified)?;
+            assert_eq!(minified, compact);
+            assert_eq!(*value, JSONValue::from_slice(&compact)?);
+        }
+        assert_eq!(br#"{"a":[1,{},[]],"b":null}"#.to_vec(), JSONValue::from_str(r#"{"a": [1, {}, []], "b": null}"#)?.to_vec());
+
+        Ok(())
+    }
+
+    #[test]
+    fn number_round_trip_test() -> json::Result<()> {
+        for (n, text) in [
+            (3.0, "3"),
+            (-0.0, "-0"),
+            (0.0, "0"),
+            (-42.0, "-42"),
+            (0.1 + 0.2, "0.30000000000000004"),
+            (1e15, "1000000000000000"),
+            (1e18, "1000000000000000000"),
+            (i64::MIN as f64, "-9223372036854775808"),
+            (i64::MAX as f64, "9.223372036854776e18"),
+            (1e21, "1e21"),
+            (1e-7, "1e-7"),
+            (1.5e-7, "1.5e-7"),
+            (0.0001, "0.0001"),
+            (1e16, "10000000000000000"),
+            (f64::MAX, "1.7976931348623157e308"),
+            (f64::MIN_POSITIVE, "2.2250738585072014e-308"),
+            (5e-324, "5e-324"),
+        ] {
+            assert_eq!(text, JSONValue::Number(n).to_string(), "{n:?}");
+        }
+
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        let mut checked = 0;
+        while checked < 200_000 {
+            // raw bit patterns cover every exponent, subnormals included; small integers and
+            // their neighbours are too rare among them to leave to chance
+            let n = match checked % 4 {
+                0 => (rng.next() as i64 >> rng.below(64)) as f64,
+                1 => f64::from_bits(((rng.next() as i64 >> rng.below(64)) as f64).to_bits() ^ 1),
+                _ => f64::from_bits(rng.next()),
+            };
+            if !n.is_finite() {
+                continue;
+            }
+            let text = JSONValue::Number(n).to_string();
+            match JSONValue::from_str(&text)? {
+                JSONValue::Number(parsed) => assert_eq!(n.to_bits(), parsed.to_bits(), "{n:?} printed as {text}"),
+                other => panic!("{n:?} printed as {text}, which parses to {other:?}"),
+            }
+            if n.fract() == 0.0 && n.abs() < 9.2e18 {
+                assert!(text.bytes().all(|b| b == b'-' || b.is_ascii_digit()), "{n:?} printed as {text}");
+            }
+            checked += 1;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn non_finite_test() -> json::Result<()> {
+        for (n, name) in [(f64::NAN, "\"NaN\""), (f64::INFINITY, "\"Infinity\""), (f64::NEG_INFINITY, "\"-Infinity\"")] {
+            // constructing one is fine, only serializing it isn't
+            let top = JSONValue::from(n);
+            assert!(matches!(top, JSONValue::Number(v) if v.is_nan() == n.is_nan()));
+            let nested = JSONValue::Array(vec![1.into(), JSONValue::Array(vec![n.into()])]);
+
+            assert_eq!("null", top.to_string());
+            assert_eq!("[1,[null]]", nested.to_string_compact());
+            assert_eq!(b"[1,[null]]".to_vec(), nested.to_vec());
+            assert_eq!(JSONValue::from_str("[1, [null]]")?, JSONValue::from_str(&nested.to_string())?);
+
+            for value in [&top, &nested] {
+                assert!(matches!(value.try_to_string(), Err(JSONError::ValueError(_))), "{n}");
+                assert!(matches!(value.write_to(&mut vec![]), Err(JSONError::ValueError(_))), "{n}");
+            }
+
+            let null = json::FormatOptions { non_finite: json::NonFinite::Null, ..json::FormatOptions::compact() };
+            assert_eq!("[1,[null]]", nested.try_to_string_with(&null)?);
+            let string = json::FormatOptions { non_finite: json::NonFinite::String, ..json::FormatOptions::compact() };
+            assert_eq!(name, top.try_to_string_with(&string)?);
+            let mut out = vec![];
+            nested.write_pretty_to(&mut out, &string)?;
+            assert_eq!(format!("[1,[{name}]]").into_bytes(), out);
+        }
+
+        let finite = JSONValue::Array(vec![1.5.into(), JSONValue::Null]);
+        assert_eq!(finite.to_string(), finite.try_to_string()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn ascii_only_test() -> json::Result<()> {
+        let ascii = json::FormatOptions { ascii_only: true, ..json::FormatOptions::compact() };
+        for (text, utf8, escaped) in [
+            ("café", r#""café""#, r#""caf\u00e9""#),
+            ("日本語", r#""日本語""#, r#""\u65e5\u672c\u8a9e""#),
+            ("😀", r#""😀""#, r#""\ud83d\ude00""#),
+            // the usual escapes still come first
+            ("\"é\"\n", r#""\"é\"\n""#, r#""\"\u00e9\"\n""#),
+        ] {
+            let value = JSONValue::String(text.to_string());
+            assert_eq!(utf8, value.to_string_compact());
+            assert_eq!(escaped, value.to_string_with(&ascii));
+        }
+
+        let value = JSONValue::from_str(r#"{"clé": ["é", "日本語", "😀", {"😀": "a\"b"}]}"#)?;
+        for opts in [ascii.clone(), json::FormatOptions { ascii_only: true, ..Default::default() }] {
+            let out = value.try_to_string_with(&opts)?;
+            assert!(out.is_ascii(), "{out}");
+            assert_eq!(value, JSONValue::from_str(&out)?);
+        }
+        assert!(!value.to_string().is_ascii());
+
+        Ok(())
+    }
+
+    #[test]
+    fn canonical_test() -> json::Result<()> {
+        // RFC 8785 section 3.2.2
+        let value = JSONValue::from_str(r#"{
+            "numbers": [333333333.33333329, 1E30, 4.50, 2e-3, 0.000000000000000000000000001],
+            "string": "\u20ac$\u000F\u000aA'\u0042\u0022\u005c\\\"\/",
+            "literals": [null, true, false]
+        }"#)?;
+        assert_eq!(
+            r#"{"literals":[null,true,false],"numbers":[333333333.3333333,1e+30,4.5,0.002,1e-27],"string":"€$\u000f\nA'B\"\\\\\"/"}"#,
+            value.to_canonical_string()?
+        );
+
+        // RFC 8785 section 3.2.3, where UTF-16 order differs from that of code points
+        let value = JSONValue::from_str(r#"{
+            "\u20ac": "Euro Sign",
+            "\r": "Carriage Return",
+            "\ufb33": "Hebrew Letter Dalet With Dagesh",
+            "1": "One",
+            "\ud83d\ude00": "Emoji: Grinning Face",
+            "\u0080": "Control",
+            "\u00f6": "Latin Small Letter O With Diaeresis"
+        }"#)?;
+        assert_eq!(
+            concat!(
+                r#"{"\r":"Carriage Return","1":"One","#,
+                "\"\u{80}\":\"Control\",",
+                r#""ö":"Latin Small Letter O With Diaeresis","€":"Euro Sign","😀":"Emoji: Grinning Face","#,
+                r#""דּ":"Hebrew Letter Dalet With Dagesh"}"#,
+            ),
+            value.to_canonical_string()?
+        );
+
+        // RFC 8785 appendix B
+        for (bits, text) in [
+            (0x0000000000000000u64, "0"),
+            (0x8000000000000000, "0"),
+            (0x0000000000000001, "5e-324"),
+            (0x8000000000000001, "-5e-324"),
+            (0x7fefffffffffffff, "1.7976931348623157e+308"),
+            (0xffefffffffffffff, "-1.7976931348623157e+308"),
+            (0x4340000000000000, "9007199254740992"),
+            (0xc340000000000000, "-9007199254740992"),
+            (0x4430000000000000, "295147905179352830000"),
+            (0x44b52d02c7e14af5, "9.999999999999997e+22"),
+            (0x44b52d02c7e14af6, "1e+23"),
+            (0x44b52d02c7e14af7, "1.0000000000000001e+23"),
+            (0x444b1ae4d6e2ef4e, "999999999999999700000"),
+            (0x444b1ae4d6e2ef4f, "999999999999999900000"),
+            (0x444b1ae4d6e2ef50, "1e+21"),
+            (0x3eb0c6f7a0b5ed8c, "9.999999999999997e-7"),
+            (0x3eb0c6f7a0b5ed8d, "0.000001"),
+            (0x41b3de4355555553, "333333333.3333332"),
+            (0x41b3de4355555554, "333333333.33333325"),
+            (0x41b3de4355555555, "333333333.3333333"),
+            (0x41b3de4355555556, "333333333.3333334"),
+            (0x41b3de4355555557, "333333333.33333343"),
+            (0xbecbf647612f3696, "-0.0000033333333333333333"),
+            (0x43143ff3c1cb0959, "1424953923781206.2"),
+        ] {
+            let n = f64::from_bits(bits);
+            assert_eq!(text, JSONValue::Number(n).to_canonical_string()?, "{n:?}");
+        }
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..20_000 {
+            let n = f64::from_bits(rng.next());
+            if n.is_finite() && n != 0.0 {
+                let text = JSONValue::Number(n).to_canonical_string()?;
+                assert_eq!(JSONValue::Number(n), JSONValue::from_str(&text)?, "{n:?} canonicalized as {text}");
+            }
+        }
+        for bits in [0x7fffffffffffffffu64, 0x7ff0000000000000, 0xfff0000000000000] {
+            let value = JSONValue::Array(vec![JSONValue::Number(f64::from_bits(bits))]);
+            assert!(matches!(value.to_canonical_string(), Err(JSONError::ValueError(_))));
+        }
+
+        // key order and whitespace in the input make no difference
+        let a = JSONValue::from_str(r#"{"b": [1, {"y": 2, "x": 1}], "a": "é"}"#)?;
+        let b = JSONValue::from_str(r#"{"a":"é","b":[1.0,{"x":1,"y":2.0}]}"#)?;
+        assert_eq!(r#"{"a":"é","b":[1,{"x":1,"y":2}]}"#, a.to_canonical_string()?);
+        assert_eq!(a.to_canonical_string()?, b.to_canonical_string()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn empty_container_test() -> json::Result<()> {
+        assert_eq!("{}", JSONValue::Object(json::Map::new()).to_string());
+        assert_eq!("[]", JSONValue::Array(vec![]).to_string());
+
+        let value = JSONValue::from_str(r#"{"list": [], "n": 1}"#)?;
+        assert_eq!("{\n    \"list\": [],\n    \"n\": 1\n}", value.to_string());
+
+        let value = JSONValue::from_str(r#"{"inner": {}}"#)?;
+        assert_eq!("{\n    \"inner\": {}\n}", value.to_string());
+
+        let value = JSONValue::from_str("[[[{}], []]]")?;
+        assert_eq!("[\n    [\n        [\n            {}\n        ],\n        []\n    ]\n]", value.to_string());
+        assert_eq!("[[[{}],[]]]", value.to_string_compact());
+
+        Ok(())
+    }
+
+    #[test]
+    fn writer_test() -> json::Result<()> {
+        // writes `expected` below, piece by piece
+        fn write<W: std::io::Write>(writer: &mut json::Writer<W>) -> json::Result<()> {
+            writer.begin_object()?;
+            writer.key("name")?;
+            writer.string("ünïcode \"quoted\"")?;
+            writer.key("items")?;
+            writer.begin_array()?;
+            for i in 0..3 {
+                writer.number(i as f64 * 1.5)?;
+            }
+            writer.value(&JSONValue::from_str(r#"{"ok": true}"#)?)?;
+            writer.begin_array()?;
+            writer.end_array()?;
+            writer.end_array()?;
+            writer.key("none")?;
+            writer.null()?;
+            writer.key("empty")?;
+            writer.begin_object()?;
+            writer.end_object()?;
+            writer.key("flag")?;
+            writer.bool(false)?;
+            writer.end_object()
+        }
+        let expected = JSONValue::from_str(r#"{
+            "name": "ünïcode \"quoted\"",
+            "items": [0, 1.5, 3, {"ok": true}, []],
+            "none": null,
+            "empty": {},
+            "flag": false
+        }"#)?;
+
+        let mut writer = json::Writer::new(vec![]);
+        write(&mut writer)?;
+        let compact = writer.finish()?;
+        assert_eq!(expected.to_vec(), compact);
+
+        let mut writer = json::Writer::with_options(vec![], json::FormatOptions::default());
+        write(&mut writer)?;
+        let pretty = writer.finish()?;
+        assert_eq!(expected.to_string().into_bytes(), pretty);
+        assert_eq!(expected, JSONValue::from_slice(&pretty)?);
+
+        let mut writer = json::Writer::new(vec![]);
+        writer.number(1.0)?;
+        assert!(matches!(writer.number(2.0), Err(JSONError::ValueError(_))));
+        assert_eq!(b"1".to_vec(), writer.finish()?);
+
+        // every illegal call is refused, and leaves the output as it was
+        type Step = fn(&mut json::Writer<Vec<u8>>) -> json::Result<()>;
+        let illegal: [(&[Step], Step); 8] = [
+            (&[|w| w.begin_object(), |w| w.key("a")], |w| w.key("b")),
+            (&[|w| w.begin_object()], |w| w.number(1.0)),
+            (&[|w| w.begin_object(), |w| w.key("a")], |w| w.end_object()),
+            (&[|w| w.begin_object()], |w| w.end_array()),
+            (&[|w| w.begin_array()], |w| w.end_object()),
+            (&[|w| w.begin_array()], |w| w.key("a")),
+            (&[], |w| w.end_array()),
+            (&[|w| w.begin_array(), |w| w.end_array()], |w| w.end_array()),
+        ];
+        for (setup, step) in illegal {
+            let mut writer = json::Writer::new(vec![]);
+            for call in setup {
+                call(&mut writer)?;
+            }
+            let written = writer.get_ref().clone();
+            assert!(matches!(step(&mut writer), Err(JSONError::ValueError(_))), "{}", String::from_utf8_lossy(&written));
+            assert_eq!(&written, writer.get_ref());
+        }
+
+        // the document has to be complete
+        assert!(json::Writer::new(vec![]).finish().is_err());
+        let mut writer = json::Writer::new(vec![]);
+        writer.begin_array()?;
+        writer.begin_object()?;
+        assert!(matches!(writer.finish(), Err(JSONError::ValueError(_))));
+
+        let mut writer = json::Writer::new(vec![]);
+        writer.begin_array()?;
+        assert!(matches!(writer.number(f64::NAN), Err(JSONError::ValueError(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn file_test() -> json::Result<()> {
+        let dir = std::env::temp_dir().join(format!("json-rs-file-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let value = JSONValue::from_file("tests/test.json")?;
+        assert_eq!(JSONValue::from_slice(&std::fs::read("tests/test.json").unwrap())?, value);
+
+        let path = dir.join("out.json");
+        value.to_file(&path, &json::FormatOptions::default())?;
+        assert_eq!(value.to_string(), std::fs::read_to_string(&path).unwrap());
+        // an existing file is replaced
+        value.to_file(&path, &json::FormatOptions::compact())?;
+        assert_eq!(value.to_string_compact(), std::fs::read_to_string(&path).unwrap());
+        assert_eq!(value, JSONValue::from_file(&path)?);
+
+        // parse errors are those of the contents
+        std::fs::write(&path, "[1, 2,]").unwrap();
+        assert_eq!(JSONValue::from_str("[1, 2,]").unwrap_err().to_string(), JSONValue::from_file(&path).unwrap_err().to_string());
+
+        let missing = dir.join("missing").join("file.json");
+        let err = JSONValue::from_file(&missing).unwrap_err();
+        assert!(matches!(&err, JSONError::IoError { source, .. } if source.kind() == std::io::ErrorKind::NotFound), "{err}");
+        assert!(err.message().contains(&missing.display().to_string()), "{err}");
+        let err = value.to_file(&missing, &json::FormatOptions::default()).unwrap_err();
+        assert!(matches!(&err, JSONError::IoError { source, .. } if source.kind() == std::io::ErrorKind::NotFound), "{err}");
+        assert!(err.message().contains(&missing.display().to_string()), "{err}");
+
+        // a directory can be neither read nor written as a file
+        assert!(matches!(JSONValue::from_file(&dir), Err(JSONError::IoError { .. })));
+        assert!(matches!(value.to_file(&dir, &json::FormatOptions::default()), Err(JSONError::IoError { .. })));
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let locked = dir.join("locked.json");
+            std::fs::write(&locked, "1").unwrap();
+            std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o000)).unwrap();
+            // permissions don't apply to root
+            if std::fs::read(&locked).is_err() {
+                let err = JSONValue::from_file(&locked).unwrap_err();
+                assert!(matches!(&err, JSONError::IoError { source, .. } if source.kind() == std::io::ErrorKind::PermissionDenied), "{err}");
+                let err = value.to_file(&locked, &json::FormatOptions::default()).unwrap_err();
+                assert!(err.message().contains("locked.json"), "{err}");
+            }
+        }
+
+        // a value that can't be serialized is still a ValueError
+        let nan = JSONValue::Array(vec![f64::NAN.into()]);
+        assert!(matches!(nan.to_file(&path, &json::FormatOptions::default()), Err(JSONError::ValueError(_))));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        Ok(())
+    }
+
+    #[test]
+    fn number_format_test() -> json::Result<()> {
+        use json::ExponentNotation::{Always, Auto, Never};
+
+        let number = |n: f64, digits: Option<u8>, exponent_notation| {
+            let opts = json::FormatOptions { max_significant_digits: digits, exponent_notation, ..json::FormatOptions::compact() };
+            JSONValue::Number(n).to_string_with(&opts)
+        };
+        for (n, digits, notation, text) in [
+            (0.000001234567, Some(6), Auto, "1.23457e-6"),
+            (0.000001234567, Some(6), Never, "0.00000123457"),
+            (0.000001234567, Some(6), Always, "1.23457e-6"),
+            (0.000001234567, None, Never, "0.000001234567"),
+            (1e30, None, Never, "1000000000000000000000000000000"),
+            (1e30, None, Auto, "1e30"),
+            (-1.5e-7, None, Never, "-0.00000015"),
+            (1500.0, None, Always, "1.5e3"),
+            (0.0, None, Always, "0e0"),
+            (0.1 + 0.2, Some(6), Auto, "0.3"),
+            // trailing zeros go, and rounding is that of the exact value, which is just above 1.995
+            // and just below 1.005
+            (1.25, Some(6), Auto, "1.25"),
+            (1.995, Some(3), Auto, "2"),
+            (1.005, Some(3), Auto, "1"),
+            (1.0051, Some(3), Auto, "1.01"),
+            (1234567.89, Some(3), Auto, "1230000"),
+            (1234567.89, Some(3), Always, "1.23e6"),
+            (2.0f64.sqrt(), Some(0), Auto, "1"),
+            (f64::MAX, Some(1), Auto, "1.7976931348623157e308"),
+            // integers are never rounded
+            (123456789.0, Some(3), Auto, "123456789"),
+            (-42.0, Some(1), Never, "-42"),
+            (123456789.0, Some(3), Always, "1.23456789e8"),
+        ] {
+            assert_eq!(text, number(n, digits, notation), "{n:?} {digits:?} {notation:?}");
+        }
+
+        // every form parses back, to the same value when nothing was rounded
+        let mut rng = Rng(0x1234_5678_9abc_def1);
+        for _ in 0..10_000 {
+            let n = f64::from_bits(rng.next());
+            if !n.is_finite() {
+                continue;
+            }
+            for notation in [Auto, Never, Always] {
+                assert_eq!(JSONValue::Number(n), JSONValue::from_str(&number(n, None, notation))?, "{n:?} {notation:?}");
+                JSONValue::from_str(&number(n, Some(5), notation))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn inline_threshold_test() -> json::Result<()> {
+        let value = JSONValue::from_str(r#"{
+            "name": "matrix",
+            "rows": [[1, 0, 0], [0, 1, 0], [0, 0, 1]],
+            "meta": {"origin": [0.5, -2], "tags": ["a", "b"], "description": "a fairly long string that never fits"},
+            "empty": []
+        }"#)?;
+        let layout = |inline_threshold| value.to_string_with(&json::FormatOptions { indent_width: 2, inline_threshold, ..Default::default() });
+
+        assert_eq!(value.to_string_with(&json::FormatOptions { indent_width: 2, ..Default::default() }), layout(0));
+        assert!(layout(0).contains("[\n      1,\n      0,\n      0\n    ]"), "{}", layout(0));
+
+        assert_eq!(r#"{
+  "name": "matrix",
+  "rows": [
+    [1, 0, 0],
+    [0, 1, 0],
+    [0, 0, 1]
+  ],
+  "meta": {
+    "origin": [0.5, -2],
+    "tags": ["a", "b"],
+    "description": "a fairly long string that never fits"
+  },
+  "empty": []
+}"#, layout(12));
+
+        assert_eq!(r#"{
+  "name": "matrix",
+  "rows": [[1, 0, 0], [0, 1, 0], [0, 0, 1]],
+  "meta": {
+    "origin": [0.5, -2],
+    "tags": ["a", "b"],
+    "description": "a fairly long string that never fits"
+  },
+  "empty": []
+}"#, layout(40));
+
+        // the budget is inclusive, and counts characters rather than bytes
+        let rows = r#"[[1, 0, 0], [0, 1, 0], [0, 0, 1]]"#;
+        assert_eq!(rows, JSONValue::from_str(rows)?.to_string_with(&json::FormatOptions { inline_threshold: rows.len(), ..Default::default() }));
+        assert!(JSONValue::from_str(rows)?.to_string_with(&json::FormatOptions { inline_threshold: rows.len() - 1, ..Default::default() }).contains('\n'));
+        assert_eq!(r#"["é", "ü"]"#, JSONValue::from_str(r#"["é","ü"]"#)?.to_string_with(&json::FormatOptions { inline_threshold: 10, ..Default::default() }));
+
+        for threshold in [0, 1, 12, 40, 1000] {
+            assert_eq!(value, JSONValue::from_str(&layout(threshold))?);
+        }
+        assert!(!layout(1000).contains('\n'));
+
+        Ok(())
+    }
+
+    #[test]
+    fn escape_html_test() -> json::Result<()> {
+        let value = JSONValue::from_str("{\"</script>\": \"a & b < c > d\u{2028}e\u{2029}\", \"url\": \"https://x/y\"}")?;
+        let html = json::FormatOptions { escape_html: true, ..json::FormatOptions::compact() };
+        assert_eq!(
+            r#"{"\u003c/script\u003e":"a \u0026 b \u003c c \u003e d\u2028e\u2029","url":"https://x/y"}"#,
+            value.to_string_with(&html)
+        );
+        let solidus = json::FormatOptions { escape_solidus: true, ..json::FormatOptions::compact() };
+        assert_eq!(
+            "{\"<\\/script>\":\"a & b < c > d\u{2028}e\u{2029}\",\"url\":\"https:\\/\\/x\\/y\"}",
+            value.to_string_with(&solidus)
+        );
+        let both = json::FormatOptions { escape_solidus: true, ..html.clone() };
+        assert_eq!(
+            r#"{"\u003c\/script\u003e":"a \u0026 b \u003c c \u003e d\u2028e\u2029","url":"https:\/\/x\/y"}"#,
+            value.to_string_with(&both)
+        );
+        // the usual escapes still come first, and ascii_only composes
+        let quoted = JSONValue::String("\"<é>\"\n".to_string());
+        let ascii = json::FormatOptions { ascii_only: true, ..both.clone() };
+        assert_eq!(r#""\"\u003c\u00e9\u003e\"\n""#, quoted.to_string_with(&ascii));
+
+        for opts in [html, solidus, both, ascii] {
+            for value in [&value, &quoted] {
+                let out = value.to_string_with(&opts);
+                assert!(out.is_ascii() || !opts.ascii_only, "{out}");
+                assert!(!out.contains("</"), "{out}");
+                assert_eq!(*value, JSONValue::from_str(&out)?);
+            }
+        }
+        // off by default
+        assert!(value.to_string().contains("</script>"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn colored_test() -> json::Result<()> {
+        // removes every `ESC [ ... m` sequence
+        fn strip_ansi(text: &str) -> String {
+            let mut out = String::new();
+            let mut chars = text.chars();
+            while let Some(c) = chars.next() {
+                if c == '\x1b' {
+                    assert_eq!(Some('['), chars.next());
+                    for c in chars.by_ref() {
+                        if c == 'm' {
+                            break;
+                        }
+                    }
+                } else {
+                    out.push(c);
+                }
+            }
+            out
+        }
+
+        let mut value = JSONValue::from_file("tests/test.json")?;
+        value.obj_insert("extra", JSONValue::from_str(r#"{"s": "a\"b", "n": -1.5e-7, "t": true, "f": false, "z": null, "e": [], "o": {}}"#)?)?;
+        value.obj_insert("nan", f64::NAN.into())?;
+        for opts in [
+            json::FormatOptions::default(),
+            json::FormatOptions::compact(),
+            json::FormatOptions { indent_width: 2, inline_threshold: 30, ..Default::default() },
+            json::FormatOptions { non_finite: json::NonFinite::String, ascii_only: true, ..Default::default() },
+        ] {
+            let colored = value.to_string_colored(&opts, true);
+            assert_ne!(colored, value.to_string_with(&opts));
+            assert_eq!(value.to_string_with(&opts), strip_ansi(&colored));
+            assert_eq!(value.to_string_with(&opts), value.to_string_colored(&opts, false));
+        }
+
+        let value = JSONValue::from_str(r#"{"k": ["s", 1, true, null]}"#)?;
+        assert_eq!(
+            "\x1b[2m{\x1b[0m\x1b[1;34m\"k\"\x1b[0m\x1b[2m:\x1b[0m\x1b[2m[\x1b[0m\x1b[32m\"s\"\x1b[0m\x1b[2m,\x1b[0m\x1b[33m1\x1b[0m\
+             \x1b[2m,\x1b[0m\x1b[35mtrue\x1b[0m\x1b[2m,\x1b[0m\x1b[90mnull\x1b[0m\x1b[2m]\x1b[0m\x1b[2m}\x1b[0m",
+            value.to_string_colored(&json::FormatOptions::compact(), true)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn round_trip_test() -> json::Result<()> {
+        // a random value with no NaN or infinite number, nested up to `depth` levels
+        fn value(rng: &mut Rng, depth: usize) -> JSONValue {
+            const CHARS: [char; 16] = ['a', 'Z', ' ', '"', '\\', '/', '\n', '\u{0}', '\u{1f}', '\u{7f}', 'é', '\u{2028}', '日', '\u{fffd}', '😀', '\u{10ffff}'];
+            fn string(rng: &mut Rng) -> String {
+                (0..rng.below(10))
+                    .map(|_| match rng.below(3) {
+                        0 => char::from_u32(rng.below(0x11_0000) as u32).unwrap_or('x'),
+                        _ => CHARS[rng.below(CHARS.len())],
+                    })
+                    .collect()
+            }
+            match rng.below(if depth == 0 { 5 } else { 8 }) {
+                0 => [JSONValue::Null, JSONValue::Bool(true), JSONValue::Bool(false)][rng.below(3)].clone(),
+                1 => JSONValue::Number(loop {
+                    let n = f64::from_bits(rng.next());
+                    if n.is_finite() {
+                        break n;
+                    }
+                }),
+                2 => JSONValue::Number(match rng.below(4) {
+                    0 => [0.0, -0.0, f64::MAX, f64::MIN, f64::MIN_POSITIVE, 5e-324, -5e-324, f64::EPSILON][rng.below(8)],
+                    1 => (rng.next() as i64) as f64,
+                    2 => rng.below(1000) as f64 - 500.0,
+                    _ => (rng.below(100_000) as f64 - 50_000.0) / 64.0,
+                }),
+                3 | 4 => JSONValue::String(string(rng)),
+                5 | 6 => JSONValue::Array((0..rng.below(5)).map(|_| value(rng, depth - 1)).collect()),
+                _ => JSONValue::Object((0..rng.below(5)).map(|_| (string(rng), value(rng, depth - 1))).collect()),
+            }
+        }
+
+        let layouts = [
+            json::FormatOptions::default(),
+            json::FormatOptions::compact(),
+            json::FormatOptions { indent_width: 1, indent_char: '\t', newline: json::Newline::CrLf, inline_threshold: 20, ..Default::default() },
+            json::FormatOptions { ascii_only: true, escape_html: true, escape_solidus: true, ..json::FormatOptions::compact() },
+            json::FormatOptions { exponent_notation: json::ExponentNotation::Never, ..json::FormatOptions::compact() },
+            json::FormatOptions { exponent_notation: json::ExponentNotation::Always, ..json::FormatOptions::compact() },
+        ];
+        let mut rng = Rng(0xdead_beef_cafe_f00d);
+        let mut values: Vec<_> = (0..3000)
+            .map(|_| {
+                let depth = rng.below(7);
+                value(&mut rng, depth)
+            })
+            .collect();
+        // as deep as the parser goes
+        values.push((1..crate::options::DEFAULT_MAX_DEPTH).fold(JSONValue::Array(vec![]), |inner, i| match i % 2 {
+            0 => JSONValue::Array(vec![inner]),
+            _ => JSONValue::Object([("k".to_string(), inner)].into_iter().collect()),
+        }));
+
+        for value in &values {
+            let mut texts = vec![value.to_string(), format!("{value:#}"), value.to_string_compact(), value.try_to_string()?];
+            texts.push(String::from_utf8(value.to_vec()).unwrap());
+            texts.push(String::from_utf8(value.to_vec_pretty()).unwrap());
+            let mut written = vec![];
+            value.write_to(&mut written)?;
+            texts.push(String::from_utf8(written).unwrap());
+            for opts in &layouts {
+                texts.push(value.try_to_string_with(opts)?);
+            }
+            texts.push(value.to_canonical_string()?);
+
+            for text in texts {
+                match JSONValue::from_str(&text) {
+                    Ok(parsed) => assert!(parsed == *value, "{value:?}\nwas written as\n{text}\nwhich parses to\n{parsed:?}"),
+                    Err(err) => panic!("{value:?}\nwas written as\n{text}\nwhich fails to parse: {err}"),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn reformat_test() -> json::Result<()> {
+        let source = std::fs::read("tests/numbers.json").unwrap();
+        let two_spaces = json::FormatOptions { indent_width: 2, ..Default::default() };
+        assert_eq!(r#"{
+  "zeta": 1.50,
+  "alpha": [
+    1e2,
+    1E+2,
+    -0,
+    -0.0,
+    0.1e-0,
+    1.000000000000000000001,
+    12345678901234567890123456789,
+    5e-324,
+    2.5E-3
+  ],
+  "mid": {
+    "price": 19.90,
+    "qty": 3.0e0,
+    "empty": [],
+    "none": {}
+  },
+  "escaped": "café \/ \"quoted\"",
+  "zeta2": -1.0E-10
+}"#, json::reformat(&source, &two_spaces)?);
+
+        let inline = json::FormatOptions { indent_width: 2, inline_threshold: 60, ..Default::default() };
+        let out = json::reformat(&source, &inline)?;
+        assert!(out.contains(r#""mid": {"price": 19.90, "qty": 3.0e0, "empty": [], "none": {}}"#), "{out}");
+        assert!(out.contains("\"alpha\": [\n    1e2,\n"), "{out}");
+
+        // compact output is that of minify
+        let mut minified = vec![];
+        json::minify(&source, &mut minified)?;
+        assert_eq!(minified, json::reformat(&source, &json::FormatOptions::compact())?.into_bytes());
+
+        // every layout keeps the value, and where the DOM writes the same literals, the same text
+        let layouts = [json::FormatOptions::default(), two_spaces.clone(), inline, json::FormatOptions::compact()];
+        for fixture in ["tests/numbers.json", "tests/test.json", "tests/array.json", "tests/string.json", "tests/bom.json"] {
+            let source = std::fs::read(fixture).unwrap();
+            let value = JSONValue::from_slice(&source)?;
+            for opts in &layouts {
+                assert_eq!(value, JSONValue::from_str(&json::reformat(&source, opts)?)?, "{fixture}");
+                let written = value.to_string_with(opts);
+                assert_eq!(written, json::reformat(written.as_bytes(), opts)?, "{fixture}");
+            }
+        }
+
+        // repeated keys are kept, and errors are those of parsing
+        assert_eq!(r#"{"a":1,"a":2}"#, json::reformat(br#"{"a": 1, "a": 2}"#, &json::FormatOptions::compact())?);
+        for broken in [&b"[1, 2"[..], b"{\"a\" 1}", b"[1,]", b"", b"[1] 2"] {
+            let expected = JSONValue::from_slice(broken).unwrap_err().to_string();
+            assert_eq!(expected, json::reformat(broken, &two_spaces).unwrap_err().to_string());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn document_test() -> json::Result<()> {
+        let source = std::fs::read_to_string("tests/config.jsonc").unwrap();
+        let mut doc = json::Document::parse(&source)?;
+        assert_eq!(source, doc.to_string());
+        assert_eq!(JSONValue::from(3), doc.get_path("/service/replicas")?);
+        assert_eq!(JSONValue::from(8443), doc.get_path("/service/ports/1")?);
+
+        doc.set_path("/service/replicas", JSONValue::from(5))?;
+        let at = source.find("\"replicas\": 3").unwrap() + "\"replicas\": ".len();
+        assert_eq!(format!("{}5{}", &source[..at], &source[at + 1..]), doc.as_str());
+        assert_eq!(JSONValue::from(5), doc.get_path("/service/replicas")?);
+
+        // strings are escaped, containers are written compactly, and earlier edits stay
+        doc.set_path("/features/regions", JSONValue::from_str(r#"["eu", "a \"quoted\" region"]"#)?)?;
+        doc.set_path("/service/name", JSONValue::from("api-v2".to_string()))?;
+        let expected = source
+            .replace("\"replicas\": 3", "\"replicas\": 5")
+            .replace(r#"["eu", "us"]"#, r#"["eu","a \"quoted\" region"]"#)
+            .replace("\"api\"", "\"api-v2\"");
+        assert_eq!(expected, doc.as_str());
+        // comments, the trailing commas and the number spelling are all still there
+        assert!(doc.as_str().contains("/* scaled up for the launch;") && doc.as_str().contains("\"ratio\": 1.50"));
+
+        // the whole document can be replaced too
+        doc.set_path("", JSONValue::from_str("{\"a\": 1}")?)?;
+        assert_eq!("// deployment settings, maintained by hand\n{\"a\":1}\n", doc.as_str());
+
+        // lookups fail as they would on the value, and leave the document alone
+        let mut doc = json::Document::parse(&source)?;
+        for ptr in ["/service/missing", "/service/ports/2", "/service/ports/x", "/service/name/0", "service"] {
+            let err = doc.set_path(ptr, JSONValue::Null).unwrap_err();
+            let expected = json::get_path_raw(&JSONValue::from_str(&doc.get_path("")?.to_string())?.to_vec(), ptr).unwrap_err();
+            assert_eq!(expected.to_string(), err.to_string(), "{ptr}");
+        }
+        assert!(matches!(doc.set_path("/service/ratio", f64::NAN.into()), Err(JSONError::ValueError(_))));
+        assert_eq!(source, doc.as_str());
+
+        // a repeated key resolves to its last value, which is the one parsing keeps
+        let mut doc = json::Document::parse(r#"{"a": 1, "a": 2}"#)?;
+        doc.set_path("/a", JSONValue::from(3))?;
+        assert_eq!(r#"{"a": 1, "a": 3}"#, doc.as_str());
+
+        // edits that change the length of the text move everything after them, and the tree
+        // follows without the document being parsed again
+        let mut doc = json::Document::parse(r#"{"a": [1, 2], /* keep */ "b": {"c": 3,}, "d": 4,}"#)?;
+        doc.set_path("/a/0", JSONValue::from_str(r#"{"x": [10, 20]}"#)?)?;
+        doc.set_path("/a/0/x/1", JSONValue::from(2000))?;
+        doc.set_path("/b", JSONValue::from(true))?;
+        doc.set_path("/d", JSONValue::from("four".to_string()))?;
+        assert_eq!(r#"{"a": [{"x":[10,2000]}, 2], /* keep */ "b": true, "d": "four",}"#, doc.as_str());
+        assert_eq!(JSONValue::from(2), doc.get_path("/a/1")?);
+        assert_eq!(doc.get_path("")?, JSONValue::parse_with(doc.as_str().as_bytes(), &json::Options::relaxed())?);
+        assert_eq!(doc.get_path("")?.pointer("/b/c").unwrap_err().to_string(), doc.get_path("/b/c").unwrap_err().to_string());
+
+        for broken in ["{\"a\": }", "[1, 2", "", "/* only a comment */", "[1,,]", "{\"a\": 1,,}", "{\"a\": 1, 2}", "[1, }", "{\"a\": 1,", "[1,", "[1] 2", "{\"a\" 1}"] {
+            let expected = JSONValue::parse_with(broken.as_bytes(), &json::Options::default().comments(true).trailing_commas(true)).unwrap_err();
+            assert_eq!(expected.to_string(), json::Document::parse(broken).unwrap_err().to_string());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn to_string_into_test() -> json::Result<()> {
+        let values: Vec<JSONValue> = (0..10_000)
+            .map(|i| match i % 4 {
+                0 => JSONValue::from(i),
+                1 => JSONValue::from(format!("entry {i}")),
+                2 => JSONValue::from_str(&format!(r#"{{"id": {i}, "tags": ["a", "b"], "ok": true}}"#)).unwrap(),
+                _ => JSONValue::from_str(&format!("[{i}, null, -{i}.5]")).unwrap(),
+            })
+            .collect();
+
+        for opts in [json::FormatOptions::compact(), json::FormatOptions::default()] {
+            let mut buf = String::new();
+            for value in &values {
+                value.to_string_into(&mut buf, &opts);
+            }
+            let expected: String = values.iter().map(|value| value.to_string_with(&opts)).collect();
+            assert_eq!(expected, buf);
+            let parsed: Vec<JSONValue> = json::Deserializer::from_slice(buf.as_bytes()).collect::<json::Result<_>>()?;
+            assert_eq!(values, parsed);
+
+            // once the buffer is big enough, serializing into it again doesn't allocate
+            buf.clear();
+            let ((), allocations) = count_allocations(|| {
+                for value in &values {
+                    value.to_string_into(&mut buf, &opts);
+                }
+            });
+            assert_eq!(0, allocations);
+            assert_eq!(expected, buf);
+        }
+
+        // appending keeps what the buffer already holds, and Display goes through the same path
+        let value = JSONValue::from_str(r#"{"a": [1, 2]}"#)?;
+        let mut buf = String::from("log: ");
+        value.to_string_into(&mut buf, &json::FormatOptions::compact());
+        assert_eq!(format!("log: {:#}", value), buf);
+        let mut out = String::new();
+        value.write_fmt_into(&mut out, &json::FormatOptions::default()).unwrap();
+        assert_eq!(value.to_string(), out);
+
+        Ok(())
+    }
+
+    #[test]
+    fn preview_test() -> json::Result<()> {
+        // small values come out whole, and still parse
+        let value = JSONValue::from_str(r#"{"a": [1, 2.5, "x\"y"], "b": {"c": null, "d": true}, "e": []}"#)?;
+        let preview = value.preview(usize::MAX, usize::MAX);
+        assert_eq!(r#"{"a": [1, 2.5, "x\"y"], "b": {"c": null, "d": true}, "e": []}"#, preview);
+        assert_eq!(value, JSONValue::from_str(&preview)?);
+
+        // a huge string is cut short, between characters, with its full size after it
+        let huge = "é".repeat(600) + &"a".repeat(1_000_000);
+        let preview = JSONValue::from(huge.clone()).preview(20, 8);
+        assert_eq!(format!("\"{}…\"(1.0MB)", "é".repeat(10)), preview);
+        for max_len in 0..40 {
+            let preview = JSONValue::from(huge.clone()).preview(max_len, 8);
+            assert!(preview.starts_with('"') && preview.ends_with("…\"(1.0MB)"), "{preview}");
+            assert!(preview.chars().count() <= max_len.max(10), "{preview}");
+        }
+        assert_eq!(r#""abc…"(1.2kB)"#, JSONValue::from("abc".repeat(400)).preview(13, 8));
+        assert_eq!(r#""abcd""#, JSONValue::from("abcd".to_string()).preview(6, 8));
+        // escapes aren't split either
+        assert_eq!(r#""\n\n…"(10B)"#, JSONValue::from("\n".repeat(10)).preview(13, 8));
+
+        // a huge flat array keeps as many items as fit, and counts the rest
+        let array = JSONValue::Array((1..=100).map(JSONValue::from).collect());
+        assert_eq!("[1, 2, 3, …97 more]", array.preview(19, 8));
+        assert_eq!("[…100 more]", array.preview(5, 8));
+        let preview = array.preview(200, 8);
+        assert!(preview.ends_with(" more]") && (190..=200).contains(&preview.chars().count()), "{preview}");
+        let preview = JSONValue::Array(vec![JSONValue::from("x".repeat(5000)); 5000]).preview(80, 8);
+        assert!(preview.ends_with(" more]") && preview.chars().count() <= 80, "{preview}");
+
+        // deep nesting stops at the maximum depth
+        let mut deep = JSONValue::from(1);
+        for i in 0..1000 {
+            deep = if i % 2 == 0 { JSONValue::Array(vec![deep]) } else { JSONValue::from_str(r#"{"k": 0}"#).map(|mut v| { v["k"] = deep; v })? };
+        }
+        assert_eq!(r#"{"k": [{"k": […]}]}"#, deep.preview(100, 3));
+        assert_eq!("{…}", deep.preview(100, 0));
+        // and so does a deep value with no limit on depth, once it runs out of room
+        let preview = deep.preview(50, usize::MAX);
+        assert!(preview.chars().count() <= 50 && preview.contains("…1 more"), "{preview}");
+
+        // long keys are cut too, and non-finite numbers don't get in the way
+        let mut object = json::Map::new();
+        object.insert("k".repeat(3000), JSONValue::from(f64::NAN));
+        object.insert("short".to_string(), JSONValue::from(1));
+        let preview = JSONValue::Object(object).preview(40, 8);
+        assert!(preview.starts_with("{\"kkk") && preview.contains("…\"(3.0kB): null") && preview.chars().count() <= 40, "{preview}");
+
+        Ok(())
+    }
+
+    #[test]
+    fn rename_keys_test() -> json::Result<()> {
+        let original = JSONValue::from_file("tests/snake_case.json")?;
+        let mut value = original.clone();
+        value.rename_keys_recursive(json::KeyCase::CamelCase)?;
+        let expected = JSONValue::from_str(r#"{
+            "userId": 42, "displayName": "Ada", "_links": {"selfUrl": "/users/42"},
+            "recentOrders": [
+                {"orderId": 1, "lineItems": [{"skuCode": "A-1", "unitPrice": 9.5}], "isGift": false},
+                {"orderId": 2, "lineItems": [], "shippingAddress": {"streetName": "Main", "zipCode": "12345"}}
+            ],
+            "preferences": {"emailOptIn": true, "theme": "dark", "uiScale": null}
+        }"#)?;
+        assert_eq!(expected, value);
+        // the order of the entries is kept too
+        assert_eq!(expected.to_string(), value.to_string());
+
+        value.rename_keys_recursive(json::KeyCase::SnakeCase)?;
+        assert_eq!(original.to_string(), value.to_string());
+        value.rename_keys_recursive(json::KeyCase::KebabCase)?;
+        assert_eq!(JSONValue::from("12345".to_string()), value["recent-orders"][1]["shipping-address"]["zip-code"]);
+
+        for (key, camel, snake, kebab) in [
+            ("userID", "userId", "user_id", "user-id"),
+            ("HTTPServer", "httpServer", "http_server", "http-server"),
+            ("parseJSONValue", "parseJsonValue", "parse_json_value", "parse-json-value"),
+            ("already_snake", "alreadySnake", "already_snake", "already-snake"),
+            ("Some Title", "someTitle", "some_title", "some-title"),
+            ("__typename", "__typename", "__typename", "__typename"),
+            ("version2Name", "version2Name", "version2_name", "version2-name"),
+            ("ID", "id", "id", "id"),
+            ("", "", "", ""),
+        ] {
+            assert_eq!(camel, json::KeyCase::CamelCase.convert(key), "{key}");
+            assert_eq!(snake, json::KeyCase::SnakeCase.convert(key), "{key}");
+            assert_eq!(kebab, json::KeyCase::KebabCase.convert(key), "{key}");
+        }
+
+        // keys that collide are an error naming both, and nothing is renamed
+        let mut value = JSONValue::from_str(r#"{"top_level": [{"userId": 1, "user_id": 2}]}"#)?;
+        let before = value.clone();
+        let err = value.rename_keys_recursive(json::KeyCase::SnakeCase).unwrap_err();
+        assert!(matches!(&err, JSONError::KeyError(message) if message == "keys userId and user_id both become user_id"), "{err}");
+        assert_eq!(before, value);
+        // values are never renamed, only keys
+        let mut value = JSONValue::from_str(r#"["some_value", {"a_b": "c_d"}]"#)?;
+        value.rename_keys_recursive(json::KeyCase::CamelCase)?;
+        assert_eq!(JSONValue::from_str(r#"["some_value", {"aB": "c_d"}]"#)?, value);
+
+        Ok(())
+    }
+
+    #[test]
+    fn json_type_test() -> json::Result<()> {
+        use json::JSONType;
+
+        let cases = [
+            (JSONValue::from_str(r#"{"a": 1}"#)?, JSONType::Object, "object"),
+            (JSONValue::Array(vec![]), JSONType::Array, "array"),
+            (JSONValue::from("text".to_string()), JSONType::String, "string"),
+            (JSONValue::from(1.5), JSONType::Number, "number"),
+            (JSONValue::Bool(false), JSONType::Bool, "boolean"),
+            (JSONValue::Null, JSONType::Null, "null"),
+        ];
+        for (value, json_type, name) in cases {
+            assert_eq!(json_type, value.json_type());
+            assert_eq!(name, json_type.as_str());
+            assert_eq!(name, json_type.to_string());
+            let predicates = [
+                (JSONType::Object, value.is_object()),
+                (JSONType::Array, value.is_array()),
+                (JSONType::String, value.is_string()),
+                (JSONType::Number, value.is_number()),
+                (JSONType::Bool, value.is_bool()),
+                (JSONType::Null, value.is_null()),
+            ];
+            for (other, holds) in predicates {
+                assert_eq!(other == json_type, holds, "{name} is {other}");
+            }
+        }
+
+        // error messages use the same names
+        let err = JSONValue::Bool(true).arr_push(JSONValue::Null).unwrap_err();
+        assert_eq!(format!("expected array, found {}", JSONType::Bool), err.message());
+
+        Ok(())
+    }
+
+    #[test]
+    fn as_accessors_test() -> json::Result<()> {
+        let values = [
+            JSONValue::from_str(r#"{"a": 1}"#)?,
+            JSONValue::from_str("[1, 2]")?,
+            JSONValue::from("text".to_string()),
+            JSONValue::from(1.5),
+            JSONValue::Bool(true),
+            JSONValue::Null,
+        ];
+        for value in &values {
+            assert_eq!(value.is_object(), value.as_object().is_some());
+            assert_eq!(value.is_array(), value.as_array().is_some());
+            assert_eq!(value.is_string(), value.as_str().is_some());
+            assert_eq!(value.is_number(), value.as_f64().is_some());
+            assert_eq!(value.is_bool(), value.as_bool().is_some());
+            assert_eq!(value.is_null(), value.as_null().is_some());
+        }
+
+        assert_eq!(Some(&JSONValue::from(1)), values[0].as_object().and_then(|map| map.get("a")));
+        assert_eq!(Some(2), values[1].as_array().map(Vec::len));
+        assert_eq!(Some("text"), values[2].as_str());
+        assert_eq!(Some(1.5), values[3].as_f64());
+        assert_eq!(Some(true), values[4].as_bool());
+        assert_eq!(Some(()), values[5].as_null());
+        assert_eq!(None, JSONValue::from("1.5".to_string()).as_f64());
+        assert_eq!(None, JSONValue::from(0).as_bool());
+        assert_eq!(None, JSONValue::from("null".to_string()).as_null());
+
+        Ok(())
+    }
+
+    #[test]
+    fn as_mut_accessors_test() -> json::Result<()> {
+        let mut value = JSONValue::from_str(r#"{"tags": ["a", "b"], "name": "json", "drop": null, "keep": 1}"#)?;
+
+        value.as_object_mut().unwrap().retain(|key, value| key != "drop" && !value.is_number());
+        assert_eq!(vec!["tags", "name"], value.as_object().unwrap().keys().collect::<Vec<_>>());
+        assert!(value.get("drop").is_err() && value.get("keep").is_err());
+        // lookups still find the entries that moved
+        assert_eq!(JSONValue::from("json".to_string()), *value.get("name")?);
+
+        let tags = value.get_mut("tags")?.as_array_mut().unwrap();
+        tags.push(JSONValue::from("c".to_string()));
+        tags.retain(|tag| tag.as_str() != Some("a"));
+        assert_eq!(JSONValue::from_str(r#"["b", "c"]"#)?, *value.get("tags")?);
+
+        value.get_mut("name")?.as_string_mut().unwrap().push_str("-rs");
+        assert_eq!(Some("json-rs"), value.get("name")?.as_str());
+
+        assert!(value.get_mut("tags")?.as_object_mut().is_none());
+        assert!(value.as_array_mut().is_none());
+        assert!(value.as_string_mut().is_none());
+        assert!(JSONValue::Null.as_object_mut().is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn into_conversions_test() -> json::Result<()> {
+        let source = format!("[{}]", vec!["{\"id\": 1, \"name\": \"x\"}"; 100_000].join(","));
+        let value = JSONValue::from_str(&source)?;
+
+        // the items move out as they are, so the only allocation is for the test's own clone
+        let (items, allocations) = count_allocations(|| value.clone().into_array());
+        let items = items?;
+        assert_eq!(100_000, items.len());
+        let ((), moved_allocations) = count_allocations(|| {
+            let items = Vec::<JSONValue>::try_from(value).unwrap();
+            assert_eq!(100_000, items.len());
+        });
+        assert!(moved_allocations == 0 && allocations > 100_000, "{moved_allocations} {allocations}");
+
+        let object = items.into_iter().next().unwrap().into_object()?;
+        assert_eq!(vec!["id", "name"], object.keys().collect::<Vec<_>>());
+        let name = String::try_from(object["name"].clone())?;
+        assert_eq!("x", name);
+        assert_eq!("x", JSONValue::from("x".to_string()).into_string()?);
+        let map: json::Map = JSONValue::from_str(r#"{"a": [1]}"#)?.try_into()?;
+        assert_eq!(JSONValue::from_str("[1]")?, map["a"]);
+
+        // a mismatch names the type the value actually is
+        let cases: [(JSONValue, &str); 6] = [
+            (JSONValue::from_str(r#"{"a": 1}"#)?, "object"),
+            (JSONValue::from_str("[1]")?, "array"),
+            (JSONValue::from("s".to_string()), "string"),
+            (JSONValue::from(1), "number"),
+            (JSONValue::Bool(true), "boolean"),
+            (JSONValue::Null, "null"),
+        ];
+        for (value, name) in cases {
+            let results = [
+                ("object", value.clone().into_object().err()),
+                ("array", value.clone().into_array().err()),
+                ("string", value.clone().into_string().err()),
+            ];
+            for (expected, err) in results {
+                match err {
+                    None => assert_eq!(expected, name),
+                    Some(err) => {
+                        assert_ne!(expected, name);
+                        assert!(matches!(&err, JSONError::ValueError(message) if *message == format!("expected {expected}, found {name:?}")), "{err}");
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn len_test() -> json::Result<()> {
+        let cases = [
+            (r#"{"a": 1, "b": [1, 2, 3]}"#, Some(2)),
+            ("{}", Some(0)),
+            ("[1, [2, 3], {}]", Some(3)),
+            ("[]", Some(0)),
+            // characters, not bytes
+            (r#""héllo""#, Some(5)),
+            (r#""""#, Some(0)),
+            ("0", None),
+            ("false", None),
+            ("null", None),
+        ];
+        for (source, len) in cases {
+            let value = JSONValue::from_str(source)?;
+            match len {
+                Some(len) => {
+                    assert_eq!(len, value.len()?, "{source}");
+                    assert_eq!(len == 0, value.is_empty(), "{source}");
+                }
+                None => {
+                    let err = value.len().unwrap_err();
+                    let expected = format!("expected object, array or string, found {:?}", value.json_type().as_str());
+                    assert!(matches!(&err, JSONError::ValueError(message) if *message == expected), "{err}");
+                    assert!(!value.is_empty(), "{source}");
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn object_iter_test() -> json::Result<()> {
+        let mut value = JSONValue::from_file("tests/test.json")?;
+        let mut keys: Vec<&str> = value.keys()?.collect();
+        keys.sort();
+        assert_eq!(vec!["baz", "foo", "obj", "qux", "tmp"], keys);
+        assert_eq!(5, value.values()?.count());
+        let entries: Vec<(&str, &JSONValue)> = value.entries()?.collect();
+        assert_eq!(("foo", &JSONValue::from("bar".to_string())), entries[0]);
+        assert!(entries.iter().all(|&(key, entry)| value.get(key).unwrap() == entry));
+
+        let obj = value.get_mut("obj")?;
+        for n in obj.values_mut()? {
+            if let JSONValue::Number(n) = n {
+                *n *= 2.0;
+            }
+        }
+        assert_eq!(JSONValue::from_str(r#"{"a": 1100, "b": 4.0, "c": -4.8886E-15}"#)?, *obj);
+        for (key, entry) in obj.entries_mut()? {
+            *entry = JSONValue::from(key.to_uppercase());
+        }
+        assert_eq!(JSONValue::from_str(r#"{"a": "A", "b": "B", "c": "C"}"#)?, value["obj"]);
+
+        let mut array = value["baz"].clone();
+        let expected = r#"expected object, found "array""#;
+        assert!(matches!(array.keys(), Err(JSONError::ValueError(message)) if message == expected));
+        assert!(matches!(array.values(), Err(JSONError::ValueError(message)) if message == expected));
+        assert!(matches!(array.values_mut(), Err(JSONError::ValueError(message)) if message == expected));
+        assert!(matches!(array.entries(), Err(JSONError::ValueError(message)) if message == expected));
+        assert!(matches!(array.entries_mut(), Err(JSONError::ValueError(message)) if message == expected));
+
+        Ok(())
+    }
+
+    #[test]
+    fn array_iter_test() -> json::Result<()> {
+        let mut value = JSONValue::from_str("[1, 2.5, 3, -4]")?;
+        let sum: f64 = value.elements()?.map(|n| n.as_f64().unwrap()).sum();
+        assert_eq!(2.5, sum);
+        assert_eq!(2, value.elements()?.filter(|n| n.as_f64().unwrap() > 2.0).count());
+
+        for (i, n) in value.elements_mut()?.enumerate() {
+            *n = JSONValue::from(n.as_f64().unwrap() * 10.0 + i as f64);
+        }
+        assert_eq!(JSONValue::from_str("[10, 26, 32, -37]")?, value);
+        assert_eq!(0, JSONValue::Array(vec![]).elements()?.count());
+
+        let mut object = JSONValue::from_str(r#"{"a": [1]}"#)?;
+        assert!(matches!(object.elements(), Err(JSONError::ValueError(message)) if message == "expected array, found object"));
+        assert!(matches!(object.elements_mut(), Err(JSONError::ValueError(message)) if message == "expected array, found object"));
+        assert!(matches!(JSONValue::Null.elements(), Err(JSONError::ValueError(message)) if message == "expected array, found null"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn from_iterator_test() -> json::Result<()> {
+        let array: JSONValue = (0..5).map(JSONValue::from).collect();
+        assert_eq!(JSONValue::from_str("[0, 1, 2, 3, 4]")?, array);
+        // anything that converts into a value can be collected
+        let array: JSONValue = [Some(true), None, Some(false)].into_iter().collect();
+        assert_eq!(JSONValue::Array(vec![JSONValue::Bool(true), JSONValue::Null, JSONValue::Bool(false)]), array);
+        let words: JSONValue = "a b c".split(' ').map(str::to_string).collect();
+        assert_eq!(JSONValue::from_str(r#"["a", "b", "c"]"#)?, words);
+        assert_eq!(JSONValue::Array(vec![]), std::iter::empty::<JSONValue>().collect::<JSONValue>());
+
+        let object: JSONValue = vec![("a", 1), ("b", 2)].into_iter().collect();
+        assert_eq!(JSONValue::from_str(r#"{"a": 1, "b": 2}"#)?, object);
+        let object: JSONValue = JSONValue::from_file("tests/test.json")?
+            .entries()?
+            .filter(|(_, value)| value.is_string())
+            .map(|(key, value)| (key.to_string(), value.clone()))
+            .collect();
+        assert_eq!(JSONValue::from_str(r#"{"foo": "bar", "tmp": "lmao"}"#)?, object);
+        let nested: JSONValue = (1..=2).map(|i| (format!("k{i}"), (0..i).collect::<JSONValue>())).collect();
+        assert_eq!(JSONValue::from_str(r#"{"k1": [0], "k2": [0, 1]}"#)?, nested);
+
+        // a repeated key keeps its first place and its last value
+        let object: JSONValue = [("x", 1), ("y", 2), ("x", 3)].into_iter().collect();
+        assert_eq!(r#"{"x":3,"y":2}"#, object.to_string_compact());
+
+        Ok(())
+    }
+
+    #[test]
+    fn extend_test() -> json::Result<()> {
+        let mut array = JSONValue::from_str("[0]")?;
+        array.extend([JSONValue::from(1.5), JSONValue::from("two".to_string()), JSONValue::Bool(true), JSONValue::Null]);
+        array.arr_extend((3..5).map(JSONValue::from))?;
+        assert_eq!(JSONValue::from_str(r#"[0, 1.5, "two", true, null, 3, 4]"#)?, array);
+
+        // a key already there keeps its place and takes the new value, as does one repeated
+        let mut object = JSONValue::from_str(r#"{"a": 1, "b": 2}"#)?;
+        object.extend([("b".to_string(), JSONValue::from(20)), ("c".to_string(), JSONValue::from(3))]);
+        object.obj_extend([("a".to_string(), JSONValue::from(10)), ("d".to_string(), JSONValue::Null), ("a".to_string(), JSONValue::from(100))])?;
+        assert_eq!(r#"{"a":100,"b":20,"c":3,"d":null}"#, object.to_string_compact());
+
+        // the checked versions leave the wrong kind of value alone
+        let before = object.clone();
+        let err = object.arr_extend([JSONValue::Null]).unwrap_err();
+        assert!(matches!(&err, JSONError::ValueError(message) if message == "expected array, found object"), "{err}");
+        assert_eq!(before, object);
+        let err = array.obj_extend([("k".to_string(), JSONValue::Null)]).unwrap_err();
+        assert!(matches!(&err, JSONError::ValueError(message) if message == r#"expected object, found "array""#), "{err}");
+
+        // and the trait impls panic, as indexing does
+        assert!(std::panic::catch_unwind(|| JSONValue::Null.extend([JSONValue::Null])).is_err());
+        assert!(std::panic::catch_unwind(|| JSONValue::Array(vec![]).extend([("k".to_string(), JSONValue::Null)])).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn obj_set_test() -> json::Result<()> {
+        let mut object = JSONValue::from_str(r#"{"a": 1, "b": 2}"#)?;
+        assert_eq!(None, object.obj_set("c", JSONValue::from(3))?);
+        assert_eq!(Some(JSONValue::from(1)), object.obj_set("a".to_string(), JSONValue::from(10))?);
+        assert_eq!(Some(JSONValue::from(10)), object.obj_set("a", JSONValue::Null)?);
+        // an overwritten key keeps its place
+        assert_eq!(r#"{"a":null,"b":2,"c":3}"#, object.to_string_compact());
+
+        let err = object.obj_insert("b", JSONValue::Null).unwrap_err();
+        assert!(matches!(&err, JSONError::KeyError(message) if message == "key b already in object, use obj_set to overwrite it"), "{err}");
+
+        let err = JSONValue::from_str("[1]")?.obj_set("a", JSONValue::Null).unwrap_err();
+        assert!(matches!(&err, JSONError::ValueError(message) if message == r#"expected object, found "array""#), "{err}");
+
+        Ok(())
+    }
+
+    #[test]
+    fn obj_entry_test() -> json::Result<()> {
+        let mut counts = JSONValue::from_str("{}")?;
+        for word in "the cat and the hat and the bat".split(' ') {
+            counts.obj_entry(word)?.and_modify(|n| *n = JSONValue::from(n.as_f64().unwrap() + 1.0)).or_insert(JSONValue::from(1));
+        }
+        assert_eq!(r#"{"the":3,"cat":1,"and":2,"hat":1,"bat":1}"#, counts.to_string_compact());
+
+        // nested structure built only where it is missing
+        let mut tree = JSONValue::from_str(r#"{"a": {"x": [0]}}"#)?;
+        let mut built = 0;
+        for (outer, inner) in [("a", "x"), ("a", "y"), ("b", "x"), ("b", "x")] {
+            let node = tree.obj_entry(outer)?.or_insert_with(|| {
+                built += 1;
+                JSONValue::from_str("{}").unwrap()
+            });
+            node.obj_entry(inner)?.or_insert_with(|| JSONValue::Array(vec![])).arr_push(JSONValue::from(1))?;
+        }
+        assert_eq!(1, built);
+        assert_eq!(JSONValue::from_str(r#"{"a": {"x": [0, 1], "y": [1]}, "b": {"x": [1, 1]}}"#)?, tree);
+        // lookups find the new keys
+        assert_eq!(JSONValue::from_str("[1]")?, tree["a"]["y"]);
+
+        // an entry that is never filled in adds nothing
+        assert_eq!("c", tree.obj_entry("c")?.and_modify(|_| unreachable!()).key());
+        assert!(tree.get("c").is_err());
+
+        let err = JSONValue::Null.obj_entry("a").map(|_| ()).unwrap_err();
+        assert!(matches!(&err, JSONError::ValueError(message) if message == r#"expected object, found "null""#), "{err}");
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_opt_test() -> json::Result<()> {
+        let mut config = JSONValue::from_str(r#"{"retries": 3, "name": null}"#)?;
+        assert_eq!(Some(&JSONValue::from(3)), config.get_opt("retries"));
+        // a key that is there with a null value is still there
+        assert_eq!(Some(&JSONValue::Null), config.get_opt("name"));
+        assert_eq!(None, config.get_opt("timeout"));
+        let timeout: f64 = config.get_opt("timeout").and_then(JSONValue::as_f64).unwrap_or(30.0);
+        assert_eq!(30.0, timeout);
+
+        *config.get_opt_mut("retries").unwrap() = JSONValue::from(5);
+        assert_eq!(JSONValue::from(5), config["retries"]);
+        assert!(config.get_opt_mut("timeout").is_none());
+
+        assert_eq!(Some(&JSONValue::from(5)), config.try_get("retries")?);
+        assert_eq!(None, config.try_get("timeout")?);
+
+        // anything but an object has no keys, which only try_get reports as a mistake
+        let mut array = JSONValue::from_str(r#"[{"retries": 3}]"#)?;
+        assert_eq!(None, array.get_opt("retries"));
+        assert_eq!(None, array.get_opt_mut("retries"));
+        assert_eq!(None, JSONValue::Null.get_opt("retries"));
+        let err = array.try_get("retries").unwrap_err();
+        assert!(matches!(&err, JSONError::ValueError(message) if message == r#"expected object, found "array""#), "{err}");
+
+        Ok(())
+    }
+
+    macro_rules! get_as_test {
+        ($var:expr, $key:expr, $($type_name:ty)+) => {
+            $(let _var: $type_name = $var.get_as($key)?;)+
+        };
+    }
+
+    #[test]
+    fn get_as_test() -> json::Result<()> {
+        let value = JSONValue::from_file("tests/test.json")?;
+        let foo: String = value.get_as("foo")?;
+        assert_eq!("bar", foo);
+        let a: u16 = value["obj"].get_as("a")?;
+        assert_eq!(550, a);
+        get_as_test!(value["obj"], "b", i8 i16 i32 i64 i128 isize u8 u16 u32 u64 u128 usize f32 f64);
+        let first: bool = value["baz"].index_as(0)?;
+        assert!(first);
+        let bozo: String = value["baz"].index_as(2)?;
+        assert_eq!("bozo", bozo);
+        let the_answer: u8 = value["qux"]["qux_obj"]["nest_arr"][3].get_as("the_answer")?;
+        assert_eq!(42, the_answer);
+
+        // failures name the key or index, and what was expected
+        let message = |result: json::Result<()>| match result.unwrap_err() {
+            JSONError::ValueError(message) | JSONError::KeyError(message) => message,
+            other => panic!("{other}"),
+        };
+        assert_eq!(r#"key missing not found"#, message(value.get_as::<u32>("missing").map(drop)));
+        assert_eq!(r#"expected number for key "foo", found "string""#, message(value.get_as::<u32>("foo").map(drop)));
+        assert_eq!(r#"expected boolean for key "obj", found "object""#, message(value.get_as::<bool>("obj").map(drop)));
+        assert_eq!(r#"expected string for key "a", found "number""#, message(value["obj"].get_as::<String>("a").map(drop)));
+        assert_eq!(r#"expected string for index 0, found "boolean""#, message(value["baz"].index_as::<String>(0).map(drop)));
+        assert_eq!(r#"expected number for index 2, found "string""#, message(value["baz"].index_as::<i64>(2).map(drop)));
+        assert_eq!("index 3 out of bounds for length 3", message(value["baz"].index_as::<i64>(3).map(drop)));
+        assert_eq!(r#"expected object, found "array""#, message(value["baz"].get_as::<i64>("a").map(drop)));
+        assert!(matches!(value.get_as::<u32>("missing"), Err(JSONError::KeyError(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn pointer_test() -> json::Result<()> {
+        let mut value = JSONValue::from_str(r#"{
+            "a": {"b": [10, 20, {"c": "deep"}]},
+            "a/b": 1, "m~n": 2, "": 3, " ": 4, "~1": 5,
+            "nums": [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10]
+        }"#)?;
+        assert_eq!(&value, value.pointer("")?);
+        assert_eq!(JSONValue::from(20), *value.pointer("/a/b/1")?);
+        assert_eq!(JSONValue::from("deep".to_string()), *value.pointer("/a/b/2/c")?);
+        // escaped keys, and the empty key, per RFC 6901
+        assert_eq!(JSONValue::from(1), *value.pointer("/a~1b")?);
+        assert_eq!(JSONValue::from(2), *value.pointer("/m~0n")?);
+        assert_eq!(JSONValue::from(3), *value.pointer("/")?);
+        assert_eq!(JSONValue::from(4), *value.pointer("/ ")?);
+        assert_eq!(JSONValue::from(5), *value.pointer("/~01")?);
+        assert_eq!(JSONValue::from(10), *value.pointer("/nums/10")?);
+        assert_eq!(JSONValue::from(0), *value.pointer("/nums/0")?);
+
+        *value.pointer_mut("/a/b/2/c")? = JSONValue::from(true);
+        assert_eq!(JSONValue::Bool(true), value["a"]["b"][2]["c"]);
+        *value.pointer_mut("")? = JSONValue::from_str("[[1]]")?;
+        assert_eq!(JSONValue::from(1), *value.pointer("/0/0")?);
+
+        // each error says which part of the pointer failed
+        let value = JSONValue::from_str(r#"{"a": {"b": [10, 20]}, "s": "text"}"#)?;
+        let cases = [
+            ("/a/x", r#"JSON Key Error: key x not found at "/a/x""#),
+            ("/a/b/2", r#"JSON Index Error: index 2 out of bounds for length 2 at "/a/b/2""#),
+            ("/a/b/01", r#"JSON Value Error: invalid array index "01" at "/a/b/01""#),
+            ("/a/b/-", r#"JSON Value Error: invalid array index "-" at "/a/b/-""#),
+            ("/a/b/+1", r#"JSON Value Error: invalid array index "+1" at "/a/b/+1""#),
+            ("/a/b/", r#"JSON Value Error: invalid array index "" at "/a/b/""#),
+            ("/s/0", r#"JSON Value Error: expected object or array, found "string" at "/s/0""#),
+            ("/a/b/0/x", r#"JSON Value Error: expected object or array, found "number" at "/a/b/0/x""#),
+            ("a", r#"JSON Value Error: json pointer "a" must be empty or start with '/'"#),
+        ];
+        for (ptr, message) in cases {
+            assert_eq!(message, value.pointer(ptr).unwrap_err().to_string(), "{ptr}");
+            assert_eq!(message, value.clone().pointer_mut(ptr).unwrap_err().to_string(), "{ptr}");
+            // looking it up without parsing gives the same error
+            assert_eq!(message, json::get_path_raw(value.to_string().as_bytes(), ptr).unwrap_err().to_string(), "{ptr}");
+        }
+        assert!(matches!(value.pointer("/a/x"), Err(JSONError::KeyError(_))));
+        assert!(matches!(value.pointer("/a/b/9"), Err(JSONError::IndexError(_))));
+        assert!(matches!(value.pointer("/s/x"), Err(JSONError::ValueError(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn set_pointer_test() -> json::Result<()> {
+        let mut value = JSONValue::Null;
+        value.set_pointer("/name", JSONValue::from("fleet".to_string()))?;
+        value.set_pointer("/servers/0/port", JSONValue::from(8080))?;
+        value.set_pointer("/servers/0/host", JSONValue::from("a.example".to_string()))?;
+        value.set_pointer("/servers/-/port", JSONValue::from(8081))?;
+        value.set_pointer("/servers/-", JSONValue::from_str(r#"{"port": 8082}"#)?)?;
+        value.set_pointer("/servers/1/tags/-", JSONValue::from("canary".to_string()))?;
+        value.set_pointer("/servers/1/tags/-", JSONValue::from("eu".to_string()))?;
+        // an index past the end pads with nulls
+        value.set_pointer("/matrix/2/1", JSONValue::from(1))?;
+        // keys that look like indices are still keys of an object that is already there
+        value.set_pointer("/limits/cpu", JSONValue::from(2))?;
+        value.set_pointer("/limits/0", JSONValue::from(0))?;
+        value.set_pointer("/limits/-", JSONValue::Bool(true))?;
+        value.set_pointer("/a~1b/~0", JSONValue::Null)?;
+        value.set_pointer("/servers/0/port", JSONValue::from(9090))?;
+        assert_eq!(JSONValue::from_str(r#"{
+            "name": "fleet",
+            "servers": [
+                {"port": 9090, "host": "a.example"},
+                {"port": 8081, "tags": ["canary", "eu"]},
+                {"port": 8082}
+            ],
+            "matrix": [null, null, [null, 1]],
+            "limits": {"cpu": 2, "0": 0, "-": true},
+            "a/b": {"~": null}
+        }"#)?, value);
+        assert_eq!(r#"{"port":9090,"host":"a.example"}"#, value.pointer("/servers/0")?.to_string_compact());
+
+        // padding is bounded, so a huge index from untrusted input is an error instead of an abort
+        let mut padded = JSONValue::from_str(r#"{"a": [1], "o": {}}"#)?;
+        let limit = json::MAX_POINTER_PADDING;
+        padded.set_pointer(&format!("/a/{}", 1 + limit), JSONValue::Bool(true))?;
+        assert_eq!(2 + limit, padded["a"].len()?);
+        padded.set_pointer(&format!("/new/{}", limit), JSONValue::Bool(true))?;
+        assert_eq!(1 + limit, padded["new"].len()?);
+        // an object's keys are not indices, however large
+        padded.set_pointer("/o/18446744073709551614", JSONValue::Null)?;
+        let before = padded.clone();
+        for (ptr, at) in [
+            ("/a/18446744073709551614".to_string(), "/a/18446744073709551614".to_string()),
+            (format!("/a/{}", 3 + 2 * limit), format!("/a/{}", 3 + 2 * limit)),
+            (format!("/fresh/x/0/{}", 1 + limit), format!("/fresh/x/0/{}", 1 + limit)),
+            (format!("/o/k/{}/y", 1 + limit), format!("/o/k/{}", 1 + limit)),
+        ] {
+            match padded.set_pointer(&ptr, JSONValue::Null) {
+                Err(JSONError::IndexError(message)) => assert!(message.ends_with(&format!("at {:?}", at)), "{message}"),
+                other => panic!("unexpected {:?}", other),
+            }
+        }
+        assert_eq!(before, padded);
+
+        // conflicts with a scalar name the part of the pointer that failed, and change nothing
+        let before = value.clone();
+        let cases = [
+            ("/name/first", r#"expected object or array, found "string" at "/name/first""#),
+            ("/servers/0/port/x", r#"expected object or array, found "number" at "/servers/0/port/x""#),
+            ("/servers/x", r#"invalid array index "x" at "/servers/x""#),
+            ("/servers/01", r#"invalid array index "01" at "/servers/01""#),
+            ("servers", r#"json pointer "servers" must be empty or start with '/'"#),
+        ];
+        for (ptr, message) in cases {
+            let err = value.set_pointer(ptr, JSONValue::Null).unwrap_err();
+            assert!(matches!(&err, JSONError::ValueError(m) if m == message), "{ptr}: {err}");
+        }
+        assert_eq!(before, value);
+
+        // the empty pointer replaces the whole value
+        value.set_pointer("", JSONValue::from(1))?;
+        assert_eq!(JSONValue::from(1), value);
+        let mut array = JSONValue::Null;
+        array.set_pointer("/-", JSONValue::from(1))?;
+        array.set_pointer("/-/-/k", JSONValue::from(2))?;
+        assert_eq!(JSONValue::from_str(r#"[1, [{"k": 2}]]"#)?, array);
+
+        Ok(())
+    }
+
+    #[test]
+    fn remove_pointer_test() -> json::Result<()> {
+        let mut value = JSONValue::from_str(r#"{"a": {"b": {"c": 1, "d": 2}, "a/b": 3}, "list": [0, 1, 2, 3]}"#)?;
+        assert_eq!(JSONValue::from(1), value.remove_pointer("/a/b/c")?);
+        assert_eq!(JSONValue::from(3), value.remove_pointer("/a/a~1b")?);
+        // the items after the removed one move up
+        assert_eq!(JSONValue::from(1), value.remove_pointer("/list/1")?);
+        assert_eq!(JSONValue::from(3), value.remove_pointer("/list/2")?);
+        assert_eq!(JSONValue::from_str(r#"{"a": {"b": {"d": 2}}, "list": [0, 2]}"#)?, value);
+        assert_eq!(JSONValue::from_str(r#"{"d": 2}"#)?, value.remove_pointer("/a/b")?);
+        assert_eq!(r#"{"a":{},"list":[0,2]}"#, value.to_string_compact());
+
+        // errors name the part of the pointer that failed, and nothing is removed
+        let before = value.clone();
+        let cases = [
+            ("/a/missing", r#"JSON Key Error: key missing not found at "/a/missing""#),
+            ("/nothing/x", r#"JSON Key Error: key nothing not found at "/nothing""#),
+            ("/list/2", r#"JSON Index Error: index 2 out of bounds for length 2 at "/list/2""#),
+            ("/list/-", r#"JSON Value Error: invalid array index "-" at "/list/-""#),
+            ("/list/0/x", r#"JSON Value Error: expected object or array, found "number" at "/list/0/x""#),
+            ("list", r#"JSON Value Error: json pointer "list" must be empty or start with '/'"#),
+        ];
+        for (ptr, message) in cases {
+            assert_eq!(message, value.remove_pointer(ptr).unwrap_err().to_string(), "{ptr}");
+        }
+        assert_eq!(before, value);
+
+        // removing the root leaves null
+        assert_eq!(before, value.remove_pointer("")?);
+        assert_eq!(JSONValue::Null, value);
+
+        Ok(())
+    }
+
+    #[test]
+    fn merge_patch_test() -> json::Result<()> {
+        // the examples of RFC 7386, appendix A
+        let examples = [
+            (r#"{"a":"b"}"#, r#"{"a":"c"}"#, r#"{"a":"c"}"#),
+            (r#"{"a":"b"}"#, r#"{"b":"c"}"#, r#"{"a":"b","b":"c"}"#),
+            (r#"{"a":"b"}"#, r#"{"a":null}"#, r#"{}"#),
+            (r#"{"a":"b","b":"c"}"#, r#"{"a":null}"#, r#"{"b":"c"}"#),
+            (r#"{"a":["b"]}"#, r#"{"a":"c"}"#, r#"{"a":"c"}"#),
+            (r#"{"a":"c"}"#, r#"{"a":["b"]}"#, r#"{"a":["b"]}"#),
+            (r#"{"a":{"b":"c"}}"#, r#"{"a":{"b":"d","c":null}}"#, r#"{"a":{"b":"d"}}"#),
+            (r#"{"a":[{"b":"c"}]}"#, r#"{"a":[1]}"#, r#"{"a":[1]}"#),
+            (r#"["a","b"]"#, r#"["c","d"]"#, r#"["c","d"]"#),
+            (r#"{"a":"b"}"#, r#"["c"]"#, r#"["c"]"#),
+            (r#"{"a":"foo"}"#, r#"null"#, r#"null"#),
+            (r#"{"a":"foo"}"#, r#""bar""#, r#""bar""#),
+            (r#"{"e":null}"#, r#"{"a":1}"#, r#"{"e":null,"a":1}"#),
+            (r#"[1,2]"#, r#"{"a":"b","c":null}"#, r#"{"a":"b"}"#),
+            (r#"{}"#, r#"{"a":{"bb":{"ccc":null}}}"#, r#"{"a":{"bb":{}}}"#),
+        ];
+        for (target, patch, result) in examples {
+            let mut value = JSONValue::from_str(target)?;
+            value.merge_patch(&JSONValue::from_str(patch)?);
+            assert_eq!(result, value.to_string_compact(), "{target} + {patch}");
+        }
+
+        // the diff is only what changed
+        let original = JSONValue::from_str(r#"{"title": "Goodbye!", "author": {"givenName": "John", "familyName": "Doe"}, "tags": ["example", "sample"], "content": "This will be unchanged"}"#)?;
+        let updated = JSONValue::from_str(r#"{"title": "Hello!", "author": {"givenName": "John"}, "tags": ["example"], "content": "This will be unchanged", "phoneNumber": "+01-123-456-7890"}"#)?;
+        let patch = JSONValue::merge_diff(&original, &updated);
+        assert_eq!(r#"{"title":"Hello!","author":{"familyName":null},"tags":["example"],"phoneNumber":"+01-123-456-7890"}"#, patch.to_string_compact());
+        assert_eq!(JSONValue::from_str("{}")?, JSONValue::merge_diff(&original, &original));
+
+        // applying the diff of two values to the first gives the second
+        fn value(rng: &mut Rng, depth: usize, member: bool) -> JSONValue {
+            match rng.below(if depth == 0 { 4 } else { 7 }) {
+                // a merge patch can't set an object member to null
+                0 if member => JSONValue::Bool(rng.below(2) == 0),
+                0 => JSONValue::Null,
+                1 => JSONValue::from(rng.below(3)),
+                2 | 3 => JSONValue::from(["x", "y"][rng.below(2)].to_string()),
+                4 => JSONValue::Array((0..rng.below(3)).map(|_| value(rng, depth - 1, false)).collect()),
+                _ => JSONValue::Object((0..rng.below(4)).map(|_| (["a", "b", "c"][rng.below(3)].to_string(), value(rng, depth - 1, true))).collect()),
+            }
+        }
+        let mut rng = Rng(0x5eed_1234_abcd_0001);
+        for _ in 0..5000 {
+            let (a, b) = (value(&mut rng, 3, false), value(&mut rng, 3, false));
+            let mut patched = a.clone();
+            patched.merge_patch(&JSONValue::merge_diff(&a, &b));
+            assert_eq!(b, patched, "{a:#} -> {b:#}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn json_patch_test() -> json::Result<()> {
+        // the examples of RFC 6902, appendix A
+        let examples = [
+            (r#"{"foo": "bar"}"#, r#"[{"op": "add", "path": "/baz", "value": "qux"}]"#, Ok(r#"{"baz": "qux", "foo": "bar"}"#)),
+            (r#"{"foo": ["bar", "baz"]}"#, r#"[{"op": "add", "path": "/foo/1", "value": "qux"}]"#, Ok(r#"{"foo": ["bar", "qux", "baz"]}"#)),
+            (r#"{"baz": "qux", "foo": "bar"}"#, r#"[{"op": "remove", "path": "/baz"}]"#, Ok(r#"{"foo": "bar"}"#)),
+            (r#"{"foo": ["bar", "qux", "baz"]}"#, r#"[{"op": "remove", "path": "/foo/1"}]"#, Ok(r#"{"foo": ["bar", "baz"]}"#)),
+            (r#"{"baz": "qux", "foo": "bar"}"#, r#"[{"op": "replace", "path": "/baz", "value": "boo"}]"#, Ok(r#"{"baz": "boo", "foo": "bar"}"#)),
+            (
+                r#"{"foo": {"bar": "baz", "waldo": "fred"}, "qux": {"corge": "grault"}}"#,
+                r#"[{"op": "move", "from": "/foo/waldo", "path": "/qux/thud"}]"#,
+                Ok(r#"{"foo": {"bar": "baz"}, "qux": {"corge": "grault", "thud": "fred"}}"#),
+            ),
+            (r#"{"foo": ["all", "grass", "cows", "eat"]}"#, r#"[{"op": "move", "from": "/foo/1", "path": "/foo/3"}]"#, Ok(r#"{"foo": ["all", "cows", "eat", "grass"]}"#)),
+            (
+                r#"{"baz": "qux", "foo": ["a", 2, "c"]}"#,
+                r#"[{"op": "test", "path": "/baz", "value": "qux"}, {"op": "test", "path": "/foo/1", "value": 2}]"#,
+                Ok(r#"{"baz": "qux", "foo": ["a", 2, "c"]}"#),
+            ),
+            (r#"{"baz": "qux"}"#, r#"[{"op": "test", "path": "/baz", "value": "bar"}]"#, Err(r#"JSON Value Error: json patch operation 0: test failed, the value at "/baz" is not the one given"#)),
+            (r#"{"foo": "bar"}"#, r#"[{"op": "add", "path": "/child", "value": {"grandchild": {}}}]"#, Ok(r#"{"foo": "bar", "child": {"grandchild": {}}}"#)),
+            (r#"{"foo": "bar"}"#, r#"[{"op": "add", "path": "/baz", "value": "qux", "xyz": 123}]"#, Ok(r#"{"foo": "bar", "baz": "qux"}"#)),
+            (r#"{"foo": "bar"}"#, r#"[{"op": "add", "path": "/baz/bat", "value": "qux"}]"#, Err(r#"JSON Key Error: json patch operation 0: key baz not found at "/baz""#)),
+            (r#"{"/": 9, "~1": 10}"#, r#"[{"op": "test", "path": "/~01", "value": 10}]"#, Ok(r#"{"/": 9, "~1": 10}"#)),
+            (r#"{"/": 9, "~1": 10}"#, r#"[{"op": "test", "path": "/~01", "value": "10"}]"#, Err(r#"JSON Value Error: json patch operation 0: test failed, the value at "/~01" is not the one given"#)),
+            (r#"{"foo": ["bar"]}"#, r#"[{"op": "add", "path": "/foo/-", "value": ["abc", "def"]}]"#, Ok(r#"{"foo": ["bar", ["abc", "def"]]}"#)),
+        ];
+        for (target, ops, result) in examples {
+            let mut value = JSONValue::from_str(target)?;
+            match (value.patch(&JSONValue::from_str(ops)?), result) {
+                (Ok(()), Ok(expected)) => assert_eq!(JSONValue::from_str(expected)?, value, "{ops}"),
+                (Err(err), Err(expected)) => assert_eq!(expected, err.to_string(), "{ops}"),
+                (outcome, _) => panic!("{ops}: {outcome:?}"),
+            }
+        }
+
+        // the other operations, and adding at the root
+        let mut value = JSONValue::from_str(r#"{"a": {"b": [1, 2]}}"#)?;
+        value.patch(&JSONValue::from_str(r#"[
+            {"op": "copy", "from": "/a/b", "path": "/c"},
+            {"op": "add", "path": "/c/0", "value": 0},
+            {"op": "replace", "path": "/a", "value": null},
+            {"op": "move", "from": "/c", "path": "/a"},
+            {"op": "add", "path": "/a/3", "value": 3},
+            {"op": "test", "path": "", "value": {"a": [0, 1, 2, 3]}}
+        ]"#)?)?;
+        assert_eq!(JSONValue::from_str(r#"{"a": [0, 1, 2, 3]}"#)?, value);
+        value.patch(&JSONValue::from_str(r#"[{"op": "add", "path": "", "value": [1]}, {"op": "remove", "path": "/0"}]"#)?)?;
+        assert_eq!(JSONValue::from_str("[]")?, value);
+
+        // a failure part way through leaves the value as it was
+        let mut value = JSONValue::from_str(r#"{"count": 1, "items": ["a"]}"#)?;
+        let before = value.clone();
+        let cases = [
+            // the first operation has already changed the count
+            (r#"{"op": "test", "path": "/count", "value": 1}"#, r#"JSON Value Error: json patch operation 2: test failed, the value at "/count" is not the one given"#),
+            (r#"{"op": "add", "path": "/items/5", "value": 2}"#, r#"JSON Index Error: json patch operation 2: index 5 out of bounds for length 2 at "/items/5""#),
+            (r#"{"op": "remove", "path": "/missing"}"#, r#"JSON Key Error: json patch operation 2: key missing not found at "/missing""#),
+            (r#"{"op": "replace", "path": "/missing", "value": 1}"#, r#"JSON Key Error: json patch operation 2: key missing not found at "/missing""#),
+            (r#"{"op": "move", "from": "/items", "path": "/items/0"}"#, r#"JSON Value Error: json patch operation 2: cannot move "/items" inside itself to "/items/0""#),
+            (r#"{"op": "copy", "from": "/nothing", "path": "/x"}"#, r#"JSON Key Error: json patch operation 2: key nothing not found at "/nothing""#),
+            (r#"{"op": "frobnicate", "path": "/x"}"#, r#"JSON Value Error: json patch operation 2: unknown operation "frobnicate""#),
+            (r#"{"op": "add", "path": "/x"}"#, "JSON Key Error: json patch operation 2: key value not found"),
+            (r#"{"path": "/x"}"#, "JSON Key Error: json patch operation 2: key op not found"),
+            (r#"{"op": "add", "path": 1, "value": 1}"#, r#"JSON Value Error: json patch operation 2: expected string for key "path", found "number""#),
+            (r#"["add"]"#, r#"JSON Value Error: json patch operation 2: expected object, found "array""#),
+        ];
+        for (op, message) in cases {
+            let ops = format!(r#"[{{"op": "replace", "path": "/count", "value": 2}}, {{"op": "add", "path": "/items/-", "value": "b"}}, {op}]"#);
+            assert_eq!(message, value.patch(&JSONValue::from_str(&ops)?).unwrap_err().to_string(), "{op}");
+            assert_eq!(before, value);
+        }
+        let err = value.patch(&JSONValue::from_str(r#"{"op": "remove", "path": "/count"}"#)?).unwrap_err();
+        assert_eq!(r#"JSON Value Error: expected json patch array, found "object""#, err.to_string());
+
+        Ok(())
+    }
+
+    #[test]
+    fn diff_test() -> json::Result<()> {
+        let old = JSONValue::from_str(r#"{"keep": 1, "gone": true, "kind": "x", "nested": {"a": [1, 2, 3], "b": {"c": 1}}, "a/b~": 0}"#)?;
+        let new = JSONValue::from_str(r#"{"keep": 1, "kind": ["x"], "nested": {"a": [1, 5], "b": {"c": 1, "d": null}}, "a/b~": 1, "added": {"x": 1}}"#)?;
+        let ops = json::diff(&old, &new);
+        let expected = JSONValue::from_str(r#"[
+            {"op": "remove", "path": "/gone"},
+            {"op": "replace", "path": "/kind", "value": ["x"]},
+            {"op": "replace", "path": "/nested/a/1", "value": 5},
+            {"op": "remove", "path": "/nested/a/2"},
+            {"op": "add", "path": "/nested/b/d", "value": null},
+            {"op": "replace", "path": "/a~1b~0", "value": 1},
+            {"op": "add", "path": "/added", "value": {"x": 1}}
+        ]"#)?;
+        assert_eq!(expected, ops);
+        let mut patched = old.clone();
+        patched.patch(&ops)?;
+        assert_eq!(new, patched);
+
+        assert_eq!(JSONValue::Array(vec![]), json::diff(&old, &old));
+        assert_eq!(JSONValue::from_str(r#"[{"op": "replace", "path": "", "value": 2}]"#)?, json::diff(&JSONValue::from(1), &JSONValue::from(2)));
+        assert_eq!(
+            JSONValue::from_str(r#"[{"op": "remove", "path": "/2"}, {"op": "remove", "path": "/1"}]"#)?,
+            json::diff(&JSONValue::from_str("[0, 1, 2]")?, &JSONValue::from_str("[0]")?),
+        );
+        assert_eq!(
+            JSONValue::from_str(r#"[{"op": "add", "path": "/-", "value": 1}, {"op": "add", "path": "/-", "value": [2]}]"#)?,
+            json::diff(&JSONValue::from_str("[]")?, &JSONValue::from_str("[1, [2]]")?),
+        );
+
+        // applying the diff of a document and an edited copy of it to the document gives the copy
+        fn value(rng: &mut Rng, depth: usize) -> JSONValue {
+            match rng.below(if depth == 0 { 4 } else { 6 }) {
+                0 => [JSONValue::Null, JSONValue::Bool(true)][rng.below(2)].clone(),
+                1 => JSONValue::from(rng.below(3)),
+                2 | 3 => JSONValue::from(["x", "~", "/"][rng.below(3)].to_string()),
+                4 => JSONValue::Array((0..rng.below(4)).map(|_| value(rng, depth - 1)).collect()),
+                _ => JSONValue::Object((0..rng.below(4)).map(|_| (["a", "b", "c/d", "~"][rng.below(4)].to_string(), value(rng, depth - 1))).collect()),
+            }
+        }
+        fn edit(rng: &mut Rng, target: &mut JSONValue) {
+            match target {
+                _ if rng.below(8) == 0 => *target = value(rng, 2),
+                JSONValue::Array(items) => {
+                    match rng.below(3) {
+                        0 if !items.is_empty() => {
+                            items.remove(rng.below(items.len()));
+                        }
+                        1 => {
+                            let at = rng.below(items.len() + 1);
+                            items.insert(at, value(rng, 2));
+                        }
+                        _ => {}
+                    }
+                    items.iter_mut().for_each(|item| edit(rng, item));
+                }
+                JSONValue::Object(map) => {
+                    match rng.below(3) {
+                        0 => {
+                            map.remove(["a", "b", "c/d", "~"][rng.below(4)]);
+                        }
+                        1 => {
+                            let key = ["a", "e", "~"][rng.below(3)].to_string();
+                            map.insert(key, value(rng, 2));
+                        }
+                        _ => {}
+                    }
+                    map.values_mut().for_each(|item| edit(rng, item));
+                }
+                _ => {}
+            }
+        }
+        let mut rng = Rng(0x0dd_ba11_5eed_0606);
+        for _ in 0..5000 {
+            let old = value(&mut rng, 4);
+            let mut new = old.clone();
+            edit(&mut rng, &mut new);
+            let mut patched = old.clone();
+            patched.patch(&json::diff(&old, &new))?;
+            assert_eq!(new, patched, "{old:#} -> {new:#}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn deep_merge_test() -> json::Result<()> {
+        use json::{ArrayMerge, ConflictMerge, MergeStrategy};
+
+        let defaults = JSONValue::from_file("tests/defaults.json")?;
+        let overrides = JSONValue::from_file("tests/override.json")?;
+        let common = r#""server": {"host": "0.0.0.0", "port": 9090, "tls": {"enabled": true, "cert": "/etc/cert.pem"}},
+            "log_level": "info", "region": "eu""#;
+        let cases = [
+            (ArrayMerge::Replace, r#""plugins": ["cache"], "workers": [{"threads": 8}, {"name": "b", "threads": 2}, {"name": "c"}]"#),
+            (
+                ArrayMerge::Concat,
+                r#""plugins": ["auth", "metrics", "cache"],
+                "workers": [{"name": "a", "threads": 2}, {"name": "b", "threads": 2}, {"threads": 8}, {"name": "b", "threads": 2}, {"name": "c"}]"#,
+            ),
+            (ArrayMerge::MergeByIndex, r#""plugins": ["cache", "metrics"], "workers": [{"name": "a", "threads": 8}, {"name": "b", "threads": 2}, {"name": "c"}]"#),
+        ];
+        for (arrays, expected) in cases {
+            let mut config = defaults.clone();
+            config.deep_merge(&overrides, MergeStrategy { arrays, ..Default::default() })?;
+            assert_eq!(JSONValue::from_str(&format!("{{{common}, {expected}}}"))?, config, "{arrays:?}");
+            // the keys of the defaults keep their places, and new ones go after them
+            assert_eq!(vec!["server", "plugins", "workers", "log_level", "region"], config.keys()?.collect::<Vec<_>>());
+        }
+
+        // keeping this side's values only adds what is missing
+        let mut config = defaults.clone();
+        config.deep_merge(&overrides, MergeStrategy { arrays: ArrayMerge::MergeByIndex, conflicts: ConflictMerge::KeepSelf })?;
+        let expected = r#"{"server": {"host": "0.0.0.0", "port": 8080, "tls": {"enabled": false, "cert": "/etc/cert.pem"}},
+            "plugins": ["auth", "metrics"], "workers": [{"name": "a", "threads": 2}, {"name": "b", "threads": 2}, {"name": "c"}],
+            "log_level": "info", "region": "eu"}"#;
+        assert_eq!(JSONValue::from_str(expected)?, config);
+
+        // a conflict names where it is, and nothing is merged
+        let mut config = defaults.clone();
+        let strategy = MergeStrategy { conflicts: ConflictMerge::Error, ..Default::default() };
+        let err = config.deep_merge(&overrides, strategy).unwrap_err();
+        assert_eq!(r#"JSON Value Error: conflicting values at "/server/port": 8080 and 9090"#, err.to_string());
+        assert_eq!(defaults, config);
+        let strategy = MergeStrategy { arrays: ArrayMerge::MergeByIndex, conflicts: ConflictMerge::Error };
+        let err = config.deep_merge(&JSONValue::from_str(r#"{"workers": [{}, {"threads": [2]}]}"#)?, strategy).unwrap_err();
+        assert_eq!(r#"JSON Value Error: conflicting values at "/workers/1/threads": 2 and [2]"#, err.to_string());
+        config.deep_merge(&JSONValue::from_str(r#"{"a/b": {"~": 1}}"#)?, strategy)?;
+        let err = config.deep_merge(&JSONValue::from_str(r#"{"a/b": {"~": 2}}"#)?, strategy).unwrap_err();
+        assert_eq!(r#"JSON Value Error: conflicting values at "/a~1b/~0": 1 and 2"#, err.to_string());
+        // equal values aren't a conflict, and neither are keys on one side only
+        config.deep_merge(&JSONValue::from_str(r#"{"server": {"port": 8080, "extra": true}}"#)?, strategy)?;
+        assert_eq!(JSONValue::Bool(true), config["server"]["extra"]);
+        assert!(JSONValue::from(1).deep_merge(&JSONValue::from_str("{}")?, strategy).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn take_test() -> json::Result<()> {
+        let results: Vec<String> = (0..10_000).map(|i| format!(r#"{{"id": {i}, "tags": ["a", "b"]}}"#)).collect();
+        let mut document = JSONValue::from_str(&format!(r#"{{"meta": {{"count": 3}}, "results": [{}], "last": true}}"#, results.join(",")))?;
+
+        // a large subtree moves out without being copied
+        let (results, allocations) = count_allocations(|| document.obj_take("results"));
+        let results = results?;
+        assert_eq!(0, allocations);
+        assert_eq!(10_000, results.len()?);
+        // the key stays, with null in its place, and its siblings stay where they were
+        assert_eq!(JSONValue::Null, document["results"]);
+        assert_eq!(vec!["meta", "results", "last"], document.keys()?.collect::<Vec<_>>());
+        assert_eq!(JSONValue::from_str(r#"{"meta": {"count": 3}, "results": null, "last": true}"#)?, document);
+
+        let mut results = results;
+        let taken = results.arr_take(1)?;
+        assert_eq!(JSONValue::from_str(r#"{"id": 1, "tags": ["a", "b"]}"#)?, taken);
+        assert_eq!(JSONValue::Null, results[1]);
+        assert_eq!(10_000, results.len()?);
+        assert_eq!(JSONValue::from(2), results[2]["id"]);
+
+        let mut meta = document["meta"].take();
+        assert_eq!(JSONValue::from(3), meta["count"].take());
+        assert_eq!(JSONValue::from_str(r#"{"count": null}"#)?, meta);
+        assert_eq!(JSONValue::Null, document["meta"]);
+
+        assert!(matches!(document.obj_take("missing"), Err(JSONError::KeyError(_))));
+        assert!(matches!(results.obj_take("id"), Err(JSONError::ValueError(_))));
+        assert!(matches!(results.arr_take(10_000), Err(JSONError::IndexError(message)) if message == "index 10000 out of bounds for length 10000"));
+        assert!(matches!(document.arr_take(0), Err(JSONError::ValueError(message)) if message == "expected array, found object"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn default_test() -> json::Result<()> {
+        assert_eq!(JSONValue::Null, JSONValue::default());
+
+        #[derive(Default)]
+        struct Settings {
+            extra: JSONValue,
+            retries: Option<u32>,
+        }
+        let settings = Settings::default();
+        assert_eq!((JSONValue::Null, None), (settings.extra, settings.retries));
+
+        let mut document = JSONValue::from_str(r#"{"a": [1, 2], "b": null}"#)?;
+        let a = std::mem::take(&mut document["a"]);
+        assert_eq!(JSONValue::from_str("[1, 2]")?, a);
+        assert_eq!(JSONValue::from_str(r#"{"a": null, "b": null}"#)?, document);
+        assert_eq!(JSONValue::Null, document.get_opt("missing").cloned().unwrap_or_default());
+
+        // fallbacks only replace null
+        assert_eq!(JSONValue::from(3), JSONValue::Null.or(JSONValue::from(3)));
+        assert_eq!(JSONValue::from(1), JSONValue::from(1).or(JSONValue::from(3)));
+        assert_eq!(JSONValue::Bool(false), JSONValue::Bool(false).or(JSONValue::Bool(true)));
+        assert_eq!(JSONValue::from("".to_string()), JSONValue::from("".to_string()).or(JSONValue::from(3)));
+        assert_eq!(JSONValue::from(30), document["b"].take().or(JSONValue::Null).or_else(|| JSONValue::from(30)));
+        assert_eq!(a, a.clone().or_else(|| unreachable!()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn json_macro_test() -> json::Result<()> {
+        let answer: u32 = 42;
+        let nest_arr = crate::json!([4, 5, 6, {"the_answer": answer, "oof": {}}]);
+        let built = crate::json!({
+            "foo": "bar",
+            "tmp": "lmao".to_string(),
+            "baz": [true, false, "bozo",],
+            "obj": {"a": 550, "b": 2.0, "c": -2.4443E-15},
+            "qux": {
+                "qux_null": null,
+                "qux_arr": [1 + 1, 3, false, "asdf", []],
+                "qux_obj": {
+                    "0_numeral": 123412314,
+                    "nest_arr": nest_arr,
+                },
+            },
+        });
+        let parsed = JSONValue::try_from(std::fs::read("tests/test.json")?)?;
+        assert_eq!(parsed, built);
+
+        assert_eq!(JSONValue::Null, crate::json!(null));
+        assert_eq!(JSONValue::Array(vec![]), crate::json!([]));
+        assert_eq!(JSONValue::Object(json::Map::new()), crate::json!({}));
+        assert_eq!(JSONValue::from(-1), crate::json!(-1));
+        assert_eq!(JSONValue::from_str("[null, [null], {}]")?, crate::json!([null, [null,], {},]));
+
+        // keys may be expressions, and a repeated key keeps its last value
+        let key = "k".to_string();
+        let value = crate::json!({(key.clone()): 1, "k": 2, (format!("{}2", key)): Some(false)});
+        assert_eq!(JSONValue::from_str(r#"{"k": 2, "k2": false}"#)?, value);
+
+        Ok(())
+    }
+
+    #[test]
+    fn from_map_test() -> json::Result<()> {
+        use std::collections::{BTreeMap, HashMap};
+
+        let hash_map = HashMap::from([("host".to_string(), JSONValue::from("localhost")), ("port".to_string(), JSONValue::from(80))]);
+        let value = JSONValue::from(hash_map);
+        assert_eq!(JSONValue::from_str(r#"{"host": "localhost", "port": 80}"#)?, value);
+        assert_eq!("localhost", value.get_as::<String>("host")?);
+        assert_eq!(80, value.get_as::<u16>("port")?);
+
+        let value = JSONValue::from(HashMap::from([("tls", true)]));
+        assert_eq!(&JSONValue::Bool(true), value.get("tls")?);
+
+        // a BTreeMap's keys come out sorted
+        let value = JSONValue::from(BTreeMap::from([("b", vec![JSONValue::Null]), ("a", vec![])]));
+        assert_eq!(vec!["a", "b"], value.keys()?.collect::<Vec<_>>());
+        assert_eq!(&JSONValue::Array(vec![JSONValue::Null]), value.get("b")?);
+
+        // the last of repeated keys wins, in the place of the first
+        let value = JSONValue::from(vec![("x".to_string(), 1), ("y".to_string(), 2), ("x".to_string(), 3)]);
+        assert_eq!(vec!["x", "y"], value.keys()?.collect::<Vec<_>>());
+        assert_eq!(3, value.get_as::<i32>("x")?);
+        assert_eq!(JSONValue::Object(json::Map::new()), JSONValue::from(Vec::<(String, JSONValue)>::new()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn from_str_slice_test() {
+        use std::borrow::Cow;
+
+        assert_eq!(JSONValue::String("x".to_string()), JSONValue::from("x"));
+        assert_eq!(JSONValue::String("".to_string()), JSONValue::from(""));
+        assert_eq!(JSONValue::String("borrowed".to_string()), JSONValue::from(Cow::Borrowed("borrowed")));
+        assert_eq!(JSONValue::String("owned".to_string()), JSONValue::from(Cow::<str>::Owned("owned".to_string())));
+
+        assert_eq!(JSONValue::String("name".to_string()), JSONValue::from(Some("name")));
+        assert_eq!(JSONValue::Null, JSONValue::from(None::<&str>));
+
+        let value = JSONValue::from('é');
+        assert_eq!(JSONValue::String("é".to_string()), value);
+        assert_eq!(1, value.len().unwrap());
+        assert_eq!("\"é\"", value.to_string());
+        assert_eq!(JSONValue::from_str("[\"a\", \"b\"]").unwrap(), ['a', 'b'].into_iter().collect::<JSONValue>());
+    }
+
+    #[test]
+    fn array_of_test() -> json::Result<()> {
+        assert_eq!(JSONValue::from_str("[1, 2, 3]")?, JSONValue::array_of(vec![1, 2, 3]));
+        assert_eq!(JSONValue::from_str(r#"["a", "b"]"#)?, JSONValue::array_of(vec!["a", "b"]));
+        assert_eq!(JSONValue::from_str("[true, null]")?, JSONValue::array_of([Some(true), None]));
+        assert_eq!(JSONValue::Array(vec![]), JSONValue::array_of(Vec::<u8>::new()));
+        assert_eq!(JSONValue::from_str("[[0.5], []]")?, JSONValue::array_of([JSONValue::array_of([0.5]), JSONValue::array_of(Vec::<f64>::new())]));
+
+        let ports: &[u16] = &[80, 443, 8080];
+        assert_eq!(JSONValue::from_str("[80, 443, 8080]")?, JSONValue::from(ports));
+        assert_eq!(JSONValue::from_str("[443]")?, JSONValue::from(&ports[1..2]));
+        assert_eq!(JSONValue::from_str(r#"["x"]"#)?, JSONValue::from(&["x".to_string()][..]));
+
+        let value = JSONValue::object_of([("host", JSONValue::from("localhost")), ("port", JSONValue::from(80)), ("host", JSONValue::from("example.com"))]);
+        assert_eq!(JSONValue::from_str(r#"{"host": "example.com", "port": 80}"#)?, value);
+        assert_eq!(vec!["host", "port"], value.keys()?.collect::<Vec<_>>());
+        assert_eq!(JSONValue::from_str(r#"{"a": [1]}"#)?, JSONValue::object_of([("a".to_string(), JSONValue::array_of([1]))]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn cast_vec_test() -> json::Result<()> {
+        let value = JSONValue::from_str(r#"{"hosts": ["a", "b"], "ports": [80, 443], "flags": [true, false], "mixed": [1, 2, "3", null], "matrix": [[0.5, 1], [], [2]]}"#)?;
+        assert_eq!(vec!["a".to_string(), "b".to_string()], value.get_as::<Vec<String>>("hosts")?);
+        assert_eq!(vec![80u16, 443], value.get_as::<Vec<u16>>("ports")?);
+        assert_eq!(vec![true, false], value.get_as::<Vec<bool>>("flags")?);
+        assert_eq!(vec![vec![0.5, 1.0], vec![], vec![2.0]], value.get_as::<Vec<Vec<f64>>>("matrix")?);
+
+        // an empty array casts to any element type
+        let empty = JSONValue::Array(vec![]);
+        assert_eq!(Vec::<String>::new(), Cast::<Vec<String>>::cast(&empty)?);
+        assert_eq!(Vec::<Vec<bool>>::new(), Cast::<Vec<Vec<bool>>>::cast(&empty)?);
+
+        let mixed = &value["mixed"];
+        match Cast::<Vec<f64>>::cast(mixed) {
+            Err(JSONError::ValueError(message)) => assert_eq!(r#"expected number for index 2, found "string""#, message),
+            other => panic!("unexpected {:?}", other),
+        }
+        match value.get_as::<Vec<Vec<f64>>>("hosts") {
+            Err(JSONError::ValueError(message)) => assert_eq!(r#"expected array for index 0 in key "hosts", found "string""#, message),
+            other => panic!("unexpected {:?}", other),
+        }
+        match Cast::<Vec<Vec<String>>>::cast(&value["matrix"]) {
+            Err(JSONError::ValueError(message)) => assert_eq!(r#"expected string for index 0 in index 0, found "number""#, message),
+            other => panic!("unexpected {:?}", other),
+        }
+        match Cast::<Vec<bool>>::cast(&JSONValue::from("[true]")) {
+            Err(JSONError::ValueError(message)) => assert_eq!(r#"expected array, found "string""#, message),
+            other => panic!("unexpected {:?}", other),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn cast_at_test() -> json::Result<()> {
+        // a cast the crate doesn't define gets the path appended to its own message
+        struct Port(u16);
+        impl Cast<Port> for JSONValue {
+            fn cast(&self) -> json::Result<Port> {
+                match Cast::<u16>::cast(self)? {
+                    0 => Err(JSONError::ValueError("port 0 is reserved".to_string())),
+                    port => Ok(Port(port)),
+                }
+            }
+        }
+
+        let value = JSONValue::from_str(r#"{"ports": [80, 0], "x, found y": ["a", 300]}"#)?;
+        assert_eq!(80, value["ports"].index_as::<Port>(0)?.0);
+        match value.get_as::<Vec<Port>>("ports") {
+            Err(JSONError::ValueError(message)) => assert_eq!(r#"port 0 is reserved for index 1 in key "ports""#, message),
+            other => panic!("unexpected {:?}", other.map(|_| ())),
+        }
+
+        // the path is never read back out of a message, so keys may look like one
+        match value.get_as::<Vec<String>>("x, found y") {
+            Err(JSONError::ValueError(message)) => assert_eq!(r#"expected string for index 1 in key "x, found y", found "number""#, message),
+            other => panic!("unexpected {:?}", other),
+        }
+        match Cast::<u8>::cast_at(&value["x, found y"][1], &[r#"key "x, found y""#, "index 1"]) {
+            Err(JSONError::ValueError(message)) => assert_eq!(r#"expected u8 for index 1 in key "x, found y", found 300, which is out of range"#, message),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert_eq!(300, Cast::<u16>::cast_at(&value["x, found y"][1], &["ignored"])?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn checked_cast_test() -> json::Result<()> {
+        use crate::json::CastLossy;
+
+        fn message<T: std::fmt::Debug>(result: json::Result<T>) -> String {
+            match result {
+                Err(JSONError::ValueError(message)) => message,
+                other => panic!("unexpected {:?}", other),
+            }
+        }
+
+        assert_eq!(255u8, Cast::<u8>::cast(&JSONValue::from(255))?);
+        assert_eq!(0u8, Cast::<u8>::cast(&JSONValue::from(0))?);
+        assert_eq!(0u8, Cast::<u8>::cast(&JSONValue::Number(-0.0))?);
+        assert_eq!("expected u8, found 256, which is out of range", message(Cast::<u8>::cast(&JSONValue::from(256))));
+        assert_eq!(-128i8, Cast::<i8>::cast(&JSONValue::from(-128))?);
+        assert_eq!("expected i8, found -129, which is out of range", message(Cast::<i8>::cast(&JSONValue::from(-129))));
+        assert_eq!("expected usize, found -1, which is out of range", message(Cast::<usize>::cast(&JSONValue::from(-1))));
+        assert_eq!("expected u32, found -0.5, which is out of range", message(Cast::<u32>::cast(&JSONValue::from(-0.5))));
+        assert_eq!("expected i32, found 2.5, which is not an integer", message(Cast::<i32>::cast(&JSONValue::from(2.5))));
+        assert_eq!("expected u64, found inf, which is out of range", message(Cast::<u64>::cast(&JSONValue::Number(f64::INFINITY))));
+        assert_eq!("expected i64, found NaN, which is not an integer", message(Cast::<i64>::cast(&JSONValue::Number(f64::NAN))));
+        assert_eq!("expected number, found \"string\"", message(Cast::<u8>::cast(&JSONValue::from("1"))));
+
+        // integers up to 2^53 are exact in an f64
+        let max_exact = 2f64.powi(53);
+        assert_eq!(9007199254740992i64, Cast::<i64>::cast(&JSONValue::from(max_exact))?);
+        assert_eq!(-9007199254740992i64, Cast::<i64>::cast(&JSONValue::from(-max_exact))?);
+        assert_eq!(9007199254740992u64, Cast::<u64>::cast(&JSONValue::from(max_exact))?);
+        assert_eq!(9007199254740992f64, Cast::<f64>::cast(&JSONValue::from(max_exact))?);
+        assert!(Cast::<u32>::cast(&JSONValue::from(max_exact)).is_err());
+        assert_eq!(i64::MIN, Cast::<i64>::cast(&JSONValue::Number(-2f64.powi(63)))?);
+        assert!(Cast::<i64>::cast(&JSONValue::Number(2f64.powi(63))).is_err());
+        assert!(Cast::<u64>::cast(&JSONValue::Number(2f64.powi(64))).is_err());
+        assert_eq!(1u128 << 100, Cast::<u128>::cast(&JSONValue::Number(2f64.powi(100)))?);
+
+        // f32 casts only fail on overflow
+        assert_eq!(0.1f32, Cast::<f32>::cast(&JSONValue::from(0.1))?);
+        assert_eq!(f32::MAX, Cast::<f32>::cast(&JSONValue::from(f32::MAX))?);
+        assert_eq!(format!("expected f32, found {}, which is out of range", 1e39), message(Cast::<f32>::cast(&JSONValue::from(1e39))));
+        assert!(Cast::<f32>::cast(&JSONValue::Number(f64::INFINITY))?.is_infinite());
+
+        // locations are still given
+        let value = JSONValue::from_str(r#"{"port": 70000, "ports": [80, 8.5]}"#)?;
+        assert_eq!(r#"expected u16 for key "port", found 70000, which is out of range"#, message(value.get_as::<u16>("port")));
+        assert_eq!(r#"expected u16 for index 1 in key "ports", found 8.5, which is not an integer"#, message(value.get_as::<Vec<u16>>("ports")));
+
+        // the old, lossy behavior
+        assert_eq!(255u8, CastLossy::<u8>::cast_lossy(&JSONValue::from(300))?);
+        assert_eq!(0usize, CastLossy::<usize>::cast_lossy(&JSONValue::from(-1))?);
+        assert_eq!(2i32, CastLossy::<i32>::cast_lossy(&JSONValue::from(2.5))?);
+        assert_eq!(f32::INFINITY, CastLossy::<f32>::cast_lossy(&JSONValue::from(1e39))?);
+        assert_eq!("expected number, found \"null\"", message(CastLossy::<u8>::cast_lossy(&JSONValue::Null)));
+
+        // comparisons no longer truncate
+        let (fraction, integer) = (JSONValue::from(2.5), JSONValue::from(2));
+        assert!(fraction != 2i32);
+        assert!(integer == 2i32);
+
+        Ok(())
+    }
+
+    #[test]
+    fn lexer_reset_test() -> json::Result<()> {
+        let documents: Vec<Vec<u8>> = ["tests/test.json", "tests/array.json", "tests/bom.json"]
+            .iter()
+            .map(|fixture| std::fs::read(fixture).unwrap())
+            .collect();
+
+        // a reset lexer gives the same tokens as a fresh one, even after stopping partway or failing
+        let broken = b"\n [1, $".to_vec();
+        let mut lexer = Lexer::new(&broken);
+        assert!(lexer.tokenify().is_err());
+        for bytes in &documents {
+            lexer.reset(bytes);
+            assert_eq!(format!("{:?}", Lexer::new(bytes).tokenify()?), format!("{:?}", lexer.tokenify()?));
+            lexer.reset(bytes);
+            assert_eq!(2, lexer.by_ref().take(2).count());
+        }
+        lexer.reset(&broken);
+        let err = lexer.tokenify().unwrap_err();
+        assert_eq!(Some(json::Position { line: 2, column: 6, offset: 6 }), err.position());
+        lexer.reset(b"");
+        assert!(lexer.next().is_none());
+
+        // options survive a reset
+        let options = json::Options::default().comments(true);
+        let commented = b"[1, /* two */ 2]".to_vec();
+        let mut lexer = Lexer::with_options(&documents[0], &options);
+        lexer.reset(&commented);
+        assert_eq!(format!("{:?}", Lexer::with_options(&commented, &options).tokenify()?), format!("{:?}", lexer.tokenify()?));
+
+        Ok(())
+    }
+
+    #[test]
+    fn parser_reset_test() -> json::Result<()> {
+        use crate::json::Parser;
+
+        let valid = std::fs::read("tests/test.json").unwrap();
+        let broken = b"\n[1 2]".to_vec();
+        let unfinished = b"{\"a\": [".to_vec();
+
+        // nothing of a failed or unfinished document carries over to the next
+        let mut parser = Parser::new(Lexer::new(&broken));
+        assert!(parser.parse_document().is_err());
+        parser.reset(Lexer::new(&valid));
+        assert_eq!(JSONValue::from_slice(&valid)?, parser.parse_document()?);
+        parser.reset(Lexer::new(&unfinished));
+        let err = parser.parse_document().unwrap_err();
+        assert_eq!(JSONValue::from_slice(&unfinished).unwrap_err().position(), err.position());
+        parser.reset(Lexer::new(&broken));
+        let err = parser.parse_document().unwrap_err();
+        assert_eq!(Some(json::Position { line: 2, column: 4, offset: 4 }), err.position());
+
+        // options survive a reset
+        let options = json::Options::default().trailing_commas(true);
+        let trailing = b"[1, 2,]".to_vec();
+        let mut parser = Parser::with_options(Lexer::new(&valid), &options);
+        parser.reset(Lexer::new(&trailing));
+        assert_eq!(JSONValue::from_str("[1, 2]")?, parser.parse_document()?);
+
         Ok(())
     }
+
+    /// The crate's original tests, run against the compat layer with only their imports, the
+    /// object constructor and the serializer call changed, next to what each breaking change does
+    /// to the current API.
+    mod migration {
+        use std::{collections::HashMap, str::FromStr, time};
+
+        use crate::compat::{self, Cast};
+        use crate::json::{self, JSONError, JSONValue};
+
+        #[test]
+        fn parser_test() -> json::Result<()> {
+            let buffer = std::fs::read("tests/array.json").unwrap();
+            assert_eq!(JSONValue::Array(vec![
+                JSONValue::Number(1.0),
+                JSONValue::Number(2.0),
+                JSONValue::Number(3.0),
+                JSONValue::Bool(true),
+                JSONValue::Null,
+            ]), JSONValue::try_from(buffer)?);
+
+            let buffer = std::fs::read("tests/string.json").unwrap();
+            let str: String = JSONValue::try_from(buffer)?.cast()?;
+            assert_eq!("asdfa sdfas\\df / wallalla tryn 165-08 {}{}___--=+123,./<>?".to_owned(), str);
+
+            Ok(())
+        }
+
+        macro_rules! int_test {
+            ($var:expr, $($type_name:ty)+) => {
+                $(let _var: $type_name = $var.cast()?;)+
+            };
+        }
+
+        #[test]
+        fn json_test() -> json::Result<()> {
+            let value = JSONValue::try_from(std::fs::read("tests/test.json").unwrap())?;
+
+            let a: String = value.get("foo")?.cast()?;
+            assert_eq!("bar", a);
+            let b: String = value["foo"].cast()?;
+            assert_eq!(a, b);
+
+            let bool: bool = value["baz"][0].cast()?;
+            assert!(bool);
+            let bool_2: bool = value.get("baz")?.try_index(0)?.cast()?;
+            assert_eq!(bool, bool_2);
+
+            int_test!(value["obj"]["b"], i8 i16 i32 i64 i128 isize u8 u16 u32 u64 u128 usize f32);
+            let the_answer: u8 = value["qux"]["qux_obj"]["nest_arr"][3]["the_answer"].cast()?;
+            assert_eq!(42, the_answer);
+
+            Ok(())
+        }
+
+        #[test]
+        fn serialize_test() -> json::Result<()> {
+            let mut values: JSONValue = JSONValue::from(compat::ObjectMap::new());
+            values.obj_insert("foo", JSONValue::from(vec![
+                JSONValue::from(23.4),
+                JSONValue::from("asdfasdf".to_owned()),
+                JSONValue::from(true),
+            ]))?;
+            values["foo"].arr_push(JSONValue::from(()))?;
+
+            let string = compat::to_string_legacy(&values);
+
+            assert_eq!("{
+    \"foo\": [
+        23.4,
+        \"asdfasdf\",
+        true,
+        null
+    ]
+}", format!("{string}"));
+
+            Ok(())
+        }
+
+        #[test]
+        fn big_parse_test() -> json::Result<()> {
+            let mut s: String = String::from("{");
+
+            for i in 0..2<<16 {
+                s.push_str(&format!("\"name_{0}\":{0},", i));
+            }
+
+            s.push_str(&format!("\"name_{0}\":{0}", 2<<16));
+            s.push('}');
+
+            let t0 = time::Instant::now();
+            let value = JSONValue::try_from(s.as_bytes().to_vec())?;
+            let t1 = time::Instant::now();
+
+            for i in 0..=2<<16 {
+                let v: i32 = value[format!("name_{}", i)].cast()?;
+                assert_eq!(i, v);
+            }
+            let t2 = time::Instant::now();
+            println!("time to complete parse: {}", (t1-t0).as_secs_f64());
+            println!("time to complete iteration: {}", (t2-t1).as_secs_f64());
+            println!("total time: {}", (t2-t0).as_secs_f64());
+
+            Ok(())
+        }
+
+        #[test]
+        fn cast_break_test() -> json::Result<()> {
+            let value = JSONValue::from_str(r#"[300, -1, 2.5, 1e39]"#)?;
+
+            let old: (u8, usize, i32, f32) = (value[0].cast()?, value[1].cast()?, value[2].cast()?, value[3].cast()?);
+            assert_eq!((255, 0, 2, f32::INFINITY), old);
+            assert!(json::Cast::<u8>::cast(&value[0]).is_err());
+            assert!(json::Cast::<usize>::cast(&value[1]).is_err());
+            assert!(json::Cast::<i32>::cast(&value[2]).is_err());
+            assert!(json::Cast::<f32>::cast(&value[3]).is_err());
+
+            // the old casts also took fractions and negative numbers
+            let value = JSONValue::try_from(std::fs::read("tests/test.json").unwrap())?;
+            int_test!(value["obj"]["c"], i8 i16 i32 i64 i128 isize u8 u16 u32 u64 u128 usize f32);
+
+            // the two agree wherever the number fits
+            let value = JSONValue::from(-42);
+            assert_eq!(Cast::<i8>::cast(&value)?, json::Cast::<i8>::cast(&value)?);
+            assert_eq!(Cast::<f64>::cast(&value)?, json::Cast::<f64>::cast(&value)?);
+            let value = JSONValue::from("x");
+            assert_eq!(Cast::<String>::cast(&value)?, json::Cast::<String>::cast(&value)?);
+            assert_eq!(
+                Cast::<bool>::cast(&value).unwrap_err().to_string(),
+                json::Cast::<bool>::cast(&value).unwrap_err().to_string(),
+            );
+
+            Ok(())
+        }
+
+        #[test]
+        fn display_break_test() -> json::Result<()> {
+            let value = JSONValue::from_str(r#"{"say \"hi\"": "a\nb", "empty": [], "none": {}, "n": [1e-7, 2.0]}"#)?;
+
+            // strings and keys were never escaped, so the old output isn't always valid JSON
+            assert_eq!("{
+    \"say \"hi\"\": \"a
+b\",
+    \"empty\": [
+    ],
+    \"none\": {
+    },
+    \"n\": [
+        0.0000001,
+        2
+    ]
+}", compat::to_string_legacy(&value));
+            assert!(JSONValue::from_str(&compat::to_string_legacy(&value)).is_err());
+
+            assert_eq!(r#"{
+    "say \"hi\"": "a\nb",
+    "empty": [],
+    "none": {},
+    "n": [
+        1e-7,
+        2
+    ]
+}"#, value.to_string());
+            assert_eq!(value, JSONValue::from_str(&value.to_string())?);
+
+            // the two agree on scalars without anything to escape
+            for scalar in ["true", "null", "12.5", "\"plain\""] {
+                let value = JSONValue::from_str(scalar)?;
+                assert_eq!(compat::to_string_legacy(&value), value.to_string());
+            }
+
+            Ok(())
+        }
+
+        #[test]
+        fn map_break_test() -> json::Result<()> {
+            let value = JSONValue::from_str(r#"{"z": 1, "a": 2, "m": 3}"#)?;
+
+            // objects hold an ordered Map instead of a HashMap
+            let map: &json::Map = value.as_object().unwrap();
+            assert_eq!(vec!["z", "a", "m"], map.keys().map(String::as_str).collect::<Vec<_>>());
+
+            let old: compat::ObjectMap = compat::into_hash_map(map.clone());
+            assert_eq!(HashMap::from([("z".to_string(), JSONValue::from(1)), ("a".to_string(), JSONValue::from(2)), ("m".to_string(), JSONValue::from(3))]), old);
+            assert_eq!(value, JSONValue::Object(json::Map::from(old.clone())));
+            assert_eq!(value, JSONValue::from(old));
+
+            Ok(())
+        }
+
+        #[test]
+        fn trailing_break_test() -> json::Result<()> {
+            // anything after the first value was ignored, as long as it could be lexed
+            assert_eq!(JSONValue::from_str("[1]")?, compat::from_slice_legacy(b"[1] 2 {")?);
+            assert_eq!(JSONValue::from(true), compat::from_slice_legacy(b"true false")?);
+            assert!(compat::from_slice_legacy(b"[1] $").is_err());
+            assert!(JSONValue::from_slice(b"[1] 2 {").is_err());
+            assert!(JSONValue::from_slice(b"true false").is_err());
+
+            // the two agree on whole documents
+            let bytes = std::fs::read("tests/test.json").unwrap();
+            assert_eq!(JSONValue::from_slice(&bytes)?, compat::from_slice_legacy(&bytes)?);
+
+            Ok(())
+        }
+
+        #[test]
+        fn error_break_test() {
+            // syntax errors became struct variants with a position, and running out of input has
+            // its own variant; message() gives the text the old variants held
+            let err = JSONValue::from_str("[1 2]").unwrap_err();
+            match &err {
+                JSONError::SyntaxError { message, position } => {
+                    assert_eq!("expected Comma, found NumericLiteral(\"2\")", message);
+                    assert_eq!(json::Position { line: 1, column: 4, offset: 3 }, *position);
+                }
+                other => panic!("unexpected {:?}", other),
+            }
+            assert_eq!(err.message(), format!("{err}").trim_start_matches("JSON Syntax Error: ").trim_end_matches(" at line 1, column 4"));
+            assert!(matches!(JSONValue::from_str("[1,"), Err(JSONError::UnexpectedEof { .. })));
+            assert!(matches!(JSONValue::from_str("[$]"), Err(JSONError::LexerError { .. })));
+
+            // errors on values kept their shape
+            let err = JSONValue::Null.get("a").unwrap_err();
+            assert!(matches!(&err, JSONError::ValueError(message) if message == "expected object, found \"null\""));
+            assert_eq!("JSON Value Error: expected object, found \"null\"", err.to_string());
+        }
+    }
 }
@@ -0,0 +1,60 @@
+/// Builds a `JSONValue` from JSON written as Rust tokens, e.g.
+/// `json!({"name": "svc", "ports": [80, port], "tls": true, "extra": null})`.
+///
+/// Keys are string literals, or any expression in parentheses that gives a `String` or `&str`.
+/// Any value other than `null` or a nested array or object is an expression, converted with
+/// `JSONValue::from`. Trailing commas are allowed. A repeated key keeps the last of its values.
+#[macro_export]
+macro_rules! json {
+    (null) => {
+        $crate::json::JSONValue::Null
+    };
+    ([ $($tt:tt)* ]) => {
+        $crate::json::JSONValue::Array($crate::json!(@array [] $($tt)*))
+    };
+    ({ $($tt:tt)* }) => {{
+        #[allow(unused_mut)]
+        let mut object = $crate::json::Map::new();
+        $crate::json!(@object object $($tt)*);
+        $crate::json::JSONValue::Object(object)
+    }};
+    ($value:expr) => {
+        $crate::json::JSONValue::from($value)
+    };
+
+    // the elements of an array, munched one at a time into the vector collected so far
+    (@array [$($elems:expr,)*]) => {
+        ::std::vec![$($elems,)*]
+    };
+    (@array [$($elems:expr,)*] null $(, $($rest:tt)*)?) => {
+        $crate::json!(@array [$($elems,)* $crate::json!(null),] $($($rest)*)?)
+    };
+    (@array [$($elems:expr,)*] [$($array:tt)*] $(, $($rest:tt)*)?) => {
+        $crate::json!(@array [$($elems,)* $crate::json!([$($array)*]),] $($($rest)*)?)
+    };
+    (@array [$($elems:expr,)*] {$($object:tt)*} $(, $($rest:tt)*)?) => {
+        $crate::json!(@array [$($elems,)* $crate::json!({$($object)*}),] $($($rest)*)?)
+    };
+    (@array [$($elems:expr,)*] $next:expr $(, $($rest:tt)*)?) => {
+        $crate::json!(@array [$($elems,)* $crate::json!($next),] $($($rest)*)?)
+    };
+
+    // the entries of an object, inserted into `$object` one at a time
+    (@object $object:ident) => {};
+    (@object $object:ident $key:tt : null $(, $($rest:tt)*)?) => {
+        $crate::json!(@entry $object $key ($crate::json!(null)) $($($rest)*)?);
+    };
+    (@object $object:ident $key:tt : [$($array:tt)*] $(, $($rest:tt)*)?) => {
+        $crate::json!(@entry $object $key ($crate::json!([$($array)*])) $($($rest)*)?);
+    };
+    (@object $object:ident $key:tt : {$($map:tt)*} $(, $($rest:tt)*)?) => {
+        $crate::json!(@entry $object $key ($crate::json!({$($map)*})) $($($rest)*)?);
+    };
+    (@object $object:ident $key:tt : $value:expr $(, $($rest:tt)*)?) => {
+        $crate::json!(@entry $object $key ($crate::json!($value)) $($($rest)*)?);
+    };
+    (@entry $object:ident $key:tt ($value:expr) $($rest:tt)*) => {
+        $object.insert(::std::string::String::from($key), $value);
+        $crate::json!(@object $object $($rest)*);
+    };
+}
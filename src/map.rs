@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+use std::ops::{Index, IndexMut};
+
+use crate::json::JSONValue;
+
+/// # Map
+///
+/// The entries of a JSON object, kept in the order their keys were first inserted, so a parsed
+/// document prints its keys in their original sequence. Lookups by key go through a hash index,
+/// as with a `HashMap`.
+///
+/// Two maps are equal when they have the same entries, whatever their order.
+#[derive(Clone, Default)]
+pub struct Map {
+    entries: Vec<(String, JSONValue)>,
+    /// Position of each key in `entries`
+    index: HashMap<String, usize>,
+}
+
+impl Map {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self { entries: Vec::with_capacity(capacity), index: HashMap::with_capacity(capacity) }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.index.contains_key(key)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&JSONValue> {
+        self.index.get(key).map(|&i| &self.entries[i].1)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut JSONValue> {
+        self.index.get(key).map(|&i| &mut self.entries[i].1)
+    }
+
+    /// Sets the value of `key`, returning its previous value if it had one. A new key goes after
+    /// every other; an existing one keeps its place.
+    pub fn insert(&mut self, key: String, value: JSONValue) -> Option<JSONValue> {
+        match self.index.get(&key) {
+            Some(&i) => Some(std::mem::replace(&mut self.entries[i].1, value)),
+            None => {
+                self.index.insert(key.clone(), self.entries.len());
+                self.entries.push((key, value));
+                None
+            }
+        }
+    }
+
+    /// The entry for `key`, to insert a value if it isn't there and change it if it is.
+    pub fn entry(&mut self, key: String) -> Entry<'_> {
+        Entry { map: self, key }
+    }
+
+    /// Removes `key`, returning its value if it had one. The entries after it move up to keep
+    /// their order, which takes time proportional to their number.
+    pub fn remove(&mut self, key: &str) -> Option<JSONValue> {
+        let i = self.index.remove(key)?;
+        let (_, value) = self.entries.remove(i);
+        for (key, _) in &self.entries[i..] {
+            if let Some(position) = self.index.get_mut(key) {
+                *position -= 1;
+            }
+        }
+        Some(value)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.index.clear();
+    }
+
+    /// Keeps only the entries `keep` returns true for, in their order.
+    pub fn retain(&mut self, mut keep: impl FnMut(&String, &mut JSONValue) -> bool) {
+        self.entries.retain_mut(|(key, value)| keep(key, value));
+        self.index.clear();
+        self.index.extend(self.entries.iter().enumerate().map(|(i, (key, _))| (key.clone(), i)));
+    }
+
+    /// The entries, in order.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (&String, &JSONValue)> + DoubleEndedIterator {
+        self.entries.iter().map(|(key, value)| (key, value))
+    }
+
+    /// The entries, in order, with their values mutable.
+    pub fn iter_mut(&mut self) -> impl ExactSizeIterator<Item = (&String, &mut JSONValue)> + DoubleEndedIterator {
+        self.entries.iter_mut().map(|(key, value)| (&*key, value))
+    }
+
+    pub fn keys(&self) -> impl ExactSizeIterator<Item = &String> + DoubleEndedIterator {
+        self.entries.iter().map(|(key, _)| key)
+    }
+
+    pub fn values(&self) -> impl ExactSizeIterator<Item = &JSONValue> + DoubleEndedIterator {
+        self.entries.iter().map(|(_, value)| value)
+    }
+
+    pub fn values_mut(&mut self) -> impl ExactSizeIterator<Item = &mut JSONValue> + DoubleEndedIterator {
+        self.entries.iter_mut().map(|(_, value)| value)
+    }
+}
+
+/// # Entry
+///
+/// A key of a [`Map`] that may or may not have a value yet, from [`Map::entry`], like the entries
+/// of a `HashMap`.
+pub struct Entry<'a> {
+    map: &'a mut Map,
+    key: String,
+}
+
+impl<'a> Entry<'a> {
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// The value of the key, after setting it to `default` if it had none. A new key goes after
+    /// every other.
+    pub fn or_insert(self, default: JSONValue) -> &'a mut JSONValue {
+        self.or_insert_with(|| default)
+    }
+
+    /// Like [`Entry::or_insert`], but only calls `default` if the key has no value.
+    pub fn or_insert_with(self, default: impl FnOnce() -> JSONValue) -> &'a mut JSONValue {
+        let i = match self.map.index.get(&self.key) {
+            Some(&i) => i,
+            None => {
+                self.map.index.insert(self.key.clone(), self.map.entries.len());
+                self.map.entries.push((self.key, default()));
+                self.map.entries.len() - 1
+            }
+        };
+        &mut self.map.entries[i].1
+    }
+
+    /// Calls `f` on the value of the key if it has one, then hands the entry back.
+    pub fn and_modify(self, f: impl FnOnce(&mut JSONValue)) -> Self {
+        if let Some(value) = self.map.get_mut(&self.key) {
+            f(value);
+        }
+        self
+    }
+}
+
+impl PartialEq for Map {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().all(|(key, value)| other.get(key) == Some(value))
+    }
+}
+
+impl std::fmt::Debug for Map {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl Index<&str> for Map {
+    type Output = JSONValue;
+    fn index(&self, key: &str) -> &Self::Output {
+        self.get(key).unwrap()
+    }
+}
+
+impl IndexMut<&str> for Map {
+    fn index_mut(&mut self, key: &str) -> &mut Self::Output {
+        self.get_mut(key).unwrap()
+    }
+}
+
+impl Extend<(String, JSONValue)> for Map {
+    fn extend<I: IntoIterator<Item = (String, JSONValue)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl FromIterator<(String, JSONValue)> for Map {
+    fn from_iter<I: IntoIterator<Item = (String, JSONValue)>>(iter: I) -> Self {
+        let mut map = Map::new();
+        map.extend(iter);
+        map
+    }
+}
+
+/// Takes the entries in the `HashMap`'s iteration order.
+impl From<HashMap<String, JSONValue>> for Map {
+    fn from(map: HashMap<String, JSONValue>) -> Self {
+        map.into_iter().collect()
+    }
+}
+
+impl IntoIterator for Map {
+    type Item = (String, JSONValue);
+    type IntoIter = std::vec::IntoIter<(String, JSONValue)>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Map {
+    type Item = (&'a String, &'a JSONValue);
+    type IntoIter = std::iter::Map<std::slice::Iter<'a, (String, JSONValue)>, fn(&'a (String, JSONValue)) -> (&'a String, &'a JSONValue)>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter().map(|(key, value)| (key, value))
+    }
+}
@@ -0,0 +1,106 @@
+use crate::json::{self, JSONError, JSONValue};
+
+/// How [`JSONValue::deep_merge`] combines two values where they differ. Objects are always
+/// merged key by key.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MergeStrategy {
+    /// What happens when both values are arrays
+    pub arrays: ArrayMerge,
+    /// What happens when the values differ and aren't both objects or both arrays
+    pub conflicts: ConflictMerge,
+}
+
+/// How [`JSONValue::deep_merge`] combines two arrays.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ArrayMerge {
+    /// The other array takes the place of this one
+    #[default]
+    Replace,
+    /// The items of the other array go after those of this one
+    Concat,
+    /// Items at the same index are merged, and the other array's items past the end of this one
+    /// are added to it
+    MergeByIndex,
+}
+
+/// What [`JSONValue::deep_merge`] does with two values that differ, other than two objects or two
+/// arrays.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ConflictMerge {
+    /// The other value takes the place of this one
+    #[default]
+    TakeOther,
+    /// This value stays as it is
+    KeepSelf,
+    /// The merge fails
+    Error,
+}
+
+impl JSONValue {
+    /// Merges `other` into this value, e.g. to layer a configuration file over the defaults. Keys
+    /// that both objects have are merged the same way, and keys only `other` has are added.
+    /// Arrays are combined as `strategy.arrays` says, and any other values that differ as
+    /// `strategy.conflicts` says.
+    ///
+    /// Returns `Err(ValueError)` naming the JSON Pointer of the first values that differ if
+    /// `strategy.conflicts` is [`ConflictMerge::Error`], in which case this value is left as it
+    /// was.
+    pub fn deep_merge(&mut self, other: &JSONValue, strategy: MergeStrategy) -> json::Result<()> {
+        let mut merged = self.clone();
+        merge(&mut merged, other, strategy, &mut String::new())?;
+        *self = merged;
+        Ok(())
+    }
+}
+
+/// Merges `other` into `target`, which is at the JSON Pointer `path`
+fn merge(target: &mut JSONValue, other: &JSONValue, strategy: MergeStrategy, path: &mut String) -> json::Result<()> {
+    match (target, other) {
+        (JSONValue::Object(map), JSONValue::Object(other)) => {
+            for (key, value) in other.iter() {
+                match map.get_mut(key) {
+                    Some(existing) => at(path, key, |path| merge(existing, value, strategy, path))?,
+                    None => {
+                        map.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (JSONValue::Array(items), JSONValue::Array(other)) => match strategy.arrays {
+            ArrayMerge::Replace => items.clone_from(other),
+            ArrayMerge::Concat => items.extend(other.iter().cloned()),
+            ArrayMerge::MergeByIndex => {
+                for (i, value) in other.iter().enumerate() {
+                    match items.get_mut(i) {
+                        Some(existing) => at(path, &i.to_string(), |path| merge(existing, value, strategy, path))?,
+                        None => items.push(value.clone()),
+                    }
+                }
+            }
+        },
+        (target, other) if target == other => {}
+        (target, other) => match strategy.conflicts {
+            ConflictMerge::TakeOther => *target = other.clone(),
+            ConflictMerge::KeepSelf => {}
+            ConflictMerge::Error => {
+                return Err(JSONError::ValueError(format!(
+                    "conflicting values at {:?}: {} and {}",
+                    path,
+                    target.preview(40, 1),
+                    other.preview(40, 1)
+                )))
+            }
+        },
+    }
+    Ok(())
+}
+
+/// Calls `f` with `path` extended by `token`, escaped as RFC 6901 asks
+fn at<R>(path: &mut String, token: &str, f: impl FnOnce(&mut String) -> R) -> R {
+    let len = path.len();
+    path.push('/');
+    path.push_str(&token.replace('~', "~0").replace('/', "~1"));
+    let result = f(path);
+    path.truncate(len);
+    result
+}
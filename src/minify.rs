@@ -0,0 +1,131 @@
+use std::io::Write;
+
+use crate::events::{validate, EventMachine};
+use crate::format::FormatOptions;
+use crate::json::{self, Position};
+use crate::lexer::{chars, Lexer};
+
+/// Appends `input` to `output` with all insignificant whitespace removed. Strings and numbers are
+/// copied byte for byte, so the result parses to the same value as `input`.
+///
+/// `input` is checked as it is copied and errors are the same as `JSONValue::try_from` would
+/// return. On error, `output` is left as it was.
+pub fn minify(input: &[u8], output: &mut Vec<u8>) -> json::Result<()> {
+    let len = output.len();
+    let result = copy_tokens(input, |token| {
+        output.extend_from_slice(token);
+        Ok(())
+    });
+    if result.is_err() {
+        output.truncate(len);
+    }
+    result
+}
+
+/// Like [`minify`], but writes to `writer`. Since written bytes can't be taken back, `input` is
+/// checked in full before anything is written. Wrapping `writer` in a `BufWriter` is recommended.
+pub fn minify_to<W: Write>(input: &[u8], mut writer: W) -> json::Result<()> {
+    validate(input)?;
+    copy_tokens(input, |token| Ok(writer.write_all(token)?))
+}
+
+/// Lays out `input` anew according to `opts`, as `JSONValue::to_string_with` would, but straight
+/// from its tokens rather than through a `JSONValue`: object members keep their order, repeated
+/// keys included, and strings and numbers are copied byte for byte, so `1.50` and `"\u00e9"`
+/// stay as they are written. The options about escaping and numbers therefore have no effect.
+///
+/// Errors are the same as `JSONValue::try_from` would return.
+pub fn reformat(input: &[u8], opts: &FormatOptions) -> json::Result<String> {
+    let mut tokens = vec![];
+    copy_tokens(input, |token| {
+        tokens.push(token);
+        Ok(())
+    })?;
+
+    let mut out = vec![];
+    let mut level = 0;
+    // the number of tokens left in the container being written on a single line, if any
+    let mut inline: Option<usize> = None;
+    let line_break = |out: &mut Vec<u8>, level: usize| {
+        out.extend_from_slice(opts.newline.as_str().as_bytes());
+        let mut indent = [0; 4];
+        for _ in 0..level * opts.indent_width {
+            out.extend_from_slice(opts.indent_char.encode_utf8(&mut indent).as_bytes());
+        }
+    };
+    for (i, &token) in tokens.iter().enumerate() {
+        let next = tokens.get(i + 1).copied();
+        match token {
+            b"{" | b"[" => {
+                out.extend_from_slice(token);
+                level += 1;
+                if inline.is_none() {
+                    inline = inline_len(&tokens[i..], opts);
+                }
+                if inline.is_none() && !matches!(next, Some(b"}" | b"]")) {
+                    line_break(&mut out, level);
+                }
+            }
+            b"}" | b"]" => {
+                level -= 1;
+                if inline.is_none() && !matches!(tokens[i - 1], b"{" | b"[") {
+                    line_break(&mut out, level);
+                }
+                out.extend_from_slice(token);
+            }
+            b"," => {
+                out.push(b',');
+                match inline {
+                    Some(_) => out.push(b' '),
+                    None => line_break(&mut out, level),
+                }
+            }
+            b":" => out.extend_from_slice(if opts.space_after_colon { b": " } else { b":" }),
+            _ => out.extend_from_slice(token),
+        }
+        inline = match inline {
+            Some(0) | None => None,
+            Some(left) => Some(left - 1),
+        };
+    }
+    Ok(String::from_utf8(out).unwrap_or_else(|err| String::from_utf8_lossy(err.as_bytes()).into_owned()))
+}
+
+/// If the container starting at the first of `tokens` fits on a single line of no more than
+/// `opts.inline_threshold` characters, the number of its tokens after the first
+fn inline_len(tokens: &[&[u8]], opts: &FormatOptions) -> Option<usize> {
+    let mut budget = opts.inline_threshold;
+    let mut depth = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        let len = match *token {
+            b"," => 2,
+            b":" if opts.space_after_colon => 2,
+            _ => chars(token),
+        };
+        budget = budget.checked_sub(len)?;
+        match *token {
+            b"{" | b"[" => depth += 1,
+            b"}" | b"]" => depth -= 1,
+            _ => {}
+        }
+        if depth == 0 {
+            return Some(i);
+        }
+    }
+    None
+}
+
+// passes the source text of each token of `input` to `emit`, checking the document as it goes
+fn copy_tokens<'a>(input: &'a [u8], mut emit: impl FnMut(&'a [u8]) -> json::Result<()>) -> json::Result<()> {
+    let mut lexer = Lexer::new(input);
+    let mut machine = EventMachine::new(Position { line: 1, column: 1, offset: 0 });
+    while let Some(token) = lexer.next_token()? {
+        let span = token.2;
+        machine.push(token, false)?;
+        emit(&input[span.range()])?;
+    }
+    if !machine.is_done() {
+        return Err(machine.eof_error());
+    }
+    Ok(())
+}
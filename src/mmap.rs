@@ -0,0 +1,68 @@
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+/// A read-only mapping of a whole file into memory, unmapped again on drop.
+///
+/// The pages are loaded lazily and can be evicted by the OS at any time, so a mapping of a huge
+/// file costs address space rather than memory. The file must not be truncated or modified while
+/// it is mapped, which is why `JSONValue::from_file_mmap` is unsafe.
+pub(crate) struct Mmap {
+    #[cfg(unix)]
+    ptr: *mut libc::c_void,
+    #[cfg(unix)]
+    len: usize,
+    /// Platforms without `mmap` read the file into memory instead
+    #[cfg(not(unix))]
+    bytes: Vec<u8>,
+}
+
+impl Mmap {
+    #[cfg(unix)]
+    pub fn open(path: &Path) -> io::Result<Self> {
+        use std::os::unix::io::AsRawFd;
+
+        let file = File::open(path)?;
+        let len = usize::try_from(file.metadata()?.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "file is too large to map"))?;
+        // mapping zero bytes is an error, and there is nothing to map anyway
+        if len == 0 {
+            return Ok(Self { ptr: std::ptr::null_mut(), len });
+        }
+        // SAFETY: a fresh private read-only mapping, which stays valid after `file` is closed
+        let ptr = unsafe { libc::mmap(std::ptr::null_mut(), len, libc::PROT_READ, libc::MAP_PRIVATE, file.as_raw_fd(), 0) };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self { ptr, len })
+    }
+
+    #[cfg(not(unix))]
+    pub fn open(path: &Path) -> io::Result<Self> {
+        Ok(Self { bytes: std::fs::read(path)? })
+    }
+
+    #[cfg(unix)]
+    pub fn as_slice(&self) -> &[u8] {
+        if self.len == 0 {
+            return &[];
+        }
+        // SAFETY: `ptr` points to `len` readable bytes until the mapping is dropped
+        unsafe { std::slice::from_raw_parts(self.ptr as *const u8, self.len) }
+    }
+
+    #[cfg(not(unix))]
+    pub fn as_slice(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        if self.len > 0 {
+            // SAFETY: the mapping was created by `open` and no slices of it outlive `self`
+            unsafe { libc::munmap(self.ptr, self.len) };
+        }
+    }
+}
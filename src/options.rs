@@ -0,0 +1,150 @@
+use std::fmt;
+use std::sync::Arc;
+
+use crate::json::{self, JSONValue, Position};
+
+/// # DuplicateKeys
+///
+/// What to do when an object contains the same key more than once.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DuplicateKeys {
+    /// Fail with a syntax error pointing at the repeated key.
+    Error,
+    /// Keep the value of the first occurrence.
+    First,
+    /// Keep the value of the last occurrence.
+    Last,
+}
+
+/// How deeply arrays and objects may nest unless [`Options::max_depth`] says otherwise, and
+/// always for the event-based readers. Parsing never recurses, but dropping, comparing and
+/// printing a value does, so this keeps hostile input like `[[[[...` from overflowing the stack.
+pub(crate) const DEFAULT_MAX_DEPTH: usize = 128;
+
+/// A caller-supplied conversion of number literals, see [`Options::number_hook`]. Compares
+/// equal only to clones of itself.
+#[derive(Clone)]
+pub(crate) struct NumberHook(Arc<NumberHookFn>);
+
+type NumberHookFn = dyn Fn(&str, Position) -> json::Result<JSONValue> + Send + Sync;
+
+impl NumberHook {
+    pub fn call(&self, literal: &str, position: Position) -> json::Result<JSONValue> {
+        (self.0)(literal, position)
+    }
+}
+
+impl fmt::Debug for NumberHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("NumberHook")
+    }
+}
+
+impl PartialEq for NumberHook {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for NumberHook {}
+
+/// # Options
+///
+/// Settings for [`JSONValue::parse_with`](crate::json::JSONValue::parse_with), built up from a
+/// preset:
+///
+/// - [`Options::default`] follows RFC 8259, keeps the last value of a duplicated key and allows
+///   nesting 128 levels deep, which is how `TryFrom` and `FromStr` parse,
+/// - [`Options::strict`] additionally rejects duplicated keys,
+/// - [`Options::relaxed`] accepts comments, trailing commas and lenient numbers.
+///
+/// Each knob only affects its own behavior, so e.g. `Options::default().comments(true)` accepts
+/// comments but is strict about everything else.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Options {
+    pub(crate) comments: bool,
+    pub(crate) trailing_commas: bool,
+    pub(crate) duplicate_keys: DuplicateKeys,
+    pub(crate) max_depth: Option<usize>,
+    pub(crate) lenient_numbers: bool,
+    pub(crate) strict_root: bool,
+    pub(crate) number_hook: Option<NumberHook>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            comments: false,
+            trailing_commas: false,
+            duplicate_keys: DuplicateKeys::Last,
+            max_depth: Some(DEFAULT_MAX_DEPTH),
+            lenient_numbers: false,
+            strict_root: false,
+            number_hook: None,
+        }
+    }
+}
+
+impl Options {
+    /// RFC 8259, with objects that repeat a key rejected.
+    pub fn strict() -> Self {
+        Self::default().duplicate_keys(DuplicateKeys::Error)
+    }
+
+    /// Accepts the common extensions found in hand-written files: comments, trailing commas and
+    /// lenient numbers.
+    pub fn relaxed() -> Self {
+        Self::default().comments(true).trailing_commas(true).lenient_numbers(true)
+    }
+
+    /// Whether `// line` and `/* block */` comments are allowed wherever whitespace is.
+    pub fn comments(mut self, allow: bool) -> Self {
+        self.comments = allow;
+        self
+    }
+
+    /// Whether the last element of an array or object may be followed by a comma.
+    pub fn trailing_commas(mut self, allow: bool) -> Self {
+        self.trailing_commas = allow;
+        self
+    }
+
+    pub fn duplicate_keys(mut self, policy: DuplicateKeys) -> Self {
+        self.duplicate_keys = policy;
+        self
+    }
+
+    /// The deepest nesting of arrays and objects allowed, with the root container at depth 1.
+    /// Defaults to 128; `None` puts no limit on it.
+    pub fn max_depth(mut self, depth: impl Into<Option<usize>>) -> Self {
+        self.max_depth = depth.into();
+        self
+    }
+
+    /// Whether numbers may have leading zeros (`007`) and a leading plus sign (`+1`).
+    pub fn lenient_numbers(mut self, allow: bool) -> Self {
+        self.lenient_numbers = allow;
+        self
+    }
+
+    /// Whether the root of the document must be an object or an array, as older (RFC 4627)
+    /// consumers expect. Scalars nested inside the root are unaffected.
+    pub fn strict_root(mut self, require: bool) -> Self {
+        self.strict_root = require;
+        self
+    }
+
+    /// Converts every number literal with `hook` instead of parsing it as an `f64`. The hook gets
+    /// the literal exactly as written, along with its position, and can return any value, e.g.
+    /// a `String` holding a decimal that an `f64` can't represent exactly.
+    ///
+    /// Errors from the hook stop parsing. Those without a position are turned into syntax errors
+    /// at the literal.
+    pub fn number_hook(
+        mut self,
+        hook: impl Fn(&str, Position) -> json::Result<JSONValue> + Send + Sync + 'static,
+    ) -> Self {
+        self.number_hook = Some(NumberHook(Arc::new(hook)));
+        self
+    }
+}
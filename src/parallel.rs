@@ -0,0 +1,87 @@
+use std::ops::Range;
+
+use rayon::prelude::*;
+
+use crate::json::{self, JSONValue, Options};
+use crate::lexer::BOM;
+use crate::options::DEFAULT_MAX_DEPTH;
+
+/// Finds the byte ranges of the elements of the array at the root of `bytes`, by balancing
+/// brackets and braces outside of string literals. Elements are not checked beyond that.
+///
+/// Returns `None` unless the root is an array with nothing but whitespace after it, and no
+/// element is empty (e.g. `[1,,2]`) or starts with a byte order mark, in which case the caller
+/// should parse `bytes` as a whole.
+pub(crate) fn split_array(bytes: &[u8]) -> Option<Vec<Range<usize>>> {
+    let is_whitespace = |b: &u8| matches!(b, b' ' | b'\t' | b'\n' | b'\r');
+    let start = if bytes.starts_with(&BOM) { BOM.len() } else { 0 };
+    let open = start + bytes[start..].iter().position(|b| !is_whitespace(b))?;
+    if bytes[open] != b'[' {
+        return None;
+    }
+
+    let mut elements = vec![];
+    let (mut depth, mut in_string, mut escaped) = (0usize, false, false);
+    let mut element_start = open + 1;
+    for (i, &b) in bytes.iter().enumerate().skip(open + 1) {
+        if in_string {
+            match b {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match b {
+            b'"' => in_string = true,
+            b'[' | b'{' => depth += 1,
+            b']' | b'}' if depth > 0 => depth -= 1,
+            b',' if depth == 0 => {
+                elements.push(element_start..i);
+                element_start = i + 1;
+            }
+            b']' if depth == 0 => {
+                let last = element_start..i;
+                // `[]` has no elements, but `[1,]` has an empty one
+                if !elements.is_empty() || bytes[last.clone()].iter().any(|b| !is_whitespace(b)) {
+                    elements.push(last);
+                }
+                // parsed on its own, an element would have a byte order mark at its start skipped
+                let invalid = elements.iter().any(|range| {
+                    let element = &bytes[range.clone()];
+                    element.iter().position(|b| !is_whitespace(b)).is_none_or(|first| element[first..].starts_with(&BOM))
+                });
+                let trailing = bytes[i + 1..].iter().any(|b| !is_whitespace(b));
+                return if invalid || trailing { None } else { Some(elements) };
+            }
+            // a `}` closing the root array
+            b'}' => return None,
+            _ => {}
+        }
+    }
+    // the array is never closed
+    None
+}
+
+/// Parses the elements of the array at the root of `bytes` on rayon's global thread pool, falling
+/// back to parsing on the current thread if the root is not an array or the document has an
+/// error, so errors are reported exactly as `from_slice` would.
+pub(crate) fn par_from_slice(bytes: &[u8]) -> json::Result<JSONValue> {
+    let elements = match split_array(bytes) {
+        Some(elements) => elements,
+        None => return JSONValue::from_slice(bytes),
+    };
+    // the elements sit one level below the root array
+    let options = Options::default().max_depth(DEFAULT_MAX_DEPTH - 1);
+
+    let parsed: json::Result<Vec<JSONValue>> = elements
+        .par_iter()
+        .map(|range| JSONValue::parse_with(&bytes[range.clone()], &options))
+        .collect();
+    match parsed {
+        Ok(values) => Ok(JSONValue::Array(values)),
+        // positions within an element are relative to it, so find the error again in context
+        Err(_) => JSONValue::from_slice(bytes),
+    }
+}
@@ -1,181 +1,324 @@
-use std::collections::HashMap;
-
 use crate::lexer::{Token, TokenPos};
 use crate::json::{*, self};
 
-pub struct Parser {
-    /// Array of lexed tokens
-    tokens: Vec<TokenPos>,
-    /// Current token
-    pos: usize,
+/// The token stream a parser built from a vector of tokens reads from.
+pub type VecTokens<'a> = std::iter::Map<std::vec::IntoIter<TokenPos<'a>>, fn(TokenPos<'a>) -> json::Result<TokenPos<'a>>>;
+
+/// Parses a stream of tokens, either a lazy [`Lexer`](crate::lexer::Lexer) or a vector of tokens
+/// that were lexed up front. Tokens are pulled one at a time, so an error in the stream stops
+/// parsing without reading anything after it.
+pub struct Parser<'a, I: Iterator<Item = json::Result<TokenPos<'a>>> = VecTokens<'a>> {
+    /// Stream of lexed tokens
+    tokens: I,
+    /// Current token, `None` before the first token is read and at the end of the stream
+    curr: Option<TokenPos<'a>>,
+    /// Position just past the last token read, where end of input errors point
+    end: Position,
+    options: Options,
 }
 
-impl From<Vec<TokenPos>> for Parser {
-    fn from(tokens: Vec<TokenPos>) -> Self {
+impl<'a> From<Vec<TokenPos<'a>>> for Parser<'a> {
+    fn from(tokens: Vec<TokenPos<'a>>) -> Self {
+        Self::new(tokens.into_iter().map(Ok as fn(TokenPos<'a>) -> json::Result<TokenPos<'a>>))
+    }
+}
+
+impl<'a, I: Iterator<Item = json::Result<TokenPos<'a>>>> Parser<'a, I> {
+    pub fn new(tokens: I) -> Self {
+        Self::with_options(tokens, &Options::default())
+    }
+
+    /// Creates a parser that applies the parsing rules in `options`. Only the options that concern
+    /// the structure of a document are used here; the rest are for the lexer.
+    pub fn with_options(tokens: I, options: &Options) -> Self {
         Self {
             tokens,
-            pos: 0,
+            curr: None,
+            end: Position { line: 1, column: 1, offset: 0 },
+            options: options.clone(),
         }
     }
-}
 
-impl Parser {
+    /// Replaces the tokens being parsed with `tokens`, forgetting how far the parser got through
+    /// the old ones but keeping its options, so one parser can be used for several documents in
+    /// turn.
+    pub fn reset(&mut self, tokens: I) {
+        self.tokens = tokens;
+        self.curr = None;
+        self.end = Position { line: 1, column: 1, offset: 0 };
+    }
+
+    /// The current token, or an end of input error naming what was `expected` instead
     #[inline]
-    fn curr(&self) -> Token {
-        self.tokens[self.pos].0.clone()
+    fn curr(&self, expected: &str) -> json::Result<&Token<'a>> {
+        match &self.curr {
+            Some(token) => Ok(&token.0),
+            None => Err(self.eof_error(expected)),
+        }
+    }
+
+    /// Moves the contents out of the current token, which the caller has checked is a string
+    /// literal, leaving it empty
+    fn take_string(&mut self) -> String {
+        match &mut self.curr {
+            Some(TokenPos(Token::StringLiteral(val), ..)) => std::mem::take(val).into_owned(),
+            _ => String::new(),
+        }
     }
+
+    fn eof_error(&self, expected: &str) -> JSONError {
+        JSONError::UnexpectedEof {
+            message: format!("unexpected end of input, expected {}", expected),
+            position: self.end,
+        }
+    }
+
+    /// Moves on to the next token of the stream
+    fn advance(&mut self) -> json::Result<()> {
+        self.curr = self.tokens.next().transpose()?;
+        if let Some(token) = &self.curr {
+            self.end = token.end();
+        }
+        Ok(())
+    }
+
     #[inline]
-    fn advance(&mut self, len: usize) {
-        self.pos += len;
+    fn position(&self) -> Position {
+        match &self.curr {
+            Some(token) => token.1,
+            None => self.end,
+        }
     }
 
-    fn expect(&mut self, expected: Token) -> json::Result<()> {
-        if self.curr() == expected {
-            self.pos += 1;
-            Ok(())
+    fn expect(&mut self, expected: Token<'a>) -> json::Result<()> {
+        let Some(TokenPos(found, ..)) = &self.curr else {
+            return Err(self.eof_error(&format!("{:?}", expected)));
+        };
+        if *found == expected {
+            self.advance()
         } else {
-            Err(JSONError::SyntaxError(format!("expected {:?}, found {:?}", expected, self.curr())))
+            Err(JSONError::SyntaxError {
+                message: format!("expected {:?}, found {:?}", expected, found),
+                position: self.position(),
+            })
         }
     }
 
-    /// Parse tokens in current
+    /// Reads the next value from the token stream.
     pub fn parse(&mut self) -> json::Result<JSONValue> {
-        let (line, column) = (self.tokens[self.pos].1, self.tokens[self.pos].2);
-        match self.curr().clone() {
-            Token::OpenBrace => {
-                // begin object
-                let mut ret: HashMap<String, JSONValue> = HashMap::new();
+        self.advance()?;
+        self.parse_value()
+    }
+
+    /// Reads a whole document: a single value, with nothing but whitespace after it.
+    pub fn parse_document(&mut self) -> json::Result<JSONValue> {
+        self.advance()?;
+        if let Some(TokenPos(token, position, _)) = &self.curr {
+            if self.options.strict_root && !matches!(token, Token::OpenBrace | Token::OpenBracket) {
+                return Err(JSONError::SyntaxError {
+                    message: format!("expected an object or array at the document root, found {:?}", token),
+                    position: *position,
+                });
+            }
+        }
+        let value = self.parse_value()?;
+        self.advance()?;
+        match &self.curr {
+            Some(TokenPos(token, position, _)) => Err(JSONError::SyntaxError {
+                message: format!("unexpected token `{:?}` after the end of the document", token),
+                position: *position,
+            }),
+            None => Ok(value),
+        }
+    }
 
-                self.advance(1);
+    /// Called on entering the container that starts at `position`, with `depth` containers
+    /// already open around it
+    fn enter(&self, depth: usize, position: Position) -> json::Result<()> {
+        match self.options.max_depth {
+            Some(max) if depth >= max => Err(JSONError::SyntaxError {
+                message: format!("arrays and objects nested deeper than the maximum depth of {}", max),
+                position,
+            }),
+            _ => Ok(()),
+        }
+    }
 
-                // catches the case of an empty object
-                if self.curr() == Token::CloseBrace {
-                    return Ok(JSONValue::Object(ret))
-                }
+    /// Whether the parser is on the closing `token` right after a comma, which is only the end
+    /// of the container if trailing commas are allowed
+    fn trailing_comma(&self, token: Token<'a>) -> json::Result<bool> {
+        let expected = match token {
+            Token::CloseBrace => "a key or CloseBrace",
+            _ => "a value or CloseBracket",
+        };
+        Ok(self.options.trailing_commas && *self.curr(expected)? == token)
+    }
 
-                // while last character is a comma
-                loop {
-                    // expect a string literal as a key
-                    let key = match self.curr().clone() {
-                        // chops off the quotations
-                        Token::StringLiteral(val) => val[1..val.len() - 1].to_owned(),
-                        _ => return Err(JSONError::SyntaxError(format!("expected string literal at line {line}, column {column}"))),
-                    };
-                    self.advance(1);
-
-                    // expect a colon
-                    self.expect(Token::Colon)?;
-                    // expect a JSONValue
-                    let val = self.parse()?;
-                    self.advance(1);
-
-                    ret.insert(key, val);
-
-                    if self.curr() == Token::CloseBrace {
-                        break;
-                    }
-                    self.expect(Token::Comma)?;
-                }
-                
-                Ok(JSONValue::Object(ret))
-            },
-            Token::CloseBrace => {
-                Err(JSONError::SyntaxError(format!("unexpected token `CloseBrace` at line {line}, column {column}")))
-            },
-            Token::OpenBracket => {
-                // begin array
-                let mut ret: Vec<JSONValue> = vec![];
-
-                // parse next token continuously, until the end of the array is reached
-                self.pos += 1;
-
-                // catch the case of an empty array
-                if self.curr() == Token::CloseBracket {
-                    return Ok(JSONValue::Array(ret));
-                }
+    /// Reads what follows an element of a container: either its `close` token, or a comma
+    /// before the next element. Returns whether the container ended.
+    fn element_end(&mut self, close: Token<'a>) -> json::Result<bool> {
+        let expected = match close {
+            Token::CloseBrace => "Comma or CloseBrace",
+            _ => "Comma or CloseBracket",
+        };
+        if *self.curr(expected)? == close {
+            return Ok(true);
+        }
+        self.expect(Token::Comma)?;
+        self.trailing_comma(close)
+    }
 
-                loop {
-                    ret.push(self.parse()?);
-                    // moves us off of value
-                    self.pos += 1;
+    /// Reads an object key and the colon after it, leaving the parser on the start of the value
+    fn key(&mut self) -> json::Result<(String, Position)> {
+        // expect a string literal as a key
+        let key_position = self.position();
+        let key = match self.curr("a key")? {
+            Token::StringLiteral(_) => self.take_string(),
+            other => return Err(JSONError::SyntaxError {
+                message: format!("expected string literal, found {:?}", other),
+                position: key_position,
+            }),
+        };
+        self.advance()?;
 
-                    // if we're at the end of the array...
-                    if self.curr() == Token::CloseBracket {
-                        break;
+        // expect a colon
+        self.expect(Token::Colon)?;
+        Ok((key, key_position))
+    }
+
+    /// Parses the value starting at the current token, leaving the parser on its last token.
+    ///
+    /// Open containers are kept on an explicit stack rather than the call stack, so nesting is
+    /// only bounded by memory and the depth limit.
+    fn parse_value(&mut self) -> json::Result<JSONValue> {
+        let mut stack: Vec<Frame> = vec![];
+        loop {
+            let position = self.position();
+            let mut value = match self.curr("a value")? {
+                Token::OpenBrace => {
+                    // begin object
+                    self.enter(stack.len(), position)?;
+                    self.advance()?;
+
+                    // catches the case of an empty object
+                    if *self.curr("a key or CloseBrace")? == Token::CloseBrace {
+                        JSONValue::Object(Map::new())
+                    } else {
+                        let (key, key_position) = self.key()?;
+                        stack.push(Frame::Object { entries: Map::new(), key, key_position });
+                        continue;
                     }
+                },
+                Token::CloseBrace => {
+                    return Err(JSONError::SyntaxError { message: "unexpected token `CloseBrace`".to_string(), position })
+                },
+                Token::OpenBracket => {
+                    // begin array
+                    self.enter(stack.len(), position)?;
+                    self.advance()?;
 
-                    self.expect(Token::Comma)?;
+                    // catch the case of an empty array
+                    if *self.curr("a value or CloseBracket")? == Token::CloseBracket {
+                        JSONValue::Array(vec![])
+                    } else {
+                        stack.push(Frame::Array(vec![]));
+                        continue;
+                    }
+                },
+                Token::CloseBracket => {
+                    return Err(JSONError::SyntaxError { message: "unexpected token `CloseBracket`".to_string(), position })
+                },
+                Token::Colon => {
+                    return Err(JSONError::SyntaxError { message: "unexpected token `Colon`".to_string(), position })
+                },
+                Token::Comma => {
+                    return Err(JSONError::SyntaxError { message: "unexpected token `Comma`".to_string(), position })
+                },
+                Token::StringLiteral(_) => {
+                    // begin string
+                    JSONValue::String(self.take_string())
+                },
+                Token::NumericLiteral(val) => {
+                    // begin number
+                    match &self.options.number_hook {
+                        Some(hook) => hook.call(val, position).map_err(|err| match err.position() {
+                            Some(_) => err,
+                            None => JSONError::SyntaxError { message: err.message().to_owned(), position },
+                        })?,
+                        None => JSONValue::Number(number(val, position)?),
+                    }
+                },
+                Token::True => {
+                    JSONValue::Bool(true)
+                },
+                Token::False => {
+                    JSONValue::Bool(false)
+                },
+                Token::Null => {
+                    JSONValue::Null
+                }
+                Token::Unknown(text) => {
+                    return Err(JSONError::SyntaxError { message: format!("unexpected token `{text}`"), position })
                 }
+            };
 
-                Ok(JSONValue::Array(ret))
-            },
-            Token::CloseBracket => {
-                Err(JSONError::SyntaxError(format!("unexpected token `CloseBracket` at line {line}, column {column}")))
-            },
-            Token::Colon => {
-                Err(JSONError::SyntaxError(format!("unexpected token `Colon` at line {line}, column {column}")))
-            },
-            Token::Comma => {
-                Err(JSONError::SyntaxError(format!("unexpected token `Comma` at line {line}, column {column}")))
-            },
-            Token::StringLiteral(val) => {
-                // begin string
-
-                // StringLiteral includes the '"' characters; filter those off
-                let trimmed = val[1..val.len() - 1].to_owned();
-                let t_iter: Vec<char> = trimmed.chars().collect();
-                let mut formatted: Vec<char> = vec![];
-                let mut i = 0;
-                while i < t_iter.len() {
-                    if t_iter[i] == '\\' {
-                        formatted.push(match t_iter[i+1] {
-                            '"' => '"',
-                            '\\' => '\\',
-                            '/' => '/',
-                            'b' => '\u{0008}',
-                            'f' => '\u{000c}',
-                            'n' => '\u{000a}',
-                            'r' => '\u{000d}',
-                            't' => '\u{0009}',
-                            'u' => {
-                                let chars: String = t_iter[i+2..i+6].iter().collect();
-
-                                let num = u16::from_str_radix(&chars, 16)
-                                    .or(Err(JSONError::ValueError(format!("invalid hexadecimal code: {}", chars))))? as u32;
-                                
-                                i += 4;
-                                match char::from_u32(num) {
-                                    Some(v) => v,
-                                    None => return Err(JSONError::ValueError(format!("invalid utf16 hexadecimal code: {}", &chars)))
-                                }
+            // hand the finished value to the innermost open container, and keep closing
+            // containers until one of them has another element to parse
+            loop {
+                let Some(frame) = stack.last_mut() else {
+                    return Ok(value);
+                };
+                // moves us off of value
+                self.advance()?;
+
+                let closed = match frame {
+                    Frame::Object { entries, key, key_position } => {
+                        let name = std::mem::take(key);
+                        match self.options.duplicate_keys {
+                            DuplicateKeys::Error if entries.contains_key(&name) => return Err(JSONError::SyntaxError {
+                                message: format!("duplicate key {:?}", name),
+                                position: *key_position,
+                            }),
+                            DuplicateKeys::First if entries.contains_key(&name) => {}
+                            _ => {
+                                entries.insert(name, value);
                             }
-                            _ => return Err(JSONError::ValueError(format!("invalid escape char: {}", t_iter[i+1])))
-                        });
-                        i += 1;
-                    } else {
-                        formatted.push(t_iter[i]);
+                        }
+
+                        let closed = self.element_end(Token::CloseBrace)?;
+                        if !closed {
+                            (*key, *key_position) = self.key()?;
+                        }
+                        closed
                     }
-                    i += 1;
+                    Frame::Array(items) => {
+                        items.push(value);
+                        self.element_end(Token::CloseBracket)?
+                    }
+                };
+                if !closed {
+                    break;
                 }
 
-                Ok(JSONValue::String(String::from_utf8(formatted.iter().map(|c| *c as u8).collect()).unwrap()))
-            },
-            Token::NumericLiteral(val) => {
-                // begin number
-                Ok(JSONValue::Number(val.parse().unwrap()))
-            },
-            Token::True => {
-                Ok(JSONValue::Bool(true))
-            },
-            Token::False => {
-                Ok(JSONValue::Bool(false))
-            },
-            Token::Null => {
-                Ok(JSONValue::Null)
-            }
-            Token::Unknown(text) => {
-                Err(JSONError::SyntaxError(format!("unexpected token `{text}` at line {line}, column {column}")))
+                value = match stack.pop() {
+                    Some(Frame::Object { entries, .. }) => JSONValue::Object(entries),
+                    Some(Frame::Array(items)) => JSONValue::Array(items),
+                    None => unreachable!("the stack was checked to be non-empty"),
+                };
             }
         }
     }
 }
+
+/// An array or object whose elements are still being parsed
+enum Frame {
+    /// The entries read so far, and the key of the value being parsed
+    Object { entries: Map, key: String, key_position: Position },
+    Array(Vec<JSONValue>),
+}
+
+/// Converts a number literal token to its value. The lexer only hands out literals that follow
+/// the JSON grammar, all of which `f64` parses, but the token may not have come from the lexer.
+pub(crate) fn number(literal: &str, position: Position) -> json::Result<f64> {
+    literal.parse().map_err(|_| JSONError::SyntaxError { message: format!("invalid number `{}`", literal), position })
+}
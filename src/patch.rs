@@ -0,0 +1,164 @@
+use crate::json::{self, JSONError, JSONValue, Map};
+use crate::pointer;
+
+impl JSONValue {
+    /// Applies `patch` to this value as a JSON Merge Patch (RFC 7386):
+    /// - a patch that isn't an object replaces the value whole, arrays included,
+    /// - an object patch sets each of its keys, turning the value into an object first if it
+    ///   isn't one: a `null` removes the key, an object is merged into the key's value the same
+    ///   way, and anything else replaces it.
+    pub fn merge_patch(&mut self, patch: &JSONValue) {
+        let JSONValue::Object(patch) = patch else {
+            *self = patch.clone();
+            return;
+        };
+        if !self.is_object() {
+            *self = JSONValue::Object(Map::new());
+        }
+        let map = self.as_object_mut().unwrap();
+        for (key, value) in patch.iter() {
+            match value {
+                JSONValue::Null => {
+                    map.remove(key);
+                }
+                _ => map.entry(key.clone()).or_insert(JSONValue::Null).merge_patch(value),
+            }
+        }
+    }
+
+    /// The smallest merge patch that turns `original` into `updated` when applied with
+    /// [`JSONValue::merge_patch`]: the keys that changed, recursing into objects on both sides,
+    /// with `null` for keys that are gone. Anything else that differs, arrays included, is
+    /// replaced whole.
+    ///
+    /// A merge patch has no way to set a key to `null`, so if `updated` has object members that
+    /// are `null`, applying the patch leaves them out.
+    pub fn merge_diff(original: &JSONValue, updated: &JSONValue) -> JSONValue {
+        let (JSONValue::Object(original), JSONValue::Object(updated)) = (original, updated) else {
+            return updated.clone();
+        };
+        let mut patch = Map::new();
+        for key in original.keys().filter(|key| !updated.contains_key(key)) {
+            patch.insert(key.clone(), JSONValue::Null);
+        }
+        for (key, value) in updated.iter() {
+            match original.get(key) {
+                Some(old) if old == value => {}
+                Some(old) => {
+                    patch.insert(key.clone(), Self::merge_diff(old, value));
+                }
+                None => {
+                    patch.insert(key.clone(), value.clone());
+                }
+            }
+        }
+        JSONValue::Object(patch)
+    }
+
+    /// Applies `ops`, a JSON Patch (RFC 6902) array of operations like
+    /// `{"op": "add", "path": "/a/0", "value": 1}`, to this value. Each operation is one of `add`,
+    /// `remove`, `replace`, `move`, `copy` and `test`, with paths written as JSON Pointers like
+    /// those of [`JSONValue::pointer`]; members other than those an operation uses are ignored.
+    ///
+    /// The operations apply all or not at all: if one fails, including a `test` whose value
+    /// differs, this value is left as it was, and the error names the index of the operation in
+    /// `ops` along with the reason, e.g. the `KeyError` of a path that leads nowhere.
+    pub fn patch(&mut self, ops: &JSONValue) -> json::Result<()> {
+        let ops = ops.as_array().ok_or_else(|| {
+            JSONError::ValueError(format!("expected json patch array, found {:?}", ops.json_type().as_str()))
+        })?;
+        let mut patched = self.clone();
+        for (i, op) in ops.iter().enumerate() {
+            apply(&mut patched, op).map_err(|err| match err {
+                JSONError::ValueError(message) => JSONError::ValueError(format!("json patch operation {}: {}", i, message)),
+                JSONError::KeyError(message) => JSONError::KeyError(format!("json patch operation {}: {}", i, message)),
+                JSONError::IndexError(message) => JSONError::IndexError(format!("json patch operation {}: {}", i, message)),
+                other => other,
+            })?;
+        }
+        *self = patched;
+        Ok(())
+    }
+}
+
+/// The JSON Patch (RFC 6902) array of operations that turns `old` into `new` when applied with
+/// [`JSONValue::patch`]. Objects on both sides are compared key by key, with a `remove` for each
+/// key that is gone, an `add` for each that is new, and only the keys that changed looked into
+/// further. Arrays are compared item by item, with items removed from or added to the end when
+/// the lengths differ, so an insertion near the start changes every item after it. Anything else
+/// that differs is a `replace`.
+pub fn diff(old: &JSONValue, new: &JSONValue) -> JSONValue {
+    let mut ops = vec![];
+    diff_at(&mut ops, String::new(), old, new);
+    JSONValue::Array(ops)
+}
+
+fn diff_at(ops: &mut Vec<JSONValue>, path: String, old: &JSONValue, new: &JSONValue) {
+    match (old, new) {
+        (JSONValue::Object(old), JSONValue::Object(new)) => {
+            for key in old.keys().filter(|key| !new.contains_key(key)) {
+                ops.push(operation("remove", child(&path, key), None));
+            }
+            for (key, value) in new.iter() {
+                match old.get(key) {
+                    Some(previous) => diff_at(ops, child(&path, key), previous, value),
+                    None => ops.push(operation("add", child(&path, key), Some(value))),
+                }
+            }
+        }
+        (JSONValue::Array(old), JSONValue::Array(new)) => {
+            for (i, (previous, value)) in old.iter().zip(new).enumerate() {
+                diff_at(ops, child(&path, &i.to_string()), previous, value);
+            }
+            // from the end, so the indices of the items still to remove stay the same
+            for i in (new.len()..old.len()).rev() {
+                ops.push(operation("remove", child(&path, &i.to_string()), None));
+            }
+            for value in new.iter().skip(old.len()) {
+                ops.push(operation("add", child(&path, "-"), Some(value)));
+            }
+        }
+        _ if old == new => {}
+        _ => ops.push(operation("replace", path, Some(new))),
+    }
+}
+
+// the path of `token` inside the value at `path`, escaped as RFC 6901 asks
+fn child(path: &str, token: &str) -> String {
+    format!("{}/{}", path, token.replace('~', "~0").replace('/', "~1"))
+}
+
+fn operation(op: &str, path: String, value: Option<&JSONValue>) -> JSONValue {
+    let mut members = vec![("op", JSONValue::from(op.to_string())), ("path", JSONValue::from(path))];
+    members.extend(value.map(|value| ("value", value.clone())));
+    members.into_iter().collect()
+}
+
+fn apply(value: &mut JSONValue, op: &JSONValue) -> json::Result<()> {
+    let name: String = op.get_as("op")?;
+    let path: String = op.get_as("path")?;
+    match name.as_str() {
+        "add" => pointer::insert(value, &path, op.get("value")?.clone()),
+        "remove" => value.remove_pointer(&path).map(drop),
+        "replace" => {
+            *value.pointer_mut(&path)? = op.get("value")?.clone();
+            Ok(())
+        }
+        "move" => {
+            let from: String = op.get_as("from")?;
+            if path.strip_prefix(from.as_str()).is_some_and(|rest| rest.starts_with('/')) {
+                return Err(JSONError::ValueError(format!("cannot move {:?} inside itself to {:?}", from, path)));
+            }
+            let moved = value.remove_pointer(&from)?;
+            pointer::insert(value, &path, moved)
+        }
+        "copy" => {
+            let from: String = op.get_as("from")?;
+            let copied = value.pointer(&from)?.clone();
+            pointer::insert(value, &path, copied)
+        }
+        "test" if value.pointer(&path)? == op.get("value")? => Ok(()),
+        "test" => Err(JSONError::ValueError(format!("test failed, the value at {:?} is not the one given", path))),
+        other => Err(JSONError::ValueError(format!("unknown operation {:?}", other))),
+    }
+}
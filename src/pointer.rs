@@ -0,0 +1,305 @@
+use crate::events::{dispatch, Event, EventReader, TreeBuilder};
+use crate::json::{self, JSONError, JSONValue, Map, Position};
+use crate::lexer::Lexer;
+
+// splits an RFC 6901 pointer into its unescaped reference tokens
+pub(crate) fn tokens(ptr: &str) -> json::Result<Vec<String>> {
+    if ptr.is_empty() {
+        return Ok(vec![]);
+    }
+    match ptr.strip_prefix('/') {
+        Some(rest) => Ok(rest.split('/').map(|t| t.replace("~1", "/").replace("~0", "~")).collect()),
+        None => Err(JSONError::ValueError(format!("json pointer {:?} must be empty or start with '/'", ptr))),
+    }
+}
+
+impl JSONValue {
+    /// Looks up the value `ptr` leads to, as an RFC 6901 JSON Pointer: `""` is this whole value,
+    /// and each `/`-separated token of `/a/b/0` selects a key of an object or an index of an
+    /// array, with `~1` and `~0` in a token standing for `/` and `~`. Indices must be written
+    /// without leading zeros, and `-` selects nothing.
+    ///
+    /// Each error names the part of `ptr` that failed, e.g. `"/a/b"` for a missing key `b`:
+    /// - `Err(KeyError)` if an object has no such key,
+    /// - `Err(IndexError)` if an index is out of bounds,
+    /// - `Err(ValueError)` if a token selects from a scalar, if a token for an array isn't an
+    ///   index, or if `ptr` is neither empty nor starts with `/`.
+    pub fn pointer(&self, ptr: &str) -> json::Result<&JSONValue> {
+        resolve(self, ptr)
+    }
+
+    /// Like [`JSONValue::pointer`], but returns a mutable reference.
+    pub fn pointer_mut(&mut self, ptr: &str) -> json::Result<&mut JSONValue> {
+        resolve_mut(self, ptr)
+    }
+
+    /// Sets the value `ptr` leads to, as a JSON Pointer like those of [`JSONValue::pointer`],
+    /// creating whatever is missing on the way there:
+    /// - a missing key of an object, or a `null`, becomes an array if the token after it is an
+    ///   index or `-`, and an object otherwise,
+    /// - an index past the end of an array pads it with `null` up to that index, as long as it is
+    ///   at most [`MAX_POINTER_PADDING`] past the end,
+    /// - `-` appends to an array.
+    ///
+    /// Each error names the part of `ptr` that failed, and nothing is changed then:
+    /// - `Err(IndexError)` if an index is more than [`MAX_POINTER_PADDING`] past the end of its array,
+    /// - `Err(ValueError)` if a token selects from a scalar other than `null`, if a token for an
+    ///   array isn't an index or `-`, or if `ptr` is neither empty nor starts with `/`.
+    pub fn set_pointer(&mut self, ptr: &str, value: JSONValue) -> json::Result<()> {
+        let segments = segments(ptr)?;
+        check_padding(self, &segments)?;
+        let mut curr = self;
+        for (token, at) in segments {
+            if curr.is_null() {
+                *curr = if token == "-" || index(&token, at).is_ok() {
+                    JSONValue::Array(vec![])
+                } else {
+                    JSONValue::Object(Map::new())
+                };
+            }
+            curr = match curr {
+                JSONValue::Object(map) => map.entry(token).or_insert(JSONValue::Null),
+                JSONValue::Array(arr) => {
+                    let index = match token.as_str() {
+                        "-" => arr.len(),
+                        _ => index(&token, at)?,
+                    };
+                    if index >= arr.len() {
+                        arr.resize(index + 1, JSONValue::Null);
+                    }
+                    &mut arr[index]
+                }
+                scalar => return Err(not_a_container(scalar.json_type().as_str(), at)),
+            };
+        }
+        *curr = value;
+        Ok(())
+    }
+
+    /// Removes the value `ptr` leads to, as a JSON Pointer like those of [`JSONValue::pointer`],
+    /// and returns it. The items after it in an array move up, as with
+    /// [`JSONValue::arr_remove`]. The empty pointer takes the whole value, leaving `Null`.
+    ///
+    /// Returns the errors of `pointer` if `ptr` doesn't lead to a value, naming the part of `ptr`
+    /// that failed. Nothing is removed then.
+    pub fn remove_pointer(&mut self, ptr: &str) -> json::Result<JSONValue> {
+        let Some((token, at)) = segments(ptr)?.pop() else {
+            return Ok(std::mem::replace(self, JSONValue::Null));
+        };
+        // a pointer with a token has a `/` before it, and what comes before leads to its parent
+        match resolve_mut(self, &ptr[..ptr.rfind('/').unwrap()])? {
+            JSONValue::Object(map) => map.remove(&token).ok_or_else(|| missing_key(&token, at)),
+            JSONValue::Array(arr) => {
+                let index = index(&token, at)?;
+                if index < arr.len() {
+                    Ok(arr.remove(index))
+                } else {
+                    Err(out_of_bounds(index, arr.len(), at))
+                }
+            }
+            scalar => Err(not_a_container(scalar.json_type().as_str(), at)),
+        }
+    }
+}
+
+/// Adds `new` at `ptr` inside `value` the way a JSON Patch `add` does: the key is set if the
+/// parent is an object, `new` goes before the item at the index if it is an array, or after the
+/// last one for `-`, and the empty pointer replaces `value` whole. The parent must already exist.
+pub(crate) fn insert(value: &mut JSONValue, ptr: &str, new: JSONValue) -> json::Result<()> {
+    let Some((token, at)) = segments(ptr)?.pop() else {
+        *value = new;
+        return Ok(());
+    };
+    // as in remove_pointer, what comes before the last `/` leads to the parent
+    match resolve_mut(value, &ptr[..ptr.rfind('/').unwrap()])? {
+        JSONValue::Object(map) => {
+            map.insert(token, new);
+            Ok(())
+        }
+        JSONValue::Array(arr) => {
+            let index = match token.as_str() {
+                "-" => arr.len(),
+                _ => index(&token, at)?,
+            };
+            if index > arr.len() {
+                return Err(out_of_bounds(index, arr.len(), at));
+            }
+            arr.insert(index, new);
+            Ok(())
+        }
+        scalar => Err(not_a_container(scalar.json_type().as_str(), at)),
+    }
+}
+
+/// Resolves `ptr` to a mutable reference inside `value`.
+pub(crate) fn resolve_mut<'a>(value: &'a mut JSONValue, ptr: &str) -> json::Result<&'a mut JSONValue> {
+    let mut curr = value;
+    for (token, at) in segments(ptr)? {
+        curr = match curr {
+            JSONValue::Object(map) => map.get_mut(&token).ok_or_else(|| missing_key(&token, at))?,
+            JSONValue::Array(arr) => {
+                let (index, len) = (index(&token, at)?, arr.len());
+                arr.get_mut(index).ok_or_else(|| out_of_bounds(index, len, at))?
+            }
+            scalar => return Err(not_a_container(scalar.json_type().as_str(), at)),
+        };
+    }
+    Ok(curr)
+}
+
+/// Resolves `ptr` to a reference inside `value`.
+pub(crate) fn resolve<'a>(value: &'a JSONValue, ptr: &str) -> json::Result<&'a JSONValue> {
+    let mut curr = value;
+    for (token, at) in segments(ptr)? {
+        curr = match curr {
+            JSONValue::Object(map) => map.get(&token).ok_or_else(|| missing_key(&token, at))?,
+            JSONValue::Array(arr) => {
+                let index = index(&token, at)?;
+                arr.get(index).ok_or_else(|| out_of_bounds(index, arr.len(), at))?
+            }
+            scalar => return Err(not_a_container(scalar.json_type().as_str(), at)),
+        };
+    }
+    Ok(curr)
+}
+
+// pairs each token of `ptr` with the part of `ptr` that ends with it, for error messages
+pub(crate) fn segments(ptr: &str) -> json::Result<Vec<(String, &str)>> {
+    let ends = ptr.match_indices('/').map(|(i, _)| i).skip(1).chain([ptr.len()]);
+    Ok(tokens(ptr)?.into_iter().zip(ends.map(|end| &ptr[..end])).collect())
+}
+
+// parses an array index, which RFC 6901 allows no sign or leading zeros in
+pub(crate) fn index(token: &str, at: &str) -> json::Result<usize> {
+    let canonical = token == "0" || (!token.starts_with('0') && !token.is_empty() && token.bytes().all(|b| b.is_ascii_digit()));
+    match token.parse() {
+        Ok(index) if canonical => Ok(index),
+        _ => Err(JSONError::ValueError(format!("invalid array index {:?} at {:?}", token, at))),
+    }
+}
+
+/// How far past the end of an array [`JSONValue::set_pointer`] may pad it with `null`, so an
+/// index from untrusted input can't make it allocate without bound
+pub const MAX_POINTER_PADDING: usize = 1 << 16;
+
+/// Checks, before anything is created, that no index along the way pads its array by more than
+/// `MAX_POINTER_PADDING`. Past the values that exist, every array is a new, empty one.
+fn check_padding(value: &JSONValue, segments: &[(String, &str)]) -> json::Result<()> {
+    let mut curr = Some(value);
+    for (token, at) in segments {
+        let len = match curr {
+            Some(JSONValue::Array(arr)) => arr.len(),
+            None | Some(JSONValue::Null) => 0,
+            // keys of objects are never indices, and scalars fail later on
+            Some(_) => {
+                curr = curr.and_then(|value| value.get_opt(token));
+                continue;
+            }
+        };
+        if let Ok(index) = index(token, at) {
+            if index > len + MAX_POINTER_PADDING {
+                return Err(JSONError::IndexError(format!(
+                    "index {} is more than {} past the end of length {} at {:?}",
+                    index, MAX_POINTER_PADDING, len, at
+                )));
+            }
+            curr = curr.and_then(JSONValue::as_array).and_then(|arr| arr.get(index));
+        } else {
+            curr = None;
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn missing_key(token: &str, at: &str) -> JSONError {
+    JSONError::KeyError(format!("key {} not found at {:?}", token, at))
+}
+
+pub(crate) fn out_of_bounds(index: usize, len: usize, at: &str) -> JSONError {
+    JSONError::IndexError(format!("index {} out of bounds for length {} at {:?}", index, len, at))
+}
+
+pub(crate) fn not_a_container(name: &str, at: &str) -> JSONError {
+    JSONError::ValueError(format!("expected object or array, found {:?} at {:?}", name, at))
+}
+
+/// Resolves `ptr` inside the document in `bytes` without parsing all of it: values before the
+/// match are checked but skipped without being built, and nothing after the match is read.
+///
+/// Errors are the same as those of looking `ptr` up in the parsed document, e.g. a `KeyError` for
+/// a missing key, as long as the document is valid up to the point where the lookup fails.
+pub fn get_path_raw(bytes: &[u8], ptr: &str) -> json::Result<JSONValue> {
+    let mut events = EventReader::new(Lexer::new(bytes));
+    // the first event of the value the pointer leads to so far
+    let mut first = next(&mut events, true)?;
+    for (token, at) in segments(ptr)? {
+        // the depth inside the container the token selects from
+        let depth = events.depth();
+        first = match first.0 {
+            Event::StartObject => loop {
+                match next(&mut events, true)?.0 {
+                    Event::Key(key) if key == token => break next(&mut events, true)?,
+                    Event::Key(_) => {
+                        next(&mut events, false)?;
+                        skip_to(&mut events, depth)?;
+                    }
+                    _ => return Err(missing_key(&token, at)),
+                }
+            },
+            Event::StartArray => {
+                let index = index(&token, at)?;
+                // counts the elements, so an index out of bounds can report the length
+                let mut len = 0;
+                loop {
+                    let event = next(&mut events, len == index)?;
+                    if event.0 == Event::EndArray {
+                        return Err(out_of_bounds(index, len, at));
+                    }
+                    if len == index {
+                        break event;
+                    }
+                    skip_to(&mut events, depth)?;
+                    len += 1;
+                }
+            }
+            scalar => {
+                let name = match scalar {
+                    Event::String(_) => "string",
+                    Event::Number(_) => "number",
+                    Event::Bool(_) => "boolean",
+                    _ => "null",
+                };
+                return Err(not_a_container(name, at));
+            }
+        };
+    }
+
+    // build the match from its first event on
+    let (event, position) = first;
+    let depth = match event {
+        Event::StartObject | Event::StartArray => events.depth() - 1,
+        _ => events.depth(),
+    };
+    let mut builder = TreeBuilder::default();
+    dispatch(&mut builder, event, position)?;
+    while events.depth() > depth {
+        let (event, position) = next(&mut events, true)?;
+        dispatch(&mut builder, event, position)?;
+    }
+    // the loop only ends once a whole value has been received
+    Ok(builder.into_value().unwrap())
+}
+
+/// Reads the next event, which the callers only ask for while the root value is incomplete.
+fn next(events: &mut EventReader<Lexer<'_>>, materialize: bool) -> json::Result<(Event, Position)> {
+    events.step(materialize)?
+        .ok_or_else(|| JSONError::ValueError("no value left in the document".to_string()))
+}
+
+/// Skips events until the containers opened since `depth` are closed again.
+fn skip_to(events: &mut EventReader<Lexer<'_>>, depth: usize) -> json::Result<()> {
+    while events.depth() > depth {
+        next(events, false)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,123 @@
+use std::fmt::Write;
+
+use crate::format::{write_string, FormatOptions};
+use crate::json::JSONValue;
+
+impl JSONValue {
+    /// Renders this value on one line for a log message, eliding whatever would make it much
+    /// longer than `max_len` characters or nest it deeper than `max_depth` arrays and objects:
+    /// - a string that is too long is cut short, followed by its full size, as in `"abcd…"(1.2kB)`,
+    /// - an array or object that is too long ends with a count of the rest, as in
+    ///   `[1, 2, 3, …97 more]`,
+    /// - an array or object nested too deep is written as `[…]` or `{…}`.
+    ///
+    /// The result is meant for people, and isn't JSON once anything has been elided.
+    pub fn preview(&self, max_len: usize, max_depth: usize) -> String {
+        preview(self, max_len, max_depth)
+    }
+}
+
+/// Renders `value` in about `budget` characters, with `depth` levels of arrays and objects left
+fn preview(value: &JSONValue, budget: usize, depth: usize) -> String {
+    match value {
+        JSONValue::String(s) => preview_string(s, budget),
+        JSONValue::Array(items) => preview_entries(['[', ']'], items.iter().map(|item| (None, item)), budget, depth),
+        JSONValue::Object(map) => preview_entries(['{', '}'], map.iter().map(|(key, value)| (Some(key), value)), budget, depth),
+        scalar => scalar.to_string_compact(),
+    }
+}
+
+/// Writes `s` as a string literal, cut short if it doesn't fit in `budget` characters. Only as
+/// much of `s` as could fit is escaped, and it is cut between characters, never inside one or
+/// inside an escape sequence.
+fn preview_string(s: &str, budget: usize) -> String {
+    let elided = format!("…\"({})", size(s.len()));
+    let room = budget.saturating_sub(elided.chars().count());
+    let mut out = String::from('"');
+    // the length of `out` in characters, and where to cut it if `s` turns out not to fit
+    let (mut len, mut cut) = (1, out.len());
+    let mut escaped = String::new();
+    for c in s.chars() {
+        escaped.clear();
+        let _ = write_string(&mut escaped, c.encode_utf8(&mut [0; 4]), &FormatOptions::compact());
+        let escaped = &escaped[1..escaped.len() - 1];
+        len += escaped.chars().count();
+        if len > budget {
+            out.truncate(cut);
+            out.push_str(&elided);
+            return out;
+        }
+        out.push_str(escaped);
+        if len <= room {
+            cut = out.len();
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Writes the items of an array or the entries of an object between `brackets`, leaving out those
+/// that don't fit in `budget` characters.
+fn preview_entries<'a>(
+    brackets: [char; 2],
+    entries: impl ExactSizeIterator<Item = (Option<&'a String>, &'a JSONValue)>,
+    budget: usize,
+    depth: usize,
+) -> String {
+    let [open, close] = brackets;
+    let count = entries.len();
+    if count == 0 {
+        return format!("{open}{close}");
+    }
+    if depth == 0 {
+        return format!("{open}…{close}");
+    }
+
+    let mut out = String::from(open);
+    let mut len = 1;
+    for (i, (key, value)) in entries.enumerate() {
+        let separator = if i == 0 { "" } else { ", " };
+        let left = count - i;
+        // keep room for what has to follow this entry: the closing bracket, and the count of the
+        // rest if they don't fit
+        let after = match left {
+            1 => 1,
+            _ => format!(", …{} more", left - 1).chars().count() + 1,
+        };
+        let room = budget.saturating_sub(len + separator.len() + after);
+        let entry = match key {
+            None => preview(value, room, depth - 1),
+            Some(key) => {
+                // a long key leaves half the room for its value
+                let key = preview_string(key, room / 2);
+                let value = preview(value, room.saturating_sub(key.chars().count() + 2), depth - 1);
+                format!("{key}: {value}")
+            }
+        };
+        let entry_len = entry.chars().count();
+        if entry_len > room {
+            let _ = write!(out, "{separator}…{left} more");
+            break;
+        }
+        out.push_str(separator);
+        out.push_str(&entry);
+        len += separator.len() + entry_len;
+    }
+    out.push(close);
+    out
+}
+
+/// A size in bytes for people to read, like `512B` or `1.2kB`
+fn size(bytes: usize) -> String {
+    if bytes < 1000 {
+        return format!("{bytes}B");
+    }
+    let mut size = bytes as f64 / 1000.0;
+    for unit in ["kB", "MB", "GB"] {
+        if size < 999.95 {
+            return format!("{size:.1}{unit}");
+        }
+        size /= 1000.0;
+    }
+    format!("{size:.1}TB")
+}
@@ -0,0 +1,49 @@
+use crate::json::{self, JSONValue};
+use crate::lexer::{Lexer, TokenPos};
+use crate::parser::Parser;
+
+/// # Session
+///
+/// A reusable parsing pipeline for applications that parse many documents in a row, lexing each
+/// one in full before parsing it. A `Session` keeps its token vector between calls, so after the
+/// first few documents it only allocates for the values it returns.
+///
+/// Each call to [`Session::parse`] is independent of the previous ones.
+pub struct Session {
+    /// Always empty between calls; only its allocation is kept
+    tokens: Vec<TokenPos<'static>>,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Self {
+            tokens: vec![],
+        }
+    }
+
+    /// Parses `bytes` into a `JSONValue`, as `JSONValue::try_from` would. The only difference is
+    /// in which error is reported when there are several: lexer errors come before syntax errors.
+    pub fn parse(&mut self, bytes: &[u8]) -> json::Result<JSONValue> {
+        let mut tokens = recycle(std::mem::take(&mut self.tokens));
+        let result = match Lexer::new(bytes).tokenify_into(&mut tokens) {
+            Ok(()) => Parser::new(tokens.drain(..).map(Ok)).parse_document(),
+            Err(err) => Err(err),
+        };
+        self.tokens = recycle(tokens);
+        result
+    }
+}
+
+/// Empties `tokens` and hands its allocation over to tokens that borrow from another buffer.
+fn recycle<'b>(mut tokens: Vec<TokenPos<'_>>) -> Vec<TokenPos<'b>> {
+    tokens.clear();
+    // the element types differ only in their lifetime, so collecting reuses the allocation in
+    // place; `session_test` checks that no allocation is made here
+    tokens.into_iter().map(|_| unreachable!("the vector is empty")).collect()
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self::new()
+    }
+}
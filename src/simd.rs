@@ -0,0 +1,115 @@
+use crate::lexer::scalar;
+
+// Each scan looks at 16 bytes at a time with SSE2 where the CPU has it, and finishes the last
+// partial block, or the whole buffer on other CPUs, with the scalar loop.
+
+/// The length of the run of whitespace at the start of `bytes`.
+pub(crate) fn whitespace_run(bytes: &[u8]) -> usize {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    if is_x86_feature_detected!("sse2") {
+        // SAFETY: SSE2 is available, as just checked
+        return unsafe { sse2::whitespace_run(bytes) };
+    }
+    scalar::whitespace_run(bytes)
+}
+
+/// The length of the run of ASCII digits at the start of `bytes`.
+pub(crate) fn digit_run(bytes: &[u8]) -> usize {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    if is_x86_feature_detected!("sse2") {
+        // SAFETY: SSE2 is available, as just checked
+        return unsafe { sse2::digit_run(bytes) };
+    }
+    scalar::digit_run(bytes)
+}
+
+/// The length of the run of bytes at the start of `bytes` that can appear in a string literal
+/// as they are, i.e. anything but a quote, a backslash or a control character.
+pub(crate) fn plain_string_run(bytes: &[u8]) -> usize {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    if is_x86_feature_detected!("sse2") {
+        // SAFETY: SSE2 is available, as just checked
+        return unsafe { sse2::plain_string_run(bytes) };
+    }
+    scalar::plain_string_run(bytes)
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+mod sse2 {
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::*;
+
+    use crate::lexer::scalar;
+
+    const BLOCK: usize = 16;
+
+    /// Calls `stop` on each whole block of `bytes`, which returns a mask with a bit set for each
+    /// byte that ends the run, and returns the length of the run.
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    unsafe fn run(bytes: &[u8], stop: impl Fn(__m128i) -> i32, rest: fn(&[u8]) -> usize) -> usize {
+        let mut i = 0;
+        while i + BLOCK <= bytes.len() {
+            // SAFETY: the block lies within `bytes`, and unaligned loads are allowed
+            let block = unsafe { _mm_loadu_si128(bytes.as_ptr().add(i) as *const __m128i) };
+            let mask = stop(block);
+            if mask != 0 {
+                return i + mask.trailing_zeros() as usize;
+            }
+            i += BLOCK;
+        }
+        i + rest(&bytes[i..])
+    }
+
+    /// A mask with a bit set for each byte of `block` that is at most `max`, comparing unsigned
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    fn at_most(block: __m128i, max: u8) -> __m128i {
+        _mm_cmpeq_epi8(_mm_min_epu8(block, _mm_set1_epi8(max as i8)), block)
+    }
+
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    fn equals(block: __m128i, byte: u8) -> __m128i {
+        _mm_cmpeq_epi8(block, _mm_set1_epi8(byte as i8))
+    }
+
+    #[target_feature(enable = "sse2")]
+    pub(super) unsafe fn whitespace_run(bytes: &[u8]) -> usize {
+        let stop = |block| {
+            let space = _mm_or_si128(
+                _mm_or_si128(equals(block, b' '), equals(block, b'\t')),
+                _mm_or_si128(equals(block, b'\n'), equals(block, b'\r')),
+            );
+            !_mm_movemask_epi8(space) & 0xFFFF
+        };
+        // SAFETY: the caller checked that SSE2 is available
+        unsafe { run(bytes, stop, scalar::whitespace_run) }
+    }
+
+    #[target_feature(enable = "sse2")]
+    pub(super) unsafe fn digit_run(bytes: &[u8]) -> usize {
+        let stop = |block| {
+            // digits are the bytes that are at most 9 once '0' is subtracted, wrapping around
+            let digits = at_most(_mm_sub_epi8(block, _mm_set1_epi8(b'0' as i8)), 9);
+            !_mm_movemask_epi8(digits) & 0xFFFF
+        };
+        // SAFETY: the caller checked that SSE2 is available
+        unsafe { run(bytes, stop, scalar::digit_run) }
+    }
+
+    #[target_feature(enable = "sse2")]
+    pub(super) unsafe fn plain_string_run(bytes: &[u8]) -> usize {
+        let stop = |block| {
+            let special = _mm_or_si128(
+                _mm_or_si128(equals(block, b'"'), equals(block, b'\\')),
+                at_most(block, 0x1F),
+            );
+            _mm_movemask_epi8(special)
+        };
+        // SAFETY: the caller checked that SSE2 is available
+        unsafe { run(bytes, stop, scalar::plain_string_run) }
+    }
+}
@@ -0,0 +1,334 @@
+use std::io::{BufRead, ErrorKind, Read};
+
+use crate::events::{self, EventMachine, TreeBuilder};
+use crate::json::{self, JSONValue, Position};
+use crate::parser::Parser;
+use crate::lexer::{Lexer, TokenPos, BOM};
+
+/// Number of bytes requested from the reader at a time
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// Lexes JSON text from an `io::Read` a chunk at a time. Only bytes that haven't been lexed yet
+/// are kept in memory, and a token that straddles chunks is lexed once the rest of it has been
+/// read.
+pub(crate) struct ReaderTokens<R: Read> {
+    reader: R,
+    /// Bytes read from the reader, the first `consumed` of which have already been lexed
+    buffer: Vec<u8>,
+    consumed: usize,
+    /// Position of `buffer[consumed]` in the whole input
+    start: Position,
+    /// Whether the start of the input has been checked for a byte order mark
+    started: bool,
+    eof: bool,
+    /// Set once an error has been returned
+    failed: bool,
+}
+
+impl<R: Read> ReaderTokens<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buffer: vec![],
+            consumed: 0,
+            start: Position { line: 1, column: 1, offset: 0 },
+            started: false,
+            eof: false,
+            failed: false,
+        }
+    }
+
+    /// Reads onto the end of the buffer, dropping the bytes already lexed. At least a chunk is
+    /// read, or as many bytes as are still buffered if that is more, so a token that straddles
+    /// many chunks is lexed again only each time its bytes double. Fewer bytes are read only at
+    /// the end of the input.
+    fn refill(&mut self) -> json::Result<()> {
+        self.buffer.drain(..self.consumed);
+        self.consumed = 0;
+
+        let len = self.buffer.len();
+        let want = CHUNK_SIZE.max(len);
+        self.buffer.resize(len + want, 0);
+        let mut filled = len;
+        while filled < len + want {
+            match self.reader.read(&mut self.buffer[filled..]) {
+                Ok(0) => {
+                    self.eof = true;
+                    break;
+                }
+                Ok(read) => filled += read,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    self.buffer.truncate(filled);
+                    return Err(e.into());
+                }
+            }
+        }
+        self.buffer.truncate(filled);
+
+        Ok(())
+    }
+
+    fn next_token(&mut self) -> json::Result<Option<TokenPos<'static>>> {
+        if !self.started {
+            // a byte order mark can only be recognized once all of its bytes have been read
+            while self.buffer.len() < BOM.len() && !self.eof {
+                self.refill()?;
+            }
+            if self.buffer.starts_with(&BOM) {
+                self.consumed = BOM.len();
+                self.start.offset = BOM.len();
+            }
+            self.started = true;
+        }
+
+        loop {
+            let mut lexer = Lexer::resume(&self.buffer[self.consumed..], self.start, !self.eof);
+            let token = lexer.next_token()?;
+            // whitespace before the token, or before the end of the chunk, is consumed either way
+            self.consumed += lexer.offset() - self.start.offset;
+            self.start = lexer.current_position();
+
+            if token.is_some() || self.eof {
+                // the chunk the token was read from is about to be dropped
+                return Ok(token.map(TokenPos::into_owned));
+            }
+            self.refill()?;
+        }
+    }
+}
+
+/// Lazily produces the tokens read from the reader. After an error, the iterator ends.
+impl<R: Read> Iterator for ReaderTokens<R> {
+    type Item = json::Result<TokenPos<'static>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let next = self.next_token().transpose();
+        self.failed = matches!(next, Some(Err(_)));
+        next
+    }
+}
+
+/// Reads the bytes of an iterator, as many as fit at a time.
+pub(crate) struct IterReader<I: Iterator<Item = u8>>(pub I);
+
+impl<I: Iterator<Item = u8>> Read for IterReader<I> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        // `buf` comes first, so no byte is taken from the iterator once it is full
+        let mut len = 0;
+        for (slot, byte) in buf.iter_mut().zip(&mut self.0) {
+            *slot = byte;
+            len += 1;
+        }
+        Ok(len)
+    }
+}
+
+/// # StreamParser
+///
+/// A push parser for JSON text that arrives in pieces, e.g. from a socket. Bytes are handed over
+/// with [`StreamParser::feed`] in chunks of any size; tokens split between chunks, like half a
+/// `true` or a string cut off in the middle of an escape, are completed by later chunks.
+///
+/// Only the bytes of a token that hasn't been completed yet are buffered, along with the value
+/// being built.
+pub struct StreamParser {
+    /// Bytes fed but not lexed yet
+    buffer: Vec<u8>,
+    /// Position of `buffer[0]` in the whole stream
+    start: Position,
+    /// Whether the start of the stream has been checked for a byte order mark
+    started: bool,
+    machine: EventMachine,
+    builder: TreeBuilder,
+}
+
+impl StreamParser {
+    pub fn new() -> Self {
+        let start = Position { line: 1, column: 1, offset: 0 };
+        Self {
+            buffer: vec![],
+            start,
+            started: false,
+            machine: EventMachine::new(start),
+            builder: TreeBuilder::default(),
+        }
+    }
+
+    /// Parses the next chunk of the stream.
+    ///
+    /// Returns `Ok(Some(value))` as soon as the top-level value is complete. Anything fed after it
+    /// is kept as the start of the next document, which the following calls to `feed` (possibly
+    /// with an empty chunk) continue to parse. A top-level number or keyword could always be
+    /// continued by the next chunk, so it is only returned once something follows it or by
+    /// [`StreamParser::finish`].
+    ///
+    /// Errors are reported as soon as the chunk that causes them is fed, at their position in
+    /// the whole stream. The parser should not be used after an error.
+    pub fn feed(&mut self, bytes: &[u8]) -> json::Result<Option<JSONValue>> {
+        self.buffer.extend_from_slice(bytes);
+        self.parse_buffer(true)
+    }
+
+    /// Ends the stream, returning the top-level value that is still being parsed.
+    ///
+    /// Returns:
+    /// - `Err(UnexpectedEof)` if the stream ends in the middle of a value, or holds no value at all,
+    /// - any other error `feed` would return for the rest of the stream,
+    /// - `Ok(JSONValue)` with the parsed document otherwise.
+    pub fn finish(mut self) -> json::Result<JSONValue> {
+        match self.parse_buffer(false)? {
+            Some(value) => Ok(value),
+            None => Err(self.machine.eof_error()),
+        }
+    }
+
+    fn parse_buffer(&mut self, partial: bool) -> json::Result<Option<JSONValue>> {
+        if !self.started {
+            // a byte order mark can only be recognized once all of its bytes have arrived
+            if partial && self.buffer.len() < BOM.len() && BOM.starts_with(&self.buffer) {
+                return Ok(None);
+            }
+            if self.buffer.starts_with(&BOM) {
+                self.buffer.drain(..BOM.len());
+                self.start.offset = BOM.len();
+            }
+            self.started = true;
+        }
+
+        let mut lexer = Lexer::resume(&self.buffer, self.start, partial);
+        let mut value = None;
+        while let Some(token) = lexer.next_token()? {
+            if let Some((event, position)) = self.machine.push(token, true)? {
+                events::dispatch(&mut self.builder, event, position)?;
+            }
+            if self.machine.is_done() {
+                value = std::mem::take(&mut self.builder).into_value();
+                break;
+            }
+        }
+
+        let consumed = lexer.offset() - self.start.offset;
+        self.start = lexer.current_position();
+        self.buffer.drain(..consumed);
+        if value.is_some() {
+            self.machine = EventMachine::new(self.start);
+        }
+        Ok(value)
+    }
+}
+
+impl Default for StreamParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Parses newline-delimited JSON (JSON Lines) from `reader`: one document per line, each parsed
+/// independently of the others. Lines holding only whitespace are skipped. Only one line is held
+/// in memory at a time.
+///
+/// Errors carry their position in the whole input, so their line number is the line of the
+/// record that failed. A malformed record doesn't stop the iterator; the lines after it are still
+/// parsed. An error reading from `reader` ends the iterator.
+pub fn lines<R: BufRead>(mut reader: R) -> impl Iterator<Item = json::Result<JSONValue>> {
+    let mut line = vec![];
+    let mut number = 0;
+    let mut offset = 0;
+    let mut failed = false;
+    std::iter::from_fn(move || {
+        while !failed {
+            line.clear();
+            let read = match reader.read_until(b'\n', &mut line) {
+                Ok(0) => return None,
+                Ok(read) => read,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    failed = true;
+                    return Some(Err(e.into()));
+                }
+            };
+            number += 1;
+            offset += read;
+            if let Some(result) = parse_line(&line, number, offset - read) {
+                return Some(result);
+            }
+        }
+        None
+    })
+}
+
+/// Like [`lines`], but reads the records straight out of a buffer without copying them.
+pub fn lines_from_slice(bytes: &[u8]) -> impl Iterator<Item = json::Result<JSONValue>> + '_ {
+    let mut offset = 0;
+    bytes.split(|&b| b == b'\n').enumerate().filter_map(move |(i, line)| {
+        let start = offset;
+        offset += line.len() + 1;
+        parse_line(line, i + 1, start)
+    })
+}
+
+/// Parses line `number` of a JSON Lines input, which starts at byte `offset`. Returns `None` for
+/// a blank line.
+fn parse_line(line: &[u8], number: usize, offset: usize) -> Option<json::Result<JSONValue>> {
+    // the byte order mark of a file sits before its first record
+    let (line, offset) = match line.strip_prefix(&BOM) {
+        Some(rest) if offset == 0 => (rest, BOM.len()),
+        _ => (line, offset),
+    };
+    if line.iter().all(|b| b" \t\r\n".contains(b)) {
+        return None;
+    }
+    let lexer = Lexer::resume(line, Position { line: number, column: 1, offset }, false);
+    Some(Parser::new(lexer).parse_document())
+}
+
+/// # Deserializer
+///
+/// Reads a stream of JSON documents written back to back, like `{"a":1}{"a":2}[3]`, with or
+/// without whitespace between them. Each item of the iterator consumes exactly one top-level
+/// value along with the whitespace after it. After an error, the iterator ends.
+pub struct Deserializer<'a> {
+    lexer: Lexer<'a>,
+    failed: bool,
+}
+
+impl<'a> Deserializer<'a> {
+    pub fn from_slice(bytes: &'a [u8]) -> Self {
+        Self {
+            lexer: Lexer::new(bytes),
+            failed: false,
+        }
+    }
+
+    /// The number of bytes of the input consumed so far.
+    pub fn byte_offset(&self) -> usize {
+        self.lexer.offset()
+    }
+
+    fn next_value(&mut self) -> json::Result<Option<JSONValue>> {
+        self.lexer.skip_whitespace()?;
+        if self.lexer.is_at_end() {
+            return Ok(None);
+        }
+        let value = Parser::new(self.lexer.by_ref()).parse()?;
+        self.lexer.skip_whitespace()?;
+        Ok(Some(value))
+    }
+}
+
+impl Iterator for Deserializer<'_> {
+    type Item = json::Result<JSONValue>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let next = self.next_value().transpose();
+        self.failed = matches!(next, Some(Err(_)));
+        next
+    }
+}
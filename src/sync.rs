@@ -0,0 +1,65 @@
+use std::sync::RwLock;
+
+use crate::json::{self, JSONError, JSONValue};
+use crate::pointer;
+
+/// # SyncJson
+///
+/// A JSON document that can be shared between threads and edited in place. Every access names the
+/// part of the document it touches with a JSON pointer (e.g. `/workers/3/count`), so the locking
+/// granularity is an implementation detail: currently one lock guards the whole document.
+///
+/// If a thread panics while holding the lock, later calls return [`JSONError::PoisonError`]
+/// instead of panicking themselves.
+#[derive(Debug)]
+pub struct SyncJson {
+    document: RwLock<JSONValue>,
+}
+
+impl SyncJson {
+    pub fn new(document: JSONValue) -> Self {
+        Self { document: RwLock::new(document) }
+    }
+
+    /// Calls `f` with a reference to the value at `ptr`, holding a read lock for the duration.
+    ///
+    /// Returns:
+    /// - `Err(PoisonError)` if another thread panicked while holding the lock,
+    /// - the errors of [`JSONValue::pointer`] if `ptr` does not resolve,
+    /// - `Ok` with the result of `f` otherwise.
+    pub fn with_pointer<R>(&self, ptr: &str, f: impl FnOnce(&JSONValue) -> R) -> json::Result<R> {
+        let document = self.document.read().map_err(|_| poisoned())?;
+        Ok(f(pointer::resolve(&document, ptr)?))
+    }
+
+    /// Calls `f` with a mutable reference to the value at `ptr`, holding a write lock for the
+    /// duration.
+    ///
+    /// Returns:
+    /// - `Err(PoisonError)` if another thread panicked while holding the lock,
+    /// - the errors of [`JSONValue::pointer`] if `ptr` does not resolve,
+    /// - `Ok` with the result of `f` otherwise.
+    pub fn with_pointer_mut<R>(&self, ptr: &str, f: impl FnOnce(&mut JSONValue) -> R) -> json::Result<R> {
+        let mut document = self.document.write().map_err(|_| poisoned())?;
+        Ok(f(pointer::resolve_mut(&mut document, ptr)?))
+    }
+
+    /// Returns a clone of the value at `ptr`, taken under the read lock.
+    pub fn get_cloned(&self, ptr: &str) -> json::Result<JSONValue> {
+        self.with_pointer(ptr, JSONValue::clone)
+    }
+
+    /// Returns a consistent copy of the whole document, taken under the read lock.
+    pub fn snapshot(&self) -> json::Result<JSONValue> {
+        self.get_cloned("")
+    }
+
+    /// Unwraps the document.
+    pub fn into_inner(self) -> json::Result<JSONValue> {
+        self.document.into_inner().map_err(|_| poisoned())
+    }
+}
+
+fn poisoned() -> JSONError {
+    JSONError::PoisonError("a thread panicked while holding the document lock".to_string())
+}
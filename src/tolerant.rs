@@ -0,0 +1,377 @@
+use std::iter::Peekable;
+
+use crate::json::{self, JSONError, JSONValue, Map, Position};
+use crate::lexer::{Lexer, Token, TokenPos};
+use crate::options::DEFAULT_MAX_DEPTH;
+use crate::parser::number;
+
+/// Parses `bytes` as far as possible even if it is broken or unfinished, e.g. a document in the
+/// middle of being edited. Returns the best-effort value along with every problem found, in the
+/// order they appear in the text; when there are none, the value is exactly what
+/// `JSONValue::try_from` would return.
+///
+/// Everything that parses cleanly ends up in the value. To get past the rest:
+/// - arrays and objects still open at the end of the input are closed, and a closing bracket or
+///   brace also closes any containers opened after its own that are missing theirs,
+/// - a missing value, e.g. in `[1, , 2]` or `{"a": }`, becomes `Null`,
+/// - a token that can't be lexed becomes `Null` where a value was expected, and is skipped
+///   anywhere else, along with the rest of the entry when it stands in for an object key,
+/// - an unterminated string ends at its line break, or at the end of the input.
+pub fn parse_tolerant(bytes: &[u8]) -> (JSONValue, Vec<JSONError>) {
+    let mut lexer = Lexer::new(bytes);
+    let mut tokens = vec![];
+    let mut errors = vec![];
+    loop {
+        match lexer.next_token() {
+            Ok(Some(token)) => tokens.push(Ok(token)),
+            Ok(None) => break,
+            // a broken string keeps its place in the document, with whatever it contains
+            Err(err) => match lexer.recover() {
+                Some(token) => {
+                    errors.push(err);
+                    tokens.push(Ok(token));
+                }
+                None => tokens.push(Err(err)),
+            },
+        }
+    }
+
+    let mut parser = Recovery {
+        eof_reported: errors.iter().any(|err| matches!(err, JSONError::UnexpectedEof { .. })),
+        tokens: tokens.into_iter().peekable(),
+        errors,
+        end: lexer.current_position(),
+        closers: vec![],
+    };
+    let value = parser.value();
+    match parser.peek() {
+        Next::End => {}
+        Next::Invalid => parser.skip_invalid(),
+        Next::Token(token, position) => parser.syntax_error(
+            format!("unexpected token `{:?}` after the end of the document", token),
+            position,
+        ),
+    }
+
+    let mut errors = parser.errors;
+    errors.sort_by_key(|err| err.position().map(|position| position.offset));
+    (value, errors)
+}
+
+/// A look at the next token, without taking it
+enum Next<'a> {
+    End,
+    /// Text the lexer couldn't make a token of
+    Invalid,
+    Token(Token<'a>, Position),
+}
+
+/// A parser that records errors and carries on instead of stopping at the first one
+struct Recovery<'a> {
+    tokens: Peekable<std::vec::IntoIter<json::Result<TokenPos<'a>>>>,
+    errors: Vec<JSONError>,
+    /// Position of the end of the input, where end of input errors point
+    end: Position,
+    /// Only the first end of input error is reported, since every container still open runs
+    /// into the same one
+    eof_reported: bool,
+    /// The closing token of each container that is open, innermost last
+    closers: Vec<Token<'static>>,
+}
+
+impl<'a> Recovery<'a> {
+    fn peek(&mut self) -> Next<'a> {
+        match self.tokens.peek() {
+            None => Next::End,
+            Some(Err(_)) => Next::Invalid,
+            Some(Ok(TokenPos(token, position, _))) => Next::Token(token.clone(), *position),
+        }
+    }
+
+    fn report(&mut self, err: JSONError) {
+        if matches!(err, JSONError::UnexpectedEof { .. }) {
+            if self.eof_reported {
+                return;
+            }
+            self.eof_reported = true;
+        }
+        self.errors.push(err);
+    }
+
+    fn syntax_error(&mut self, message: String, position: Position) {
+        self.report(JSONError::SyntaxError { message, position });
+    }
+
+    fn eof_error(&mut self, expected: &str) {
+        self.report(JSONError::UnexpectedEof {
+            message: format!("unexpected end of input, expected {}", expected),
+            position: self.end,
+        });
+    }
+
+    /// Takes the next token, which the lexer failed on, and reports its error
+    fn skip_invalid(&mut self) {
+        if let Some(Err(err)) = self.tokens.next() {
+            self.report(err);
+        }
+    }
+
+    /// Whether `closer` ends a container enclosing the innermost one, which must then be missing
+    /// its own closing token
+    fn closes_outer(&self, closer: &Token) -> bool {
+        self.closers.contains(closer)
+    }
+
+    /// Skips tokens up to the next comma or closing token outside of any container opened along
+    /// the way, taking the comma if that is where it stops
+    fn skip_entry(&mut self) {
+        let mut depth = 0;
+        loop {
+            match self.peek() {
+                Next::End => return,
+                Next::Invalid => self.skip_invalid(),
+                Next::Token(Token::Comma, _) if depth == 0 => {
+                    self.tokens.next();
+                    return;
+                }
+                Next::Token(Token::CloseBrace | Token::CloseBracket, _) if depth == 0 => return,
+                Next::Token(token, _) => {
+                    match token {
+                        Token::OpenBrace | Token::OpenBracket => depth += 1,
+                        Token::CloseBrace | Token::CloseBracket => depth -= 1,
+                        _ => {}
+                    }
+                    self.tokens.next();
+                }
+            }
+        }
+    }
+
+    /// Skips the container opening at the next token, including everything nested inside it
+    fn skip_container(&mut self) {
+        let mut depth = 0;
+        while let Some(next) = self.tokens.next() {
+            match next {
+                Err(err) => self.report(err),
+                Ok(TokenPos(Token::OpenBrace | Token::OpenBracket, ..)) => depth += 1,
+                Ok(TokenPos(Token::CloseBrace | Token::CloseBracket, ..)) => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return;
+                    }
+                }
+                Ok(_) => {}
+            }
+        }
+    }
+
+    /// Parses the value starting at the next token. Missing and unreadable values become `Null`.
+    fn value(&mut self) -> JSONValue {
+        loop {
+            let (token, position) = match self.peek() {
+                Next::End => {
+                    self.eof_error("a value");
+                    return JSONValue::Null;
+                }
+                Next::Invalid => {
+                    self.skip_invalid();
+                    return JSONValue::Null;
+                }
+                Next::Token(token, position) => (token, position),
+            };
+            return match token {
+                Token::OpenBrace | Token::OpenBracket if self.closers.len() >= DEFAULT_MAX_DEPTH => {
+                    self.syntax_error(
+                        format!("arrays and objects nested deeper than the maximum depth of {}", DEFAULT_MAX_DEPTH),
+                        position,
+                    );
+                    self.skip_container();
+                    JSONValue::Null
+                }
+                Token::OpenBrace => self.object(),
+                Token::OpenBracket => self.array(),
+                // the value is missing; the token is left for the container to deal with
+                Token::Comma | Token::CloseBrace | Token::CloseBracket => {
+                    self.syntax_error(format!("expected a value, found {:?}", token), position);
+                    JSONValue::Null
+                }
+                Token::Colon => {
+                    self.syntax_error("unexpected token `Colon`".to_string(), position);
+                    self.tokens.next();
+                    continue;
+                }
+                Token::StringLiteral(text) => {
+                    self.tokens.next();
+                    JSONValue::String(text.into_owned())
+                }
+                Token::NumericLiteral(text) => {
+                    self.tokens.next();
+                    match number(&text, position) {
+                        Ok(number) => JSONValue::Number(number),
+                        Err(err) => {
+                            self.report(err);
+                            JSONValue::Null
+                        }
+                    }
+                }
+                Token::True | Token::False | Token::Null => {
+                    self.tokens.next();
+                    match token {
+                        Token::True => JSONValue::Bool(true),
+                        Token::False => JSONValue::Bool(false),
+                        _ => JSONValue::Null,
+                    }
+                }
+                Token::Unknown(text) => {
+                    self.tokens.next();
+                    self.syntax_error(format!("unexpected token `{text}`"), position);
+                    JSONValue::Null
+                }
+            };
+        }
+    }
+
+    /// Reads what follows an element of the innermost container, taking the comma or closing
+    /// token if that is what comes next. Returns whether the container ended.
+    fn element_end(&mut self) -> bool {
+        let close = self.closers.last().cloned().unwrap();
+        loop {
+            return match self.peek() {
+                Next::End => {
+                    self.eof_error(&format!("Comma or {:?}", close));
+                    true
+                }
+                Next::Token(Token::Comma, position) => {
+                    self.tokens.next();
+                    if matches!(self.peek(), Next::Token(token, _) if token == close) {
+                        self.syntax_error(format!("trailing comma before {:?}", close), position);
+                        self.tokens.next();
+                        return true;
+                    }
+                    false
+                }
+                Next::Token(token, _) if token == close => {
+                    self.tokens.next();
+                    true
+                }
+                Next::Token(token @ (Token::CloseBrace | Token::CloseBracket), position) => {
+                    self.syntax_error(format!("expected Comma or {:?}, found {:?}", close, token), position);
+                    // a closing token that belongs further out closes this container too, and
+                    // a stray one is dropped
+                    if self.closes_outer(&token) {
+                        return true;
+                    }
+                    self.tokens.next();
+                    continue;
+                }
+                // an unreadable token gets reported as the next element
+                Next::Invalid => false,
+                // most likely a missing comma, so carry on with the next element
+                Next::Token(token, position) => {
+                    self.syntax_error(format!("expected Comma, found {:?}", token), position);
+                    false
+                }
+            };
+        }
+    }
+
+    fn array(&mut self) -> JSONValue {
+        self.tokens.next();
+        self.closers.push(Token::CloseBracket);
+        let mut items = vec![];
+        loop {
+            match self.peek() {
+                Next::End => {
+                    self.eof_error(if items.is_empty() { "a value or CloseBracket" } else { "a value" });
+                    break;
+                }
+                Next::Token(Token::CloseBracket, _) if items.is_empty() => {
+                    self.tokens.next();
+                    break;
+                }
+                Next::Token(Token::CloseBrace, position) if items.is_empty() => {
+                    self.syntax_error("expected a value or CloseBracket, found CloseBrace".to_string(), position);
+                    if self.closes_outer(&Token::CloseBrace) {
+                        break;
+                    }
+                    self.tokens.next();
+                    continue;
+                }
+                _ => items.push(self.value()),
+            }
+            if self.element_end() {
+                break;
+            }
+        }
+        self.closers.pop();
+        JSONValue::Array(items)
+    }
+
+    fn object(&mut self) -> JSONValue {
+        self.tokens.next();
+        self.closers.push(Token::CloseBrace);
+        let mut entries = Map::new();
+        loop {
+            let key = match self.peek() {
+                Next::End => {
+                    self.eof_error(if entries.is_empty() { "a key or CloseBrace" } else { "a key" });
+                    break;
+                }
+                // the object is empty, or ends after a skipped entry
+                Next::Token(Token::CloseBrace, _) => {
+                    self.tokens.next();
+                    break;
+                }
+                Next::Token(Token::CloseBracket, position) => {
+                    self.syntax_error("expected a key or CloseBrace, found CloseBracket".to_string(), position);
+                    if self.closes_outer(&Token::CloseBracket) {
+                        break;
+                    }
+                    self.tokens.next();
+                    continue;
+                }
+                Next::Token(Token::StringLiteral(text), _) => {
+                    self.tokens.next();
+                    text.into_owned()
+                }
+                Next::Invalid => {
+                    self.skip_entry();
+                    continue;
+                }
+                Next::Token(other, position) => {
+                    self.syntax_error(format!("expected string literal, found {:?}", other), position);
+                    self.skip_entry();
+                    continue;
+                }
+            };
+
+            let value = match self.peek() {
+                Next::Token(Token::Colon, _) => {
+                    self.tokens.next();
+                    self.value()
+                }
+                Next::End => {
+                    self.eof_error("Colon");
+                    JSONValue::Null
+                }
+                // neither the colon nor the value is there
+                Next::Token(token @ (Token::Comma | Token::CloseBrace | Token::CloseBracket), position) => {
+                    self.syntax_error(format!("expected Colon, found {:?}", token), position);
+                    JSONValue::Null
+                }
+                Next::Invalid => self.value(),
+                Next::Token(token, position) => {
+                    self.syntax_error(format!("expected Colon, found {:?}", token), position);
+                    self.value()
+                }
+            };
+            entries.insert(key, value);
+
+            if self.element_end() {
+                break;
+            }
+        }
+        self.closers.pop();
+        JSONValue::Object(entries)
+    }
+}
@@ -0,0 +1,194 @@
+use std::io::Write;
+
+use crate::format::{indent, write_string, write_value, Failure, FormatOptions, IoSink};
+use crate::json::{self, JSONError, JSONValue};
+
+/// # Writer
+///
+/// Writes a JSON document piece by piece as it is produced, so a large one never has to be built
+/// as a `JSONValue` first. Containers are opened and closed with `begin_*` and `end_*` calls, and
+/// each value in an object follows its [`Writer::key`]. [`Writer::finish`] checks that the
+/// document is complete.
+///
+/// Calls out of sequence, like a value in an object with no key before it or an `end_*` call
+/// that doesn't match the open container, return `Err(ValueError)` and write nothing. A failed
+/// write returns `Err(IoError)` and a NaN or infinite number `Err(ValueError)`; either may leave
+/// part of a value written. Wrapping the underlying writer in a `BufWriter` is recommended.
+pub struct Writer<W: Write> {
+    writer: W,
+    opts: FormatOptions,
+    /// The containers that are open, innermost last
+    stack: Vec<Frame>,
+    /// Whether the top level value is complete
+    done: bool,
+}
+
+/// An open container
+struct Frame {
+    object: bool,
+    /// Number of elements or entries so far
+    len: usize,
+    /// Whether a key is waiting for its value
+    key: bool,
+}
+
+impl<W: Write> Writer<W> {
+    /// A writer of compact JSON, with no whitespace between tokens.
+    pub fn new(writer: W) -> Self {
+        Self::with_options(writer, FormatOptions::compact())
+    }
+
+    /// A writer that lays out the document according to `opts`, e.g. `FormatOptions::default()`
+    /// for the layout of `to_string()`.
+    pub fn with_options(writer: W, opts: FormatOptions) -> Self {
+        Self { writer, opts, stack: vec![], done: false }
+    }
+
+    pub fn begin_object(&mut self) -> json::Result<()> {
+        self.begin(true)
+    }
+
+    pub fn begin_array(&mut self) -> json::Result<()> {
+        self.begin(false)
+    }
+
+    pub fn end_object(&mut self) -> json::Result<()> {
+        self.end(true)
+    }
+
+    pub fn end_array(&mut self) -> json::Result<()> {
+        self.end(false)
+    }
+
+    /// Writes the key of the next entry of the innermost object.
+    pub fn key(&mut self, key: &str) -> json::Result<()> {
+        match self.stack.last() {
+            Some(Frame { object: true, key: false, .. }) => {}
+            Some(Frame { object: true, key: true, .. }) => return Err(sequence_error("expected a value after the key, found another key")),
+            _ => return Err(sequence_error("a key can only be written inside an object")),
+        }
+        self.element_start()?;
+        let mut sink = IoSink::new(&mut self.writer);
+        let result = write_string(&mut sink, key, &self.opts).map_err(Failure::from);
+        sink.check(result)?;
+        self.writer.write_all(if self.opts.space_after_colon { b": " } else { b":" })?;
+        if let Some(frame) = self.stack.last_mut() {
+            frame.key = true;
+        }
+        Ok(())
+    }
+
+    /// Writes `value` in full, laid out like the rest of the document.
+    pub fn value(&mut self, value: &JSONValue) -> json::Result<()> {
+        self.value_start()?;
+        let mut sink = IoSink::new(&mut self.writer);
+        let result = write_value(&mut sink, value, &self.opts, self.stack.len());
+        sink.check(result)?;
+        self.value_end();
+        Ok(())
+    }
+
+    pub fn string(&mut self, s: &str) -> json::Result<()> {
+        self.value_start()?;
+        let mut sink = IoSink::new(&mut self.writer);
+        let result = write_string(&mut sink, s, &self.opts).map_err(Failure::from);
+        sink.check(result)?;
+        self.value_end();
+        Ok(())
+    }
+
+    /// Writes `n`, which is an error if it is NaN or infinite unless the options say otherwise.
+    pub fn number(&mut self, n: f64) -> json::Result<()> {
+        self.value(&JSONValue::Number(n))
+    }
+
+    pub fn bool(&mut self, b: bool) -> json::Result<()> {
+        self.value(&JSONValue::Bool(b))
+    }
+
+    pub fn null(&mut self) -> json::Result<()> {
+        self.value(&JSONValue::Null)
+    }
+
+    /// The underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Checks that the document is complete, flushes it and returns the underlying writer.
+    pub fn finish(mut self) -> json::Result<W> {
+        if !self.done {
+            return Err(sequence_error(match self.stack.len() {
+                0 => "the document is empty".to_string(),
+                open => format!("{} arrays or objects are still open", open),
+            }));
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn begin(&mut self, object: bool) -> json::Result<()> {
+        self.value_start()?;
+        self.writer.write_all(if object { b"{" } else { b"[" })?;
+        self.stack.push(Frame { object, len: 0, key: false });
+        Ok(())
+    }
+
+    fn end(&mut self, object: bool) -> json::Result<()> {
+        let (name, close) = if object { ("object", b"}") } else { ("array", b"]") };
+        match self.stack.last() {
+            Some(Frame { key: true, .. }) => return Err(sequence_error("expected a value after the key, found the end of the object")),
+            Some(frame) if frame.object == object => {}
+            _ => return Err(sequence_error(format!("there is no open {} to end", name))),
+        }
+        let frame = self.stack.pop().unwrap();
+        if frame.len > 0 {
+            self.newline()?;
+        }
+        self.writer.write_all(close)?;
+        self.value_end();
+        Ok(())
+    }
+
+    /// Checks that a value may come next, and writes what goes before it
+    fn value_start(&mut self) -> json::Result<()> {
+        match self.stack.last_mut() {
+            None if self.done => Err(sequence_error("the document already has its value")),
+            None => Ok(()),
+            Some(Frame { object: true, key: false, .. }) => Err(sequence_error("expected a key before each value in an object")),
+            Some(Frame { object: true, .. }) => Ok(()),
+            Some(Frame { object: false, .. }) => self.element_start(),
+        }
+    }
+
+    /// Records that a value was written
+    fn value_end(&mut self) {
+        match self.stack.last_mut() {
+            None => self.done = true,
+            Some(frame) => frame.key = false,
+        }
+    }
+
+    /// Writes the separator and indentation before the next element or key of the innermost
+    /// container
+    fn element_start(&mut self) -> json::Result<()> {
+        let frame = self.stack.last_mut().unwrap();
+        frame.len += 1;
+        if frame.len > 1 {
+            self.writer.write_all(b",")?;
+        }
+        self.newline()
+    }
+
+    /// Starts a new line indented for the innermost open container's nesting level
+    fn newline(&mut self) -> json::Result<()> {
+        self.writer.write_all(self.opts.newline.as_str().as_bytes())?;
+        let mut sink = IoSink::new(&mut self.writer);
+        let result = indent(&mut sink, &self.opts, self.stack.len()).map_err(Failure::from);
+        sink.check(result)
+    }
+}
+
+fn sequence_error(message: impl Into<String>) -> JSONError {
+    JSONError::ValueError(message.into())
+}